@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::str::Chars;
+
+use nanoserde::{DeJson, DeJsonErr, DeJsonState, DeJsonTok};
+
+/// The API dump as published by Roblox, kept unmodified.
+const API_DUMP: &str = "api/Full-API-Dump.json";
+/// Members and enum items the engine adds to the API dump or declares differently, in the format of the dump.
+/// Members replace the member of the same name of their class, classes and enums missing from the dump are added.
+const ENGINE_EXTENSIONS: &str = "api/Engine-Extensions.json";
+
+#[derive(DeJson)]
+struct ApiDump {
+    #[nserde(rename = "Classes")]
+    classes: Vec<ApiClass>,
+    #[nserde(rename = "Enums")]
+    enums: Vec<ApiEnum>
+}
+
+#[derive(DeJson)]
+struct ApiExtensions {
+    #[nserde(rename = "Classes")]
+    classes: Vec<ApiClassExtension>,
+    #[nserde(rename = "Enums")]
+    enums: Vec<ApiEnum>
+}
+
+/// A class of the engine extensions, the superclass is only needed by classes missing from the dump.
+#[derive(DeJson)]
+struct ApiClassExtension {
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Superclass")]
+    superclass: Option<String>,
+    #[nserde(rename = "Tags")]
+    tags: Option<Vec<ApiTag>>,
+    #[nserde(rename = "Members")]
+    members: Vec<ApiMember>
+}
+
+#[derive(DeJson)]
+struct ApiClass {
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Superclass")]
+    superclass: String,
+    #[nserde(rename = "Tags")]
+    tags: Option<Vec<ApiTag>>,
+    #[nserde(rename = "Members")]
+    members: Vec<ApiMember>
+}
+
+#[derive(DeJson)]
+struct ApiMember {
+    #[nserde(rename = "MemberType")]
+    member_type: String,
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Security")]
+    security: ApiSecurity,
+    #[nserde(rename = "ThreadSafety")]
+    thread_safety: Option<String>,
+    #[nserde(rename = "Tags")]
+    tags: Option<Vec<ApiTag>>,
+    // Properties
+    #[nserde(rename = "ValueType")]
+    value_type: Option<ApiType>,
+    #[nserde(rename = "Default")]
+    default: Option<String>,
+    // Functions, events and callbacks
+    #[nserde(rename = "Parameters")]
+    parameters: Option<Vec<ApiParameter>>,
+    #[nserde(rename = "ReturnType")]
+    return_type: Option<ApiType>
+}
+
+#[derive(DeJson)]
+struct ApiParameter {
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Type")]
+    ty: ApiType,
+    #[nserde(rename = "Default")]
+    default: Option<String>
+}
+
+#[derive(DeJson)]
+struct ApiType {
+    #[nserde(rename = "Category")]
+    category: String,
+    #[nserde(rename = "Name")]
+    name: String
+}
+
+#[derive(DeJson)]
+struct ApiEnum {
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Items")]
+    items: Vec<ApiEnumItem>
+}
+
+#[derive(DeJson)]
+struct ApiEnumItem {
+    #[nserde(rename = "Name")]
+    name: String,
+    #[nserde(rename = "Value")]
+    value: i64
+}
+
+/// Properties have a read and a write security, the other members a single one.
+struct ApiSecurity {
+    read: String,
+    write: String
+}
+
+/// Tags are strings, except for a few objects like `{"PreferredDescriptorName": ...}`, which are skipped.
+struct ApiTag(Option<String>);
+
+impl DeJson for ApiSecurity {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self, DeJsonErr> {
+        if s.tok == DeJsonTok::Str {
+            let security = String::de_json(s, i)?;
+            return Ok(ApiSecurity { read: security.clone(), write: security });
+        }
+        let mut security = HashMap::<String, String>::de_json(s, i)?;
+        Ok(ApiSecurity {
+            read: security.remove("Read").ok_or_else(|| s.err_nf("Read"))?,
+            write: security.remove("Write").ok_or_else(|| s.err_nf("Write"))?
+        })
+    }
+}
+
+impl DeJson for ApiTag {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self, DeJsonErr> {
+        if s.tok == DeJsonTok::Str {
+            return String::de_json(s, i).map(|x| ApiTag(Some(x)));
+        }
+        s.whole_field(i)?;
+        Ok(ApiTag(None))
+    }
+}
+
+fn security_level(security: &str) -> &'static str {
+    match security {
+        "None" => "SecurityLevel::None",
+        "PluginSecurity" => "SecurityLevel::PluginSecurity",
+        "LocalUserSecurity" => "SecurityLevel::LocalUserSecurity",
+        "RobloxScriptSecurity" => "SecurityLevel::RobloxScriptSecurity",
+        "RobloxSecurity" => "SecurityLevel::RobloxSecurity",
+        "NotAccessibleSecurity" => "SecurityLevel::NotAccessibleSecurity",
+        _ => panic!("{API_DUMP}: unknown security level {security:?}")
+    }
+}
+
+/// Members without a ThreadSafety are treated as unsafe, like the members of old dumps.
+fn thread_safety(safety: &Option<String>) -> &'static str {
+    match safety.as_deref() {
+        None | Some("Unsafe") => "ThreadSafety::Unsafe",
+        Some("ReadSafe") => "ThreadSafety::ReadSafe",
+        Some("Safe") => "ThreadSafety::Safe",
+        Some(safety) => panic!("{API_DUMP}: unknown thread safety {safety:?}")
+    }
+}
+
+fn tags(tags: &Option<Vec<ApiTag>>) -> String {
+    let tags: Vec<String> = tags.iter()
+        .flatten()
+        .filter_map(|x| x.0.as_ref())
+        .map(|x| format!("{x:?}"))
+        .collect();
+    format!("&[{}]", tags.join(", "))
+}
+
+fn value_type(ty: &ApiType) -> String {
+    format!("ValueType {{ category: {:?}, name: {:?} }}", ty.category, ty.name)
+}
+
+fn parameters(parameters: &Option<Vec<ApiParameter>>) -> String {
+    let parameters: Vec<String> = parameters.iter()
+        .flatten()
+        .map(|x| format!("ParameterDescriptor {{ name: {:?}, value_type: {}, default: {:?} }}",
+            x.name, value_type(&x.ty), x.default))
+        .collect();
+    format!("&[{}]", parameters.join(", "))
+}
+
+fn write_class(out: &mut String, class: &ApiClass) {
+    let mut members: Vec<&ApiMember> = class.members.iter().collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    let superclass = Some(&class.superclass).filter(|x| *x != "<<<ROOT>>>");
+    writeln!(out, "    ClassDescriptor {{").unwrap();
+    writeln!(out, "        name: {:?},", class.name).unwrap();
+    writeln!(out, "        superclass: {superclass:?},").unwrap();
+    writeln!(out, "        tags: {},", tags(&class.tags)).unwrap();
+    writeln!(out, "        properties: &[").unwrap();
+    for member in members.iter().filter(|x| x.member_type == "Property") {
+        let ty = member.value_type.as_ref().unwrap_or_else(|| panic!("{API_DUMP}: {}.{} has no ValueType", class.name, member.name));
+        writeln!(out, "            PropertyDescriptor {{ name: {:?}, value_type: {}, default: {:?}, read_security: {}, write_security: {}, thread_safety: {}, tags: {} }},",
+            member.name, value_type(ty), member.default,
+            security_level(&member.security.read), security_level(&member.security.write), thread_safety(&member.thread_safety), tags(&member.tags)).unwrap();
+    }
+    writeln!(out, "        ],").unwrap();
+    writeln!(out, "        functions: &[").unwrap();
+    for member in members.iter().filter(|x| x.member_type == "Function" || x.member_type == "Callback") {
+        let ty = member.return_type.as_ref().unwrap_or_else(|| panic!("{API_DUMP}: {}.{} has no ReturnType", class.name, member.name));
+        writeln!(out, "            FunctionDescriptor {{ name: {:?}, parameters: {}, return_type: {}, security: {}, thread_safety: {}, tags: {}, callback: {} }},",
+            member.name, parameters(&member.parameters), value_type(ty),
+            security_level(&member.security.read), thread_safety(&member.thread_safety), tags(&member.tags), member.member_type == "Callback").unwrap();
+    }
+    writeln!(out, "        ],").unwrap();
+    writeln!(out, "        events: &[").unwrap();
+    for member in members.iter().filter(|x| x.member_type == "Event") {
+        writeln!(out, "            EventDescriptor {{ name: {:?}, parameters: {}, security: {}, tags: {} }},",
+            member.name, parameters(&member.parameters), security_level(&member.security.read), tags(&member.tags)).unwrap();
+    }
+    writeln!(out, "        ]").unwrap();
+    writeln!(out, "    }},").unwrap();
+}
+
+/// Applies the engine extensions to the dump.
+fn merge_extensions(dump: &mut ApiDump, extensions: ApiExtensions) {
+    for extension in extensions.classes {
+        let class = match dump.classes.iter_mut().position(|x| x.name == extension.name) {
+            Some(index) => &mut dump.classes[index],
+            None => {
+                let superclass = extension.superclass.unwrap_or_else(|| panic!("{ENGINE_EXTENSIONS}: class {} is not in the dump and has no Superclass", extension.name));
+                dump.classes.push(ApiClass { name: extension.name, superclass, tags: None, members: Vec::new() });
+                dump.classes.last_mut().unwrap()
+            }
+        };
+        if extension.tags.is_some() {
+            class.tags = extension.tags;
+        }
+        for member in extension.members {
+            class.members.retain(|x| x.name != member.name);
+            class.members.push(member);
+        }
+    }
+    for extension in extensions.enums {
+        let api_enum = match dump.enums.iter_mut().position(|x| x.name == extension.name) {
+            Some(index) => &mut dump.enums[index],
+            None => {
+                dump.enums.push(ApiEnum { name: extension.name, items: Vec::new() });
+                dump.enums.last_mut().unwrap()
+            }
+        };
+        for item in extension.items {
+            api_enum.items.retain(|x| x.name != item.name);
+            api_enum.items.push(item);
+        }
+    }
+}
+
+fn write_enum(out: &mut String, api_enum: &ApiEnum) {
+    let items: Vec<String> = api_enum.items.iter()
+        .map(|x| format!("EnumItemDescriptor {{ name: {:?}, value: {} }}", x.name, x.value))
+        .collect();
+    writeln!(out, "    EnumDescriptor {{ name: {:?}, items: &[{}] }},", api_enum.name, items.join(", ")).unwrap();
+}
+
+/// Generates the tables of `core::class_database` from the Roblox API dump and the engine extensions,
+/// sorted by name for binary searches.
+fn main() {
+    println!("cargo:rerun-if-changed={API_DUMP}");
+    println!("cargo:rerun-if-changed={ENGINE_EXTENSIONS}");
+    let json = fs::read_to_string(API_DUMP).unwrap_or_else(|e| panic!("{API_DUMP}: {e}"));
+    let mut dump = ApiDump::deserialize_json(&json).unwrap_or_else(|e| panic!("{API_DUMP}: {e}"));
+    let json = fs::read_to_string(ENGINE_EXTENSIONS).unwrap_or_else(|e| panic!("{ENGINE_EXTENSIONS}: {e}"));
+    let extensions = ApiExtensions::deserialize_json(&json).unwrap_or_else(|e| panic!("{ENGINE_EXTENSIONS}: {e}"));
+    merge_extensions(&mut dump, extensions);
+    dump.classes.sort_by(|a, b| a.name.cmp(&b.name));
+    dump.enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs from {API_DUMP} and {ENGINE_EXTENSIONS}.").unwrap();
+    writeln!(out, "static CLASSES: &[ClassDescriptor] = &[").unwrap();
+    for class in dump.classes.iter() {
+        write_class(&mut out, class);
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "static ENUMS: &[EnumDescriptor] = &[").unwrap();
+    for api_enum in dump.enums.iter() {
+        write_enum(&mut out, api_enum);
+    }
+    writeln!(out, "];").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("class_database.rs");
+    fs::write(path, out).unwrap();
+}
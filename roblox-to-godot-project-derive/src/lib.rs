@@ -1 +1,416 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Fields, GenericArgument, Ident, LitStr, Path, PathArguments, Result, Type};
 
+/// Implements `InheritanceBase`, `IObject`, `IInstance` and the component traits of an instance class,
+/// and its `new` constructor with the `CLASS` it is registered with for `Instance.new`, from the components it is made of.
+///
+/// ```ignore
+/// #[derive(Debug, Instance)]
+/// pub struct Model {
+///     #[component(IInstance)]
+///     instance: RwLock<InstanceComponent>,
+///     #[component(IPVInstance)]
+///     pvinstance: RwLock<PVInstanceComponent>,
+///     #[component(IModel)]
+///     model: RwLock<ModelComponent>
+/// }
+/// ```
+///
+/// The class must be in the class database, which `IsA` looks its superclasses up in.
+///
+/// Class options, in `#[instance(...)]` on the struct:
+/// - `class = "..."`: the class name, the name of the struct by default.
+/// - `lua_get = f`, `lua_set = f`: members of the class itself, looked up before the components.
+///   `f(&self, lua, name: &str)` and `f(&self, lua, name: &str, &value)` return `None` for anything else.
+/// - `get_actor = f`: overrides `IInstance::get_actor` with `f(&self)`.
+/// - `uncloneable`: `Clone` errors, like for services.
+/// - `no_new`: the class writes its own `new`, and its own `CLASS` when it can be created.
+///
+/// Components are `RwLock`s of an `IInstanceComponent`, marked with `#[component]`, or with `#[component(ITrait)]` when
+/// the class implements `ITrait` through its `get_<name>_component` and `get_<name>_component_mut` getters.
+/// The `IInstance` component is required. Properties are looked up from the last component to the `IInstance` one.
+/// - `getter = name`: the getter of `ITrait`, when it isn't named after the trait.
+/// - `manual`: the class implements `ITrait` itself, because the trait has more than the getters.
+///
+/// Other fields are created by `#[instance(new = expr)]` and cloned by `#[instance(clone = f)]`, which is called with
+/// `(&self, lua)` and returns a `LuaResult`.
+///
+/// The generated code refers to the items of roblox-to-godot-project through `crate::`, so it can only be used there.
+#[proc_macro_derive(Instance, attributes(instance, component))]
+pub fn derive_instance(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    Class::parse(&input)
+        .map(|class| class.expand())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Component {
+    field: Ident,
+    ty: Type,
+    interface: Option<Path>,
+    getter: Option<Ident>,
+    manual: bool
+}
+
+struct OtherField {
+    field: Ident,
+    new: Option<Expr>,
+    clone: Option<Expr>
+}
+
+struct Class {
+    ident: Ident,
+    class_name: String,
+    lua_get: Option<Expr>,
+    lua_set: Option<Expr>,
+    get_actor: Option<Expr>,
+    uncloneable: bool,
+    no_new: bool,
+    instance: Ident,
+    components: Vec<Component>,
+    fields: Vec<OtherField>
+}
+
+impl Component {
+    fn parse(field: Ident, ty: &Type, attr: &Attribute) -> Result<Component> {
+        let mut component = Component {
+            field,
+            ty: component_type(ty)?,
+            interface: None,
+            getter: None,
+            manual: false
+        };
+        if let syn::Meta::Path(_) = attr.meta {
+            return Ok(component);
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("getter") {
+                component.getter = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("manual") {
+                component.manual = true;
+            } else if component.interface.is_none() {
+                component.interface = Some(meta.path);
+            } else {
+                return Err(meta.error("unknown component option"));
+            }
+            Ok(())
+        })?;
+        if component.interface.is_none() && (component.getter.is_some() || component.manual) {
+            return Err(Error::new_spanned(attr, "getter and manual need the trait of the component"));
+        }
+        Ok(component)
+    }
+    fn is_instance(&self) -> bool {
+        self.interface.as_ref()
+            .and_then(|x| x.segments.last())
+            .is_some_and(|x| x.ident == "IInstance")
+    }
+    fn getters(&self) -> Option<(Ident, Ident)> {
+        let interface = self.interface.as_ref()?;
+        let getter = self.getter.clone().unwrap_or_else(|| {
+            let name = interface.segments.last().unwrap().ident.to_string();
+            let name = name.strip_prefix('I').unwrap_or(&name);
+            format_ident!("get_{}_component", snake_case(name))
+        });
+        let getter_mut = format_ident!("{}_mut", getter);
+        Some((getter, getter_mut))
+    }
+}
+
+impl Class {
+    fn parse(input: &DeriveInput) -> Result<Class> {
+        let Data::Struct(data) = &input.data else {
+            return Err(Error::new_spanned(input, "instance classes must be structs"));
+        };
+        let Fields::Named(fields) = &data.fields else {
+            return Err(Error::new_spanned(input, "instance classes must have named fields"));
+        };
+        let mut class = Class {
+            ident: input.ident.clone(),
+            class_name: input.ident.to_string(),
+            lua_get: None,
+            lua_set: None,
+            get_actor: None,
+            uncloneable: false,
+            no_new: false,
+            instance: input.ident.clone(),
+            components: Vec::new(),
+            fields: Vec::new()
+        };
+        for attr in input.attrs.iter().filter(|x| x.path().is_ident("instance")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("class") {
+                    class.class_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("lua_get") {
+                    class.lua_get = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("lua_set") {
+                    class.lua_set = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("get_actor") {
+                    class.get_actor = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("uncloneable") {
+                    class.uncloneable = true;
+                    Ok(())
+                } else if meta.path.is_ident("no_new") {
+                    class.no_new = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown instance option"))
+                }
+            })?;
+        }
+        let mut instance = None;
+        for field in fields.named.iter() {
+            let ident = field.ident.clone().unwrap();
+            if let Some(attr) = field.attrs.iter().find(|x| x.path().is_ident("component")) {
+                let component = Component::parse(ident, &field.ty, attr)?;
+                if component.is_instance() {
+                    instance = Some(component.field.clone());
+                }
+                class.components.push(component);
+                continue;
+            }
+            let mut other = OtherField { field: ident, new: None, clone: None };
+            for attr in field.attrs.iter().filter(|x| x.path().is_ident("instance")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("new") {
+                        other.new = Some(meta.value()?.parse()?);
+                    } else if meta.path.is_ident("clone") {
+                        other.clone = Some(meta.value()?.parse()?);
+                    } else {
+                        return Err(meta.error("unknown field option"));
+                    }
+                    Ok(())
+                })?;
+            }
+            if other.new.is_none() && !class.no_new {
+                return Err(Error::new_spanned(field, "needs #[instance(new = ...)], or the class needs #[instance(no_new)]"));
+            }
+            if other.clone.is_none() && !class.uncloneable {
+                return Err(Error::new_spanned(field, "needs #[instance(clone = ...)], or the class needs #[instance(uncloneable)]"));
+            }
+            class.fields.push(other);
+        }
+        class.instance = instance
+            .ok_or_else(|| Error::new_spanned(&input.ident, "instance classes need an #[component(IInstance)] field"))?;
+        Ok(class)
+    }
+
+    fn expand(&self) -> TokenStream2 {
+        let mut output = self.expand_inheritance();
+        output.extend(self.expand_object());
+        output.extend(self.expand_instance());
+        output.extend(self.expand_components());
+        if !self.no_new {
+            output.extend(self.expand_new());
+        }
+        output
+    }
+
+    fn expand_inheritance(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let interfaces = self.components.iter().filter_map(|x| x.interface.as_ref());
+        quote! {
+            impl crate::core::InheritanceBase for #ident {
+                fn inheritance_table(&self) -> crate::core::InheritanceTable {
+                    crate::core::InheritanceTableBuilder::new()
+                        .insert_type::<#ident, dyn crate::instance::IObject>(|x| x, |x| x)
+                        #(.insert_type::<#ident, dyn #interfaces>(|x| x, |x| x))*
+                        .insert_type::<#ident, #ident>(|x| x, |x| x)
+                        .output()
+                }
+            }
+        }
+    }
+
+    fn expand_object(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let instance = &self.instance;
+        let class_name = &self.class_name;
+        let lookups = self.lua_get.iter()
+            .map(|f| quote!((#f)(self, lua, &name)))
+            .chain(self.components.iter().rev().filter(|x| !x.is_instance()).map(|x| {
+                let field = &x.field;
+                quote!(crate::instance::IInstanceComponent::lua_get(&mut self.#field.read().unwrap(), self, lua, &name))
+            }));
+        let fallback = quote!(self.#instance.read().unwrap().lua_get(lua, &name));
+        let lua_get = chain(lookups.collect(), fallback);
+        quote! {
+            impl crate::instance::IObject for #ident {
+                fn is_a(&self, class_name: &String) -> bool {
+                    crate::core::CLASS_DATABASE.is_a(#class_name, class_name)
+                }
+                fn lua_get(&self, lua: &::r2g_mlua::prelude::Lua, name: String) -> ::r2g_mlua::prelude::LuaResult<::r2g_mlua::prelude::LuaValue> {
+                    #lua_get
+                }
+                fn get_changed_signal(&self) -> crate::userdata::ManagedRBXScriptSignal {
+                    self.#instance.read().unwrap().changed.clone()
+                }
+                fn get_property_changed_signal(&self, property: String) -> crate::userdata::ManagedRBXScriptSignal {
+                    self.#instance.read().unwrap().get_property_changed_signal(property).unwrap()
+                }
+                fn get_class_name(&self) -> &'static str { #class_name }
+            }
+        }
+    }
+
+    fn expand_instance(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let instance = &self.instance;
+        let lookups = self.lua_set.iter()
+            .map(|f| quote!((#f)(self, lua, &name, &val)))
+            .chain(self.components.iter().rev().filter(|x| !x.is_instance()).map(|x| {
+                let field = &x.field;
+                quote!(crate::instance::IInstanceComponent::lua_set(&mut self.#field.write().unwrap(), self, lua, &name, &val))
+            }));
+        let fallback = quote!(self.#instance.write().unwrap().lua_set(lua, &name, val));
+        let lua_set = chain(lookups.collect(), fallback);
+        let clone_instance = if self.uncloneable {
+            let message = format!("Cannot clone {}.", self.class_name);
+            quote!(Err(::r2g_mlua::prelude::LuaError::RuntimeError(#message.into())))
+        } else {
+            let components = self.components.iter().map(|x| {
+                let field = &x.field;
+                quote!(#field: crate::core::RwLock::new_with_flag_auto(
+                    crate::instance::IInstanceComponent::clone(&self.#field.read().unwrap(), lua, &i)?
+                ))
+            });
+            let fields = self.fields.iter().map(|x| {
+                let field = &x.field;
+                let clone = x.clone.as_ref().unwrap();
+                quote!(#field: (#clone)(self, lua)?)
+            });
+            quote! {
+                Ok(crate::core::Irc::new_cyclic_fallable::<_, ::r2g_mlua::prelude::LuaError>(|x| {
+                    let i = x.cast_to_instance();
+                    Ok(#ident {
+                        #(#components,)*
+                        #(#fields,)*
+                    })
+                })?.cast_from_sized().unwrap())
+            }
+        };
+        let get_actor = self.get_actor.as_ref().map(|f| quote! {
+            fn get_actor(&self) -> ::r2g_mlua::prelude::LuaResult<Option<crate::instance::ManagedInstance>> {
+                (#f)(self)
+            }
+        });
+        quote! {
+            impl crate::instance::IInstance for #ident {
+                fn get_instance_component(&self) -> crate::core::RwLockReadGuard<'_, crate::instance::InstanceComponent> {
+                    self.#instance.read().unwrap()
+                }
+                fn get_instance_component_mut(&self) -> crate::core::RwLockWriteGuard<'_, crate::instance::InstanceComponent> {
+                    self.#instance.write().unwrap()
+                }
+                fn lua_set(&self, lua: &::r2g_mlua::prelude::Lua, name: String, val: ::r2g_mlua::prelude::LuaValue) -> ::r2g_mlua::prelude::LuaResult<()> {
+                    #lua_set
+                }
+                fn clone_instance(&self, lua: &::r2g_mlua::prelude::Lua) -> ::r2g_mlua::prelude::LuaResult<crate::instance::ManagedInstance> {
+                    #clone_instance
+                }
+                #get_actor
+            }
+        }
+    }
+
+    fn expand_components(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        self.components.iter()
+            .filter(|x| !x.manual && !x.is_instance())
+            .filter_map(|x| {
+                let interface = x.interface.as_ref()?;
+                let (getter, getter_mut) = x.getters()?;
+                let field = &x.field;
+                let ty = &x.ty;
+                Some(quote! {
+                    impl #interface for #ident {
+                        fn #getter(&self) -> crate::core::RwLockReadGuard<'_, #ty> {
+                            self.#field.read().unwrap()
+                        }
+                        fn #getter_mut(&self) -> crate::core::RwLockWriteGuard<'_, #ty> {
+                            self.#field.write().unwrap()
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn expand_new(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let class_name = &self.class_name;
+        let components = self.components.iter().map(|x| {
+            let field = &x.field;
+            let ty = &x.ty;
+            quote!(#field: crate::core::RwLock::new_with_flag_auto(
+                <#ty as crate::instance::IInstanceComponent>::new(x.cast_to_instance(), #class_name)
+            ))
+        });
+        let fields = self.fields.iter().map(|x| {
+            let field = &x.field;
+            let new = x.new.as_ref().unwrap();
+            quote!(#field: #new)
+        });
+        quote! {
+            impl #ident {
+                pub const CLASS: crate::instance::InstanceClass = crate::instance::InstanceClass {
+                    name: #class_name,
+                    new: |_| Self::new()
+                };
+                pub fn new() -> crate::instance::ManagedInstance {
+                    crate::core::Irc::new_cyclic(|x| #ident {
+                        #(#components,)*
+                        #(#fields,)*
+                    }).cast_from_sized().unwrap()
+                }
+            }
+        }
+    }
+}
+
+/// `first.or_else(|| second)...unwrap_or_else(|| fallback)`, for lookups returning an `Option`.
+fn chain(lookups: Vec<TokenStream2>, fallback: TokenStream2) -> TokenStream2 {
+    let mut lookups = lookups.into_iter();
+    match lookups.next() {
+        Some(first) => quote!(#first #(.or_else(|| #lookups))* .unwrap_or_else(|| #fallback)),
+        None => fallback
+    }
+}
+
+/// The `T` of a `RwLock<T>` component.
+fn component_type(ty: &Type) -> Result<Type> {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last().filter(|x| x.ident == "RwLock") {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(GenericArgument::Type(ty)) = args.args.first() {
+                    return Ok(ty.clone());
+                }
+            }
+        }
+    }
+    Err(Error::new_spanned(ty, "components must be a RwLock<T>"))
+}
+
+/// `PVInstance` to `pv_instance`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let after_lower = chars[i - 1].is_lowercase();
+            let before_lower = chars.get(i + 1).is_some_and(|x| x.is_lowercase());
+            if after_lower || (before_lower && chars[i - 1].is_uppercase()) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
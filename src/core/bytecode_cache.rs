@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::sync::{Arc, Mutex};
+
+use r2g_mlua::prelude::*;
+use tiny_keccak::{Hasher, Sha3};
+
+/// Compiler configuration a chunk was compiled with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum CompileMode {
+    Debug,
+    Release,
+    /// Release bytecode loaded with native code generation enabled.
+    Native
+}
+
+/// Identifies a source by its length and SHA3-256 hash, so distinct sources can't share cached bytecode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+struct SourceKey {
+    len: u64,
+    hash: [u8; 32]
+}
+
+type BytecodeKey = (SourceKey, CompileMode);
+
+#[derive(Debug)]
+struct CacheEntry {
+    bytecode: Arc<[u8]>,
+    last_used: u64
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    map: HashMap<BytecodeKey, CacheEntry>,
+    /// Total size of the cached bytecode, in bytes.
+    size: usize,
+    tick: u64
+}
+
+/// VM-wide cache of compiled bytecode, keyed by the source and the compile mode.
+/// It is shared by every LuauState of a VM, so it is internally synchronized.
+/// Once the bytecode outgrows the capacity, the least recently used entries are evicted.
+#[derive(Debug)]
+pub struct BytecodeCache {
+    entries: Mutex<CacheEntries>,
+    capacity: AtomicUsize,
+    hits: AtomicUsize,
+    misses: AtomicUsize
+}
+
+impl Default for BytecodeCache {
+    fn default() -> Self {
+        BytecodeCache {
+            entries: Mutex::default(),
+            capacity: AtomicUsize::new(DEFAULT_CAPACITY),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0)
+        }
+    }
+}
+
+/// Default capacity of a cache, in bytes of bytecode.
+const DEFAULT_CAPACITY: usize = 64 * 1024 * 1024;
+
+const CACHE_FILE_MAGIC: &[u8; 8] = b"R2GLBC\0\0";
+/// Bump when the file layout or the compiler options change, older files are then ignored.
+const CACHE_FILE_VERSION: u32 = 3;
+
+/// The Luau release the bytecode was compiled by, files from another release are ignored and their sources
+/// recompiled.
+fn luau_version() -> &'static str {
+    r2g_mlua::ffi::luau_version().unwrap_or("unknown")
+}
+
+fn sha3(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha3::v256();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+fn source_key(source: &str) -> SourceKey {
+    SourceKey { len: source.len() as u64, hash: sha3(&[source.as_bytes()]) }
+}
+
+/// Checksum of a saved entry. It covers the key too, so bytecode can't be moved to another source's entry.
+fn checksum_entry((source, mode): BytecodeKey, bytecode: &[u8]) -> [u8; 32] {
+    sha3(&[&source.len.to_le_bytes(), &source.hash, &[mode.to_byte()], bytecode])
+}
+
+impl CacheEntries {
+    /// Inserts an entry, evicting the least recently used ones until the cache fits in `capacity`.
+    /// Bytecode larger than the whole capacity isn't cached.
+    fn insert(&mut self, key: BytecodeKey, bytecode: Arc<[u8]>, capacity: usize) {
+        if bytecode.len() > capacity {
+            return;
+        }
+        if let Some(old) = self.map.remove(&key) {
+            self.size -= old.bytecode.len();
+        }
+        while self.size + bytecode.len() > capacity {
+            self.evict_least_recently_used();
+        }
+        self.tick += 1;
+        self.size += bytecode.len();
+        self.map.insert(key, CacheEntry { bytecode, last_used: self.tick });
+    }
+    fn get(&mut self, key: &BytecodeKey) -> Option<Arc<[u8]>> {
+        self.tick += 1;
+        let entry = self.map.get_mut(key)?;
+        entry.last_used = self.tick;
+        Some(entry.bytecode.clone())
+    }
+    fn remove(&mut self, key: &BytecodeKey) {
+        if let Some(old) = self.map.remove(key) {
+            self.size -= old.bytecode.len();
+        }
+    }
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self.map.iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.remove(&key);
+        }
+    }
+    fn shrink_to(&mut self, capacity: usize) {
+        while self.size > capacity {
+            self.evict_least_recently_used();
+        }
+    }
+}
+
+impl CompileMode {
+    const fn to_byte(self) -> u8 {
+        match self {
+            CompileMode::Debug => 0,
+            CompileMode::Release => 1,
+            CompileMode::Native => 2
+        }
+    }
+    const fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(CompileMode::Debug),
+            1 => Some(CompileMode::Release),
+            2 => Some(CompileMode::Native),
+            _ => None
+        }
+    }
+}
+
+impl BytecodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Gets the bytecode of `source`, calling `compile` only if it isn't cached yet.
+    /// Failed compilations are not cached.
+    pub fn get_or_compile(&self, mode: CompileMode, source: &str, compile: impl FnOnce() -> LuaResult<Vec<u8>>) -> LuaResult<Arc<[u8]>> {
+        let key = (source_key(source), mode);
+        if let Some(bytecode) = self.entries.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Relaxed);
+            return Ok(bytecode);
+        }
+        self.misses.fetch_add(1, Relaxed);
+        // Compiling is done without the lock, so states compiling different sources don't wait on each other.
+        let bytecode: Arc<[u8]> = compile()?.into();
+        self.entries.lock().unwrap().insert(key, bytecode.clone(), self.capacity());
+        Ok(bytecode)
+    }
+    /// Removes the entry of `source`, used when cached bytecode fails to load.
+    pub fn invalidate(&self, mode: CompileMode, source: &str) {
+        self.entries.lock().unwrap().remove(&(source_key(source), mode));
+    }
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.map.clear();
+        entries.size = 0;
+    }
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Total size of the cached bytecode, in bytes.
+    pub fn size(&self) -> usize {
+        self.entries.lock().unwrap().size
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Relaxed)
+    }
+    /// Sets how many bytes of bytecode the cache holds, evicting the least recently used entries which don't fit.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Relaxed);
+        self.entries.lock().unwrap().shrink_to(capacity);
+    }
+    /// Returns how many lookups were served from the cache and how many had to compile.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Relaxed), self.misses.load(Relaxed))
+    }
+
+    /// Writes every entry to `path`, replacing it. The entries are written to a temporary file next to it first,
+    /// so a failed save leaves the previous file intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temp_name = path.file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the cache path has no file name"))?
+            .to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+        let result = self.write_file(&temp_path)
+            .and_then(|_| std::fs::rename(&temp_path, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+    fn write_file(&self, path: &Path) -> io::Result<()> {
+        let entries = self.entries.lock().unwrap();
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        file.write_all(CACHE_FILE_MAGIC)?;
+        file.write_all(&CACHE_FILE_VERSION.to_le_bytes())?;
+        let luau_version = luau_version().as_bytes();
+        file.write_all(&(luau_version.len() as u32).to_le_bytes())?;
+        file.write_all(luau_version)?;
+        file.write_all(&(entries.map.len() as u64).to_le_bytes())?;
+        for (&(source, mode), entry) in entries.map.iter() {
+            file.write_all(&source.len.to_le_bytes())?;
+            file.write_all(&source.hash)?;
+            file.write_all(&[mode.to_byte()])?;
+            file.write_all(&(entry.bytecode.len() as u64).to_le_bytes())?;
+            file.write_all(&checksum_entry((source, mode), &entry.bytecode))?;
+            file.write_all(&entry.bytecode)?;
+        }
+        file.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()
+    }
+    /// Loads the entries saved at `path`, returning how many were read.
+    /// Files from an incompatible version or another Luau release are ignored, and so are entries whose checksum
+    /// doesn't match, their sources are compiled again when they're needed. Entries are evicted like compiled ones
+    /// when the file holds more than the capacity.
+    pub fn load(&self, path: &Path) -> io::Result<usize> {
+        fn read_array<const N: usize>(file: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut buf = [0u8; N];
+            file.read_exact(&mut buf)?;
+            Ok(buf)
+        }
+        let mut file = io::BufReader::new(std::fs::File::open(path)?);
+        if &read_array::<8>(&mut file)? != CACHE_FILE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a bytecode cache file"));
+        }
+        if u32::from_le_bytes(read_array(&mut file)?) != CACHE_FILE_VERSION {
+            return Ok(0);
+        }
+        let luau_version_len = u32::from_le_bytes(read_array(&mut file)?) as u64;
+        let mut saved_luau_version = Vec::new();
+        (&mut file).take(luau_version_len).read_to_end(&mut saved_luau_version)?;
+        if saved_luau_version != luau_version().as_bytes() {
+            return Ok(0);
+        }
+        let count = u64::from_le_bytes(read_array(&mut file)?);
+        let mut loaded = Vec::new();
+        for _ in 0..count {
+            let source = SourceKey {
+                len: u64::from_le_bytes(read_array(&mut file)?),
+                hash: read_array(&mut file)?
+            };
+            let mode = CompileMode::from_byte(read_array::<1>(&mut file)?[0])
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid compile mode"))?;
+            let len = u64::from_le_bytes(read_array(&mut file)?);
+            let checksum: [u8; 32] = read_array(&mut file)?;
+            let mut bytecode = Vec::new();
+            (&mut file).take(len).read_to_end(&mut bytecode)?;
+            if bytecode.len() as u64 != len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if checksum_entry((source, mode), &bytecode) != checksum {
+                continue;
+            }
+            loaded.push(((source, mode), bytecode));
+        }
+        let capacity = self.capacity();
+        let mut entries = self.entries.lock().unwrap();
+        let count = loaded.len();
+        for (key, bytecode) in loaded {
+            entries.insert(key, bytecode.into(), capacity);
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,243 @@
+use super::SecurityContext;
+
+/// Security a member needs to be accessed with, from the least to the most privileged.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum SecurityLevel {
+    None,
+    PluginSecurity,
+    LocalUserSecurity,
+    RobloxScriptSecurity,
+    RobloxSecurity,
+    /// Not accessible from Lua at all.
+    NotAccessibleSecurity
+}
+
+impl SecurityLevel {
+    /// The context a thread needs for the level, `None` for NotAccessibleSecurity which no thread has.
+    pub const fn get_security_context(self) -> Option<SecurityContext> {
+        match self {
+            Self::None => Some(SecurityContext::NONE),
+            Self::PluginSecurity => Some(SecurityContext::PLUGIN),
+            Self::LocalUserSecurity => Some(SecurityContext::LOCAL_USER),
+            Self::RobloxScriptSecurity => Some(SecurityContext::ROBLOX_SCRIPT),
+            Self::RobloxSecurity => Some(SecurityContext::ROBLOX),
+            Self::NotAccessibleSecurity => None
+        }
+    }
+    /// Whether a thread with `contexts` can access members of this level.
+    pub const fn is_accessible_with(self, contexts: SecurityContext) -> bool {
+        match self.get_security_context() {
+            Some(context) => contexts.has(context),
+            None => false
+        }
+    }
+    /// The name of the level in "lacking capability" errors.
+    pub const fn get_capability_name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::PluginSecurity => "Plugin",
+            Self::LocalUserSecurity => "LocalUser",
+            Self::RobloxScriptSecurity => "RobloxScript",
+            Self::RobloxSecurity => "Roblox",
+            Self::NotAccessibleSecurity => "NotAccessible"
+        }
+    }
+}
+
+/// Whether a member can be used while desynchronized. Properties which are ReadSafe can be read but not written.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ThreadSafety {
+    Unsafe,
+    ReadSafe,
+    Safe
+}
+
+/// Type of a property, parameter or return value, e.g. `Primitive` `bool` or `Class` `Instance`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ValueType {
+    pub category: &'static str,
+    pub name: &'static str
+}
+
+#[derive(Debug)]
+pub struct ParameterDescriptor {
+    pub name: &'static str,
+    pub value_type: ValueType,
+    pub default: Option<&'static str>
+}
+
+#[derive(Debug)]
+pub struct PropertyDescriptor {
+    pub name: &'static str,
+    pub value_type: ValueType,
+    pub default: Option<&'static str>,
+    pub read_security: SecurityLevel,
+    pub write_security: SecurityLevel,
+    pub thread_safety: ThreadSafety,
+    pub tags: &'static [&'static str]
+}
+
+/// A method, or a callback when `callback` is set.
+#[derive(Debug)]
+pub struct FunctionDescriptor {
+    pub name: &'static str,
+    pub parameters: &'static [ParameterDescriptor],
+    pub return_type: ValueType,
+    pub security: SecurityLevel,
+    pub thread_safety: ThreadSafety,
+    pub tags: &'static [&'static str],
+    pub callback: bool
+}
+
+#[derive(Debug)]
+pub struct EventDescriptor {
+    pub name: &'static str,
+    pub parameters: &'static [ParameterDescriptor],
+    pub security: SecurityLevel,
+    pub tags: &'static [&'static str]
+}
+
+/// A class, with the members it declares itself. Members are sorted by name.
+#[derive(Debug)]
+pub struct ClassDescriptor {
+    pub name: &'static str,
+    pub superclass: Option<&'static str>,
+    pub tags: &'static [&'static str],
+    pub properties: &'static [PropertyDescriptor],
+    pub functions: &'static [FunctionDescriptor],
+    pub events: &'static [EventDescriptor]
+}
+
+#[derive(Debug)]
+pub struct EnumItemDescriptor {
+    pub name: &'static str,
+    pub value: i64
+}
+
+#[derive(Debug)]
+pub struct EnumDescriptor {
+    pub name: &'static str,
+    pub items: &'static [EnumItemDescriptor]
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum MemberDescriptor {
+    Property(&'static PropertyDescriptor),
+    Function(&'static FunctionDescriptor),
+    Event(&'static EventDescriptor)
+}
+
+/// Classes and enums of the Roblox API, generated at compile time from `api/Full-API-Dump.json` and the members the
+/// engine adds or declares differently in `api/Engine-Extensions.json`.
+/// Classes and enums are sorted by name.
+#[derive(Debug)]
+pub struct ClassDatabase {
+    classes: &'static [ClassDescriptor],
+    enums: &'static [EnumDescriptor]
+}
+
+include!(concat!(env!("OUT_DIR"), "/class_database.rs"));
+
+pub static CLASS_DATABASE: ClassDatabase = ClassDatabase { classes: CLASSES, enums: ENUMS };
+
+fn find_by_name<T>(items: &'static [T], name: &str, key: impl Fn(&T) -> &'static str) -> Option<&'static T> {
+    items.binary_search_by(|x| key(x).cmp(name)).ok().map(|i| &items[i])
+}
+
+impl ClassDatabase {
+    pub fn classes(&self) -> &'static [ClassDescriptor] {
+        self.classes
+    }
+    pub fn enums(&self) -> &'static [EnumDescriptor] {
+        self.enums
+    }
+    pub fn get_class(&self, name: &str) -> Option<&'static ClassDescriptor> {
+        find_by_name(self.classes, name, |x| x.name)
+    }
+    pub fn get_enum(&self, name: &str) -> Option<&'static EnumDescriptor> {
+        find_by_name(self.enums, name, |x| x.name)
+    }
+    /// The class followed by its superclasses, up to Instance.
+    pub fn get_inheritance_chain(&self, name: &str) -> impl Iterator<Item = &'static ClassDescriptor> + use<'_> {
+        let mut class = self.get_class(name);
+        std::iter::from_fn(move || {
+            let current = class?;
+            class = current.superclass.and_then(|x| self.get_class(x));
+            Some(current)
+        })
+    }
+    /// Whether `class_name` is `other` or inherits from it. Every class is an Object.
+    pub fn is_a(&self, class_name: &str, other: &str) -> bool {
+        other == "Object" || self.get_inheritance_chain(class_name).any(|x| x.name == other)
+    }
+    /// A property, method or event of `class_name`, including the inherited ones.
+    pub fn find_member(&self, class_name: &str, member: &str) -> Option<MemberDescriptor> {
+        self.get_inheritance_chain(class_name).find_map(|x| x.get_member(member))
+    }
+    pub fn find_property(&self, class_name: &str, property: &str) -> Option<&'static PropertyDescriptor> {
+        self.get_inheritance_chain(class_name).find_map(|x| x.get_property(property))
+    }
+    pub fn find_function(&self, class_name: &str, function: &str) -> Option<&'static FunctionDescriptor> {
+        self.get_inheritance_chain(class_name).find_map(|x| x.get_function(function))
+    }
+    pub fn find_event(&self, class_name: &str, event: &str) -> Option<&'static EventDescriptor> {
+        self.get_inheritance_chain(class_name).find_map(|x| x.get_event(event))
+    }
+    /// The security needed to read a member of `class_name`, or to write it.
+    /// `None` when the database doesn't declare the member, which then can't be accessed.
+    pub fn get_member_security(&self, class_name: &str, member: &str, write: bool) -> Option<SecurityLevel> {
+        match self.find_member(class_name, member)? {
+            MemberDescriptor::Property(x) if write => Some(x.write_security),
+            MemberDescriptor::Property(x) => Some(x.read_security),
+            MemberDescriptor::Function(x) => Some(x.security),
+            MemberDescriptor::Event(x) => Some(x.security)
+        }
+    }
+}
+
+impl ClassDescriptor {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag)
+    }
+    /// Whether `Instance.new` can create the class.
+    pub fn is_creatable(&self) -> bool {
+        !self.has_tag("NotCreatable")
+    }
+    pub fn is_service(&self) -> bool {
+        self.has_tag("Service")
+    }
+    pub fn get_property(&self, name: &str) -> Option<&'static PropertyDescriptor> {
+        find_by_name(self.properties, name, |x| x.name)
+    }
+    pub fn get_function(&self, name: &str) -> Option<&'static FunctionDescriptor> {
+        find_by_name(self.functions, name, |x| x.name)
+    }
+    pub fn get_event(&self, name: &str) -> Option<&'static EventDescriptor> {
+        find_by_name(self.events, name, |x| x.name)
+    }
+    /// A member declared by the class itself.
+    pub fn get_member(&self, name: &str) -> Option<MemberDescriptor> {
+        self.get_property(name).map(MemberDescriptor::Property)
+            .or_else(|| self.get_function(name).map(MemberDescriptor::Function))
+            .or_else(|| self.get_event(name).map(MemberDescriptor::Event))
+    }
+}
+
+impl PropertyDescriptor {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag)
+    }
+    pub fn is_read_only(&self) -> bool {
+        self.has_tag("ReadOnly")
+    }
+    /// Hidden from Lua, like properties only used for serialization.
+    pub fn is_scriptable(&self) -> bool {
+        !self.has_tag("NotScriptable")
+    }
+}
+
+impl EnumDescriptor {
+    pub fn get_item(&self, name: &str) -> Option<&'static EnumItemDescriptor> {
+        self.items.iter().find(|x| x.name == name)
+    }
+}
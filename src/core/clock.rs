@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::time::{Duration, Instant};
+
+/// Time source of a RobloxVM, in seconds. Used by the task scheduler, `time()` and `os.clock()`.
+/// The watchdogs always use wall time.
+#[derive(Debug)]
+pub enum Clock {
+    /// Wall time elapsed since the clock was created.
+    RealTime(Instant),
+    /// Time which only moves when advanced by the host, stored as the bits of an `f64`.
+    Virtual(AtomicU64)
+}
+
+impl Clock {
+    pub fn new_real_time() -> Clock {
+        Clock::RealTime(Instant::now())
+    }
+    /// Real-time clock reading `start` right now, used to switch clocks without time going backwards.
+    pub fn new_real_time_at(start: f64) -> Clock {
+        let now = Instant::now();
+        Clock::RealTime(now.checked_sub(Duration::from_secs_f64(start.max(0.0))).unwrap_or(now))
+    }
+    pub fn new_virtual(start: f64) -> Clock {
+        Clock::Virtual(AtomicU64::new(start.to_bits()))
+    }
+    #[inline]
+    pub fn now(&self) -> f64 {
+        match self {
+            Clock::RealTime(started) => started.elapsed().as_secs_f64(),
+            Clock::Virtual(time) => f64::from_bits(time.load(Relaxed))
+        }
+    }
+    #[inline]
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Clock::Virtual(_))
+    }
+    /// Moves a virtual clock forward by `seconds`, returns false for a real-time clock.
+    pub fn advance(&self, seconds: f64) -> bool {
+        match self {
+            Clock::RealTime(_) => false,
+            Clock::Virtual(time) => {
+                let _ = time.fetch_update(Relaxed, Relaxed, |x| Some((f64::from_bits(x) + seconds.max(0.0)).to_bits()));
+                true
+            }
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new_real_time()
+    }
+}
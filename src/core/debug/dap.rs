@@ -0,0 +1,106 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::json::JsonValue;
+
+/// How the debug adapter talks to its client.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DebugTransport {
+    /// Listens on `127.0.0.1:<port>`, accepting one client at a time.
+    /// Any local process can connect, so clients must attach with `token` before anything else is served.
+    Tcp { port: u16, token: String },
+    /// Uses the process' standard input and output, trusted as they belong to whoever started the process.
+    Stdio
+}
+
+pub(super) type SharedWriter = Arc<Mutex<Option<Box<dyn Write + Send>>>>;
+
+/// Sent to the VM when the client goes away, so a paused VM doesn't wait forever.
+pub(super) fn disconnect_message() -> JsonValue {
+    JsonValue::object([
+        ("seq", 0.into()),
+        ("type", "request".into()),
+        ("command", "disconnect".into()),
+        ("arguments", JsonValue::object([]))
+    ])
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<JsonValue>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"))?;
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "message is not utf-8"))?;
+    JsonValue::parse(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub(super) fn write_message(writer: &SharedWriter, message: &JsonValue) {
+    let body = message.to_string();
+    let mut writer = writer.lock().unwrap();
+    if let Some(w) = writer.as_mut() {
+        let res = write!(w, "Content-Length: {}\r\n\r\n{}", body.len(), body).and_then(|_| w.flush());
+        if res.is_err() {
+            *writer = None;
+        }
+    }
+}
+
+/// Forwards every message of a client to `sender` until it disconnects.
+fn serve_client(reader: impl Read, sender: &Sender<JsonValue>) {
+    let mut reader = BufReader::new(reader);
+    while let Ok(Some(message)) = read_message(&mut reader) {
+        if sender.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+/// Starts the thread accepting clients on `transport`.
+pub(super) fn start_server(transport: DebugTransport, sender: Sender<JsonValue>, writer: SharedWriter) -> io::Result<()> {
+    match transport {
+        DebugTransport::Tcp { port, .. } => {
+            let listener = TcpListener::bind(("127.0.0.1", port))?;
+            thread::Builder::new().name("r2g-dap".into()).spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { continue };
+                    let Ok(write_half) = stream.try_clone() else { continue };
+                    *writer.lock().unwrap() = Some(Box::new(write_half));
+                    serve_client(stream, &sender);
+                    *writer.lock().unwrap() = None;
+                    if sender.send(disconnect_message()).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        }
+        DebugTransport::Stdio => {
+            *writer.lock().unwrap() = Some(Box::new(io::stdout()));
+            thread::Builder::new().name("r2g-dap".into()).spawn(move || {
+                serve_client(io::stdin(), &sender);
+                *writer.lock().unwrap() = None;
+                let _ = sender.send(disconnect_message());
+            })?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use std::fmt::{self, Display, Write};
+
+/// Minimal JSON value, enough for the Debug Adapter Protocol.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum JsonValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Objects keep their insertion order.
+    Object(Vec<(String, JsonValue)>)
+}
+
+impl JsonValue {
+    pub fn object<const N: usize>(fields: [(&str, JsonValue); N]) -> JsonValue {
+        JsonValue::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+    pub fn get(&self, key: &str) -> &JsonValue {
+        const NULL: JsonValue = JsonValue::Null;
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v).unwrap_or(&NULL),
+            _ => &NULL
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s.as_str()),
+            _ => None
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Number(n) => Some(*n as i64),
+            _ => None
+        }
+    }
+    pub fn as_array(&self) -> &[JsonValue] {
+        match self {
+            JsonValue::Array(values) => values.as_slice(),
+            _ => &[]
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<JsonValue, String> {
+        let mut parser = Parser { source: source.as_bytes(), pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos != parser.source.len() {
+            return Err(format!("unexpected trailing data at {}", parser.pos));
+        }
+        Ok(value)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self { JsonValue::Bool(value) }
+}
+impl From<i64> for JsonValue {
+    fn from(value: i64) -> Self { JsonValue::Number(value as f64) }
+}
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self { JsonValue::String(value.to_owned()) }
+}
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self { JsonValue::String(value) }
+}
+impl From<Vec<JsonValue>> for JsonValue {
+    fn from(value: Vec<JsonValue>) -> Self { JsonValue::Array(value) }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) if n.is_finite() && n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            JsonValue::Number(n) if n.is_finite() => write!(f, "{}", n),
+            JsonValue::Number(_) => f.write_str("null"),
+            JsonValue::String(s) => write_string(f, s),
+            JsonValue::Array(values) => {
+                f.write_char('[')?;
+                for (i, v) in values.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                f.write_char(']')
+            }
+            JsonValue::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// How deeply arrays and objects can nest, so a hostile message can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    source: &'a [u8],
+    pos: usize,
+    depth: usize
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while self.pos < self.source.len() && self.source[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+    fn peek(&mut self) -> Option<u8> {
+        self.whitespace();
+        self.source.get(self.pos).copied()
+    }
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", byte as char, self.pos))
+        }
+    }
+    fn literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, String> {
+        if self.source[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(format!("invalid literal at {}", self.pos))
+        }
+    }
+    fn value(&mut self) -> Result<JsonValue, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("nesting deeper than {} at {}", MAX_DEPTH, self.pos));
+        }
+        self.depth += 1;
+        let value = self.nested_value();
+        self.depth -= 1;
+        value
+    }
+    fn nested_value(&mut self) -> Result<JsonValue, String> {
+        match self.peek().ok_or("unexpected end of input")? {
+            b'{' => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.value()?));
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => { self.pos += 1; return Ok(JsonValue::Object(fields)); }
+                        _ => return Err(format!("expected ',' or '}}' at {}", self.pos))
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => { self.pos += 1; return Ok(JsonValue::Array(values)); }
+                        _ => return Err(format!("expected ',' or ']' at {}", self.pos))
+                    }
+                }
+            }
+            b'"' => Ok(JsonValue::String(self.string()?)),
+            b't' => self.literal("true", JsonValue::Bool(true)),
+            b'f' => self.literal("false", JsonValue::Bool(false)),
+            b'n' => self.literal("null", JsonValue::Null),
+            _ => {
+                let start = self.pos;
+                while self.pos < self.source.len() && matches!(self.source[self.pos], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+                    self.pos += 1;
+                }
+                std::str::from_utf8(&self.source[start..self.pos]).ok()
+                    .and_then(|x| x.parse().ok())
+                    .map(JsonValue::Number)
+                    .ok_or_else(|| format!("invalid number at {}", start))
+            }
+        }
+    }
+    fn string(&mut self) -> Result<String, String> {
+        if self.source.get(self.pos) != Some(&b'"') {
+            return Err(format!("expected string at {}", self.pos));
+        }
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self.source.get(self.pos).ok_or("unterminated string")?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.source.get(self.pos).ok_or("unterminated string")?;
+                    self.pos += 1;
+                    match escape {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(8),
+                        b'f' => bytes.push(12),
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Surrogate pairs are split into two escapes.
+                            if (0xd800..0xdc00).contains(&code) && self.source[self.pos..].starts_with(b"\\u") {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => bytes.push(other)
+                    }
+                }
+                other => bytes.push(other)
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| "invalid utf-8 in string".into())
+    }
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.source.get(self.pos..self.pos + 4).ok_or("unterminated escape")?;
+        self.pos += 4;
+        std::str::from_utf8(digits).ok()
+            .and_then(|x| u32::from_str_radix(x, 16).ok())
+            .ok_or_else(|| format!("invalid escape at {}", self.pos - 4))
+    }
+}
@@ -0,0 +1,818 @@
+//! Debug Adapter Protocol server for the Luau states of a RobloxVM.
+//!
+//! The server thread only forwards messages, every request is handled on the thread running the VM:
+//! either between frames in [`Debugger::poll`], or inside of Luau's debug callbacks while a thread is paused.
+//! Pausing blocks the VM until the client resumes it. Only synchronized code pauses, breakpoints hit by Actors running
+//! in parallel are skipped so the worker threads never wait on the client.
+//!
+//! Clients of the TCP transport must pass the token the server was started with in their `attach` or `launch` request,
+//! every other request is refused until then.
+
+mod dap;
+mod json;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_int, CStr, CString};
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering::Relaxed};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+
+use r2g_mlua::ffi::{self, lua_Debug, lua_State, LUA_REGISTRYINDEX};
+use r2g_mlua::Compiler;
+
+use super::{registry_keys, LuauState, RobloxVM};
+use crate::instance::WeakManagedInstance;
+use dap::{start_server, write_message, SharedWriter};
+
+pub use dap::DebugTransport;
+pub use json::JsonValue;
+
+type InterruptCallback = Option<unsafe extern "C-unwind" fn(*mut lua_State, c_int)>;
+
+#[derive(Debug)]
+struct StateEntry {
+    thread_id: i64,
+    main: usize,
+    /// Registry references of the chunks loaded in this state, by what they were loaded for.
+    chunks: HashMap<ChunkOwner, LoadedChunk>
+}
+
+/// What a chunk was loaded for. Loading another chunk for the same owner releases the previous one.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum ChunkOwner {
+    Script(WeakManagedInstance),
+    /// Code pushed by the host, by chunk name.
+    Code(String)
+}
+
+#[derive(Debug)]
+struct LoadedChunk {
+    /// The script name breakpoints are set by.
+    script: String,
+    r: c_int
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum StepKind {
+    In,
+    Over,
+    Out
+}
+
+/// Luau only checks for single stepping when entering the interpreter, which doesn't happen when resuming from a
+/// breakpoint. Steps instead arm a breakpoint on every line of the state, only stopping once the thread reaches a line
+/// matching the step.
+#[derive(Debug)]
+struct StepRequest {
+    thread: usize,
+    kind: StepKind,
+    depth: c_int,
+    line: c_int
+}
+
+#[derive(Debug, Default)]
+struct ScriptBreakpoints {
+    /// Lines requested by the client.
+    lines: Vec<c_int>,
+    /// Lines the breakpoints were actually placed on, which is the next line with code.
+    resolved: HashSet<c_int>
+}
+
+#[derive(Debug, Default)]
+struct DebuggerInner {
+    states: HashMap<usize, StateEntry>,
+    next_thread_id: i64,
+    breakpoints: HashMap<String, ScriptBreakpoints>,
+    step: Option<StepRequest>,
+    /// Interrupts replaced by [`debug_interrupt`] while a pause is pending, by main thread.
+    saved_interrupts: HashMap<usize, InterruptCallback>,
+    /// Chunk refs of released scripts with their state and its main thread, unreferenced in [`Debugger::poll`].
+    released: Vec<(usize, usize, c_int)>
+}
+
+impl StepRequest {
+    fn should_stop(&self, depth: c_int, line: c_int) -> bool {
+        match self.kind {
+            StepKind::In => depth != self.depth || line != self.line,
+            StepKind::Over => depth < self.depth || (depth == self.depth && line != self.line),
+            StepKind::Out => depth < self.depth
+        }
+    }
+}
+
+impl DebuggerInner {
+    /// Gets the state `l` is a thread of.
+    unsafe fn state_of(&self, l: *mut lua_State) -> Option<&StateEntry> {
+        let main = ffi::lua_mainthread(l) as usize;
+        self.states.values().find(|x| x.main == main)
+    }
+    /// Enables or disables a breakpoint on every line with code of the function at the top of the stack.
+    unsafe fn set_all_lines(l: *mut lua_State, enabled: c_int) {
+        let mut line = 0;
+        loop {
+            let actual = ffi::lua_breakpoint(l, -1, line, enabled);
+            if actual < 0 {
+                break;
+            }
+            line = actual + 1;
+        }
+    }
+    unsafe fn arm_step(&self, l: *mut lua_State) {
+        let Some(entry) = self.state_of(l) else { return };
+        ffi::lua_checkstack(l, 1);
+        for chunk in entry.chunks.values() {
+            ffi::lua_rawgeti(l, LUA_REGISTRYINDEX, chunk.r as _);
+            Self::set_all_lines(l, 1);
+            ffi::lua_pop(l, 1);
+        }
+    }
+    /// Puts back the interrupts replaced by a pending pause, unless something else replaced ours since.
+    unsafe fn restore_interrupts(&mut self) {
+        for (main, previous) in self.saved_interrupts.drain() {
+            let callbacks = ffi::lua_callbacks(main as *mut lua_State);
+            if (*callbacks).interrupt.is_some_and(|x| std::ptr::fn_addr_eq(x, debug_interrupt as unsafe extern "C-unwind" fn(_, _))) {
+                (*callbacks).interrupt = previous;
+            }
+        }
+    }
+    /// Removes the breakpoints armed for a step, restoring the ones of the client.
+    unsafe fn disarm_step(&self, l: *mut lua_State) {
+        let Some(entry) = self.state_of(l) else { return };
+        ffi::lua_checkstack(l, 1);
+        for chunk in entry.chunks.values() {
+            ffi::lua_rawgeti(l, LUA_REGISTRYINDEX, chunk.r as _);
+            Self::set_all_lines(l, 0);
+            for line in self.breakpoints.get(&chunk.script).map(|x| x.lines.as_slice()).unwrap_or_default() {
+                ffi::lua_breakpoint(l, -1, *line, 1);
+            }
+            ffi::lua_pop(l, 1);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum VariableHandle {
+    Locals(c_int),
+    Upvalues(c_int),
+    Table(c_int)
+}
+
+/// Values handed out to the client while a thread is paused, released when it resumes.
+struct PauseContext {
+    thread: *mut lua_State,
+    handles: Vec<VariableHandle>,
+    refs: Vec<c_int>
+}
+
+#[derive(PartialEq, Eq)]
+enum Flow {
+    Stay,
+    Resume
+}
+
+pub struct Debugger {
+    inner: Mutex<DebuggerInner>,
+    incoming: Mutex<Receiver<JsonValue>>,
+    writer: SharedWriter,
+    seq: AtomicI64,
+    /// Set while the debugger itself runs Lua code, so it doesn't pause on its own evaluations.
+    evaluating: AtomicBool,
+    /// Token clients must attach with, `None` when the transport is trusted.
+    token: Option<String>,
+    /// Whether the current client attached with the token.
+    authenticated: AtomicBool
+}
+
+impl std::fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Debugger").field("inner", &self.inner).finish_non_exhaustive()
+    }
+}
+
+/// Gets the script a chunk was compiled from, chunks are named `<script at Name>` or `<module at Name>`.
+pub(super) fn script_name(chunk_name: &str) -> &str {
+    ["<script at ", "<module at "].iter()
+        .find_map(|prefix| chunk_name.strip_prefix(prefix))
+        .and_then(|x| x.strip_suffix('>'))
+        .unwrap_or(chunk_name)
+}
+
+unsafe fn cstr_to_string(s: *const std::ffi::c_char) -> Option<String> {
+    (!s.is_null()).then(|| CStr::from_ptr(s).to_string_lossy().into_owned())
+}
+
+unsafe fn lookup(l: *mut lua_State) -> Option<(&'static Debugger, &'static RobloxVM, &'static LuauState)> {
+    let key = CString::new(registry_keys::STATE_REGISTRYKEY).unwrap();
+    ffi::lua_rawgetfield(l, LUA_REGISTRYINDEX, key.as_ptr());
+    let state = ffi::lua_tolightuserdata(l, -1).cast::<LuauState>().as_ref()?;
+    ffi::lua_pop(l, 1);
+    let vm = state.get_vm_ptr().as_ref()?.access().as_ref()?;
+    vm.get_debugger().map(|debugger| (debugger, vm, state))
+}
+
+unsafe extern "C-unwind" fn debug_break(l: *mut lua_State, _: *mut lua_Debug) {
+    let Some((debugger, vm, state)) = lookup(l) else { return };
+    if debugger.evaluating.load(Relaxed) {
+        return;
+    }
+    let mut inner = debugger.inner.lock().unwrap();
+    let mut ar: lua_Debug = std::mem::zeroed();
+    ffi::lua_getinfo(l, 0, c"sl".as_ptr(), &mut ar);
+    let source = cstr_to_string(ar.source).unwrap_or_default();
+    let user_breakpoint = inner.breakpoints.get(script_name(&source))
+        .is_some_and(|x| x.resolved.contains(&ar.currentline));
+    if state.get_task_scheduler().is_desynchronized() {
+        // Pausing here would block a worker thread, and with it every other Actor and the frame.
+        drop(inner);
+        if user_breakpoint {
+            debugger.send_event("output", JsonValue::object([
+                ("category", "console".into()),
+                ("output", format!("skipped breakpoint at {}:{} in parallel code\n", script_name(&source), ar.currentline).into())
+            ]));
+        }
+        return;
+    }
+    let reason = match inner.step.as_ref() {
+        None => "breakpoint",
+        Some(step) if step.thread == l as usize && step.should_stop(ffi::lua_stackdepth(l), ar.currentline) => "step",
+        Some(_) if user_breakpoint => "breakpoint",
+        // Lines armed for a step, hit by another thread or before the step is done.
+        Some(_) => return
+    };
+    if let Some(step) = inner.step.take() {
+        inner.disarm_step(step.thread as *mut lua_State);
+    }
+    drop(inner);
+    debugger.pause(vm, l, reason);
+}
+
+/// Pauses at the next safepoint of synchronized code, after running the interrupt it replaced.
+unsafe extern "C-unwind" fn debug_interrupt(l: *mut lua_State, gc: c_int) {
+    let Some((debugger, vm, state)) = lookup(l) else { return };
+    let previous = debugger.inner.lock().unwrap().saved_interrupts.get(&(ffi::lua_mainthread(l) as usize)).copied().flatten();
+    // The profiler samples and the watchdog raises its error from here, neither may be skipped while a pause is pending.
+    if let Some(previous) = previous {
+        previous(l, gc);
+    }
+    if gc >= 0 || debugger.evaluating.load(Relaxed) || state.get_task_scheduler().is_desynchronized() {
+        return;
+    }
+    debugger.inner.lock().unwrap().restore_interrupts();
+    debugger.pause(vm, l, "pause");
+}
+
+unsafe extern "C-unwind" fn tostring_cfn(l: *mut lua_State) -> c_int {
+    ffi::luaL_tolstring(l, 1, std::ptr::null_mut());
+    1
+}
+
+impl Debugger {
+    pub(crate) fn new(transport: DebugTransport) -> io::Result<Debugger> {
+        let (sender, receiver) = channel();
+        let writer: SharedWriter = Arc::new(Mutex::new(None));
+        let token = match &transport {
+            DebugTransport::Tcp { token, .. } if token.is_empty() =>
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the debugger token must not be empty")),
+            DebugTransport::Tcp { token, .. } => Some(token.clone()),
+            DebugTransport::Stdio => None
+        };
+        start_server(transport, sender, writer.clone())?;
+        Ok(Debugger {
+            inner: Mutex::new(DebuggerInner { next_thread_id: 1, ..Default::default() }),
+            incoming: Mutex::new(receiver),
+            writer,
+            seq: AtomicI64::new(1),
+            evaluating: AtomicBool::new(false),
+            authenticated: AtomicBool::new(token.is_none()),
+            token
+        })
+    }
+    fn check_token(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (None, _) => true,
+            // Compares every byte, so how long it takes doesn't tell how much of the token matched.
+            (Some(expected), Some(token)) => expected.len() == token.len()
+                && expected.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0,
+            (Some(_), None) => false
+        }
+    }
+
+    fn send(&self, mut message: JsonValue) {
+        if let JsonValue::Object(fields) = &mut message {
+            fields.insert(0, ("seq".into(), self.seq.fetch_add(1, Relaxed).into()));
+        }
+        write_message(&self.writer, &message);
+    }
+    fn send_event(&self, event: &str, body: JsonValue) {
+        self.send(JsonValue::object([("type", "event".into()), ("event", event.into()), ("body", body)]));
+    }
+    fn respond(&self, request: &JsonValue, result: Result<JsonValue, String>) {
+        let mut response = JsonValue::object([
+            ("type", "response".into()),
+            ("request_seq", request.get("seq").clone()),
+            ("command", request.get("command").clone()),
+            ("success", result.is_ok().into())
+        ]);
+        if let JsonValue::Object(fields) = &mut response {
+            match result {
+                Ok(body) => fields.push(("body".into(), body)),
+                Err(message) => fields.push(("message".into(), message.into()))
+            }
+        }
+        self.send(response);
+    }
+
+    /// Registers a state, installing the debug callbacks in it.
+    pub(crate) unsafe fn register_state(&self, state: *mut LuauState) {
+        let mut main = std::ptr::null_mut();
+        let _ = state.as_mut().unwrap_unchecked().get_lua().exec_raw::<()>((), |l| main = ffi::lua_mainthread(l));
+        let callbacks = ffi::lua_callbacks(main);
+        (*callbacks).debugbreak = Some(debug_break);
+
+        let mut inner = self.inner.lock().unwrap();
+        let thread_id = inner.next_thread_id;
+        inner.next_thread_id += 1;
+        inner.states.insert(state as usize, StateEntry { thread_id, main: main as usize, chunks: HashMap::new() });
+    }
+    /// Keeps track of a freshly loaded chunk, at the top of the stack of `l`, and applies the breakpoints of its script.
+    pub(crate) unsafe fn on_chunk_loaded(&self, state: *mut LuauState, l: *mut lua_State, owner: ChunkOwner, chunk_name: &str) {
+        let script = script_name(chunk_name);
+        let mut inner = self.inner.lock().unwrap();
+        let Some(entry) = inner.states.get_mut(&(state as usize)) else { return };
+        let chunk = LoadedChunk { script: script.to_owned(), r: ffi::lua_ref(l, -1) };
+        if let Some(previous) = entry.chunks.insert(owner, chunk) {
+            ffi::lua_unref(l, previous.r);
+        }
+        if let Some(breakpoints) = inner.breakpoints.get_mut(script) {
+            for line in breakpoints.lines.iter() {
+                let actual = ffi::lua_breakpoint(l, -1, *line, 1);
+                if actual >= 0 {
+                    breakpoints.resolved.insert(actual);
+                }
+            }
+        }
+    }
+
+    /// Releases the chunks loaded for `script` in every state, once it stopped or was destroyed.
+    /// Other states may be running, so the refs are only dropped by the next [`Debugger::poll`].
+    pub(crate) fn release_script(&self, script: &WeakManagedInstance) {
+        let owner = ChunkOwner::Script(script.clone());
+        let mut inner = self.inner.lock().unwrap();
+        let released: Vec<_> = inner.states.iter_mut()
+            .filter_map(|(ptr, entry)| entry.chunks.remove(&owner).map(|chunk| (*ptr, entry.main, chunk.r)))
+            .collect();
+        inner.released.extend(released);
+    }
+    fn live_states(vm: &RobloxVM) -> HashSet<usize> {
+        vm.get_all_states().iter().map(|x| unsafe { x.access() } as usize).collect()
+    }
+    fn thread_id_of(&self, l: *mut lua_State) -> i64 {
+        let main = unsafe { ffi::lua_mainthread(l) } as usize;
+        self.inner.lock().unwrap().states.values()
+            .find(|x| x.main == main)
+            .map(|x| x.thread_id)
+            .unwrap_or(0)
+    }
+
+    /// Handles requests which arrived while no thread is paused. Called between frames.
+    pub(crate) fn poll(&self, vm: &RobloxVM) {
+        let live = Self::live_states(vm);
+        {
+            let mut inner = self.inner.lock().unwrap();
+            for (state, main, r) in std::mem::take(&mut inner.released) {
+                // The chunks of a closed state went away with it.
+                if live.contains(&state) && inner.states.get(&state).is_some_and(|x| x.main == main) {
+                    unsafe { ffi::lua_unref(main as *mut lua_State, r) };
+                }
+            }
+        }
+        loop {
+            let message = match self.incoming.lock().unwrap().try_recv() {
+                Ok(message) => message,
+                Err(_) => return
+            };
+            self.handle(vm, &message, None);
+        }
+    }
+
+    unsafe fn pause(&self, vm: &RobloxVM, l: *mut lua_State, reason: &str) {
+        self.send_event("stopped", JsonValue::object([
+            ("reason", reason.into()),
+            ("threadId", self.thread_id_of(l).into()),
+            ("allThreadsStopped", true.into())
+        ]));
+        let mut context = PauseContext { thread: l, handles: Vec::new(), refs: Vec::new() };
+        {
+            let incoming = self.incoming.lock().unwrap();
+            while let Ok(message) = incoming.recv() {
+                if self.handle(vm, &message, Some(&mut context)) == Flow::Resume {
+                    break;
+                }
+            }
+        }
+        for r in context.refs {
+            ffi::lua_unref(l, r);
+        }
+    }
+
+    fn handle(&self, vm: &RobloxVM, request: &JsonValue, paused: Option<&mut PauseContext>) -> Flow {
+        if request.get("type").as_str() != Some("request") {
+            return Flow::Stay;
+        }
+        let arguments = request.get("arguments");
+        let command = request.get("command").as_str().unwrap_or_default();
+        if !self.authenticated.load(Relaxed) && !matches!(command, "initialize" | "attach" | "launch") {
+            self.respond(request, Err("attach or launch with the debugger token first".into()));
+            return Flow::Stay;
+        }
+        let not_paused = || Err::<JsonValue, String>("no thread is paused".into());
+        let mut flow = Flow::Stay;
+        let result = match (command, paused) {
+            ("initialize", _) => Ok(JsonValue::object([
+                ("supportsConfigurationDoneRequest", true.into()),
+                ("supportsEvaluateForHovers", true.into())
+            ])),
+            ("launch" | "attach", _) => {
+                if !self.check_token(arguments.get("token").as_str()) {
+                    self.respond(request, Err("invalid debugger token".into()));
+                    return Flow::Stay;
+                }
+                self.authenticated.store(true, Relaxed);
+                self.respond(request, Ok(JsonValue::object([])));
+                self.send_event("initialized", JsonValue::object([]));
+                return Flow::Stay;
+            }
+            ("configurationDone" | "setExceptionBreakpoints", _) => Ok(JsonValue::object([])),
+            ("setBreakpoints", paused) => {
+                let source = arguments.get("source");
+                let script = source.get("path").as_str().or(source.get("name").as_str()).unwrap_or_default().to_owned();
+                let lines: Vec<c_int> = arguments.get("breakpoints").as_array().iter()
+                    .filter_map(|x| x.get("line").as_i64())
+                    .map(|x| x as c_int)
+                    .collect();
+                let verified = unsafe { self.set_breakpoints(vm, script, lines.clone(), paused.map(|x| x.thread)) };
+                Ok(JsonValue::object([("breakpoints", lines.iter().map(|line| match verified.get(line) {
+                    Some(actual) => JsonValue::object([("verified", true.into()), ("line", (*actual as i64).into())]),
+                    None => JsonValue::object([
+                        ("verified", false.into()),
+                        ("line", (*line as i64).into()),
+                        ("message", "no loaded code at this line".into())
+                    ])
+                }).collect::<Vec<_>>().into())]))
+            }
+            ("threads", _) => {
+                let live = Self::live_states(vm);
+                let main = unsafe { vm.get_main_state_rc().access() } as usize;
+                let inner = self.inner.lock().unwrap();
+                let mut threads: Vec<_> = inner.states.iter()
+                    .filter(|(ptr, _)| live.contains(ptr))
+                    .map(|(ptr, entry)| (entry.thread_id, if *ptr == main { "Main".to_owned() } else { format!("Actor {}", entry.thread_id) }))
+                    .collect();
+                threads.sort();
+                Ok(JsonValue::object([("threads", threads.into_iter()
+                    .map(|(id, name)| JsonValue::object([("id", id.into()), ("name", name.into())]))
+                    .collect::<Vec<_>>().into())]))
+            }
+            ("pause", Some(_)) => Ok(JsonValue::object([])),
+            ("pause", None) => {
+                unsafe { self.request_pause(vm) };
+                Ok(JsonValue::object([]))
+            }
+            ("continue", Some(_)) => {
+                flow = Flow::Resume;
+                Ok(JsonValue::object([("allThreadsContinued", true.into())]))
+            }
+            ("continue", None) => Ok(JsonValue::object([("allThreadsContinued", true.into())])),
+            (command @ ("next" | "stepIn" | "stepOut"), Some(context)) => {
+                let kind = match command {
+                    "next" => StepKind::Over,
+                    "stepIn" => StepKind::In,
+                    _ => StepKind::Out
+                };
+                unsafe {
+                    let mut ar: lua_Debug = std::mem::zeroed();
+                    ffi::lua_getinfo(context.thread, 0, c"l".as_ptr(), &mut ar);
+                    let mut inner = self.inner.lock().unwrap();
+                    inner.step = Some(StepRequest {
+                        thread: context.thread as usize,
+                        kind,
+                        depth: ffi::lua_stackdepth(context.thread),
+                        line: ar.currentline
+                    });
+                    inner.arm_step(context.thread);
+                }
+                flow = Flow::Resume;
+                Ok(JsonValue::object([]))
+            }
+            ("stackTrace", Some(context)) => Ok(unsafe { Self::stack_trace(context.thread) }),
+            ("scopes", Some(context)) => {
+                let level = arguments.get("frameId").as_i64().unwrap_or(1) as c_int - 1;
+                let mut scope = |name: &str, handle| {
+                    context.handles.push(handle);
+                    JsonValue::object([
+                        ("name", name.into()),
+                        ("variablesReference", (context.handles.len() as i64).into()),
+                        ("expensive", false.into())
+                    ])
+                };
+                Ok(JsonValue::object([("scopes", vec![
+                    scope("Locals", VariableHandle::Locals(level)),
+                    scope("Upvalues", VariableHandle::Upvalues(level))
+                ].into())]))
+            }
+            ("variables", Some(context)) => {
+                let reference = arguments.get("variablesReference").as_i64().unwrap_or(0);
+                match context.handles.get((reference - 1) as usize).copied() {
+                    Some(handle) => Ok(JsonValue::object([("variables", unsafe { self.variables(context, handle) }.into())])),
+                    None => Err("invalid variables reference".into())
+                }
+            }
+            ("evaluate", Some(context)) => {
+                let expression = arguments.get("expression").as_str().unwrap_or_default();
+                let level = arguments.get("frameId").as_i64().map(|x| x as c_int - 1);
+                unsafe { self.evaluate(context, level, expression) }
+            }
+            ("stackTrace" | "scopes" | "variables" | "next" | "stepIn" | "stepOut" | "evaluate", None) => not_paused(),
+            ("disconnect", paused) => {
+                if self.token.is_some() {
+                    self.authenticated.store(false, Relaxed);
+                }
+                let mut inner = self.inner.lock().unwrap();
+                if let Some(step) = inner.step.take() {
+                    unsafe { inner.disarm_step(step.thread as *mut lua_State) };
+                }
+                unsafe { inner.restore_interrupts() };
+                let scripts: Vec<String> = inner.breakpoints.keys().cloned().collect();
+                drop(inner);
+                for script in scripts {
+                    unsafe { self.set_breakpoints(vm, script, Vec::new(), paused.as_ref().map(|x| x.thread)) };
+                }
+                if paused.is_some() {
+                    flow = Flow::Resume;
+                }
+                Ok(JsonValue::object([]))
+            }
+            (command, _) => Err(format!("unsupported request '{}'", command))
+        };
+        self.respond(request, result);
+        flow
+    }
+
+    /// Replaces the breakpoints of a script in every state, returning the line each breakpoint ended up on.
+    unsafe fn set_breakpoints(&self, vm: &RobloxVM, script: String, lines: Vec<c_int>, paused: Option<*mut lua_State>) -> HashMap<c_int, c_int> {
+        let live = Self::live_states(vm);
+        let mut inner = self.inner.lock().unwrap();
+        let old = inner.breakpoints.remove(&script).map(|x| x.lines).unwrap_or_default();
+        let mut verified = HashMap::new();
+        for (_, entry) in inner.states.iter().filter(|(ptr, _)| live.contains(ptr)) {
+            let refs: Vec<c_int> = entry.chunks.values().filter(|x| x.script == script).map(|x| x.r).collect();
+            if refs.is_empty() {
+                continue;
+            }
+            // The paused thread's stack is the only one of its state which is safe to use.
+            let l = paused.filter(|l| ffi::lua_mainthread(*l) as usize == entry.main)
+                .unwrap_or(entry.main as *mut lua_State);
+            ffi::lua_checkstack(l, 1);
+            for r in refs {
+                ffi::lua_rawgeti(l, LUA_REGISTRYINDEX, r as _);
+                for line in old.iter() {
+                    ffi::lua_breakpoint(l, -1, *line, 0);
+                }
+                for line in lines.iter() {
+                    let actual = ffi::lua_breakpoint(l, -1, *line, 1);
+                    if actual >= 0 {
+                        verified.insert(*line, actual);
+                    }
+                }
+                ffi::lua_pop(l, 1);
+            }
+        }
+        if !lines.is_empty() {
+            let resolved = verified.values().copied().collect();
+            inner.breakpoints.insert(script, ScriptBreakpoints { lines, resolved });
+        }
+        verified
+    }
+
+    /// Makes every state pause at its next safepoint in synchronized code.
+    unsafe fn request_pause(&self, vm: &RobloxVM) {
+        let live = Self::live_states(vm);
+        let mut inner = self.inner.lock().unwrap();
+        let mains: Vec<usize> = inner.states.iter()
+            .filter(|(ptr, _)| live.contains(ptr))
+            .map(|(_, entry)| entry.main)
+            .filter(|main| !inner.saved_interrupts.contains_key(main))
+            .collect();
+        for main in mains {
+            let callbacks = ffi::lua_callbacks(main as *mut lua_State);
+            inner.saved_interrupts.insert(main, (*callbacks).interrupt);
+            (*callbacks).interrupt = Some(debug_interrupt);
+        }
+    }
+
+    unsafe fn stack_trace(l: *mut lua_State) -> JsonValue {
+        let mut frames = Vec::new();
+        let mut level = 0;
+        loop {
+            let mut ar: lua_Debug = std::mem::zeroed();
+            if ffi::lua_getinfo(l, level, c"sln".as_ptr(), &mut ar) == 0 {
+                break;
+            }
+            let what = cstr_to_string(ar.what).unwrap_or_default();
+            let name = cstr_to_string(ar.name).unwrap_or_else(|| "<anonymous>".into());
+            let mut frame = vec![
+                ("id".to_owned(), (level as i64 + 1).into()),
+                ("name".to_owned(), name.into()),
+                ("line".to_owned(), (ar.currentline.max(0) as i64).into()),
+                ("column".to_owned(), 1.into())
+            ];
+            if what != "C" {
+                let source = cstr_to_string(ar.source).unwrap_or_default();
+                let script = script_name(&source);
+                frame.push(("source".to_owned(), JsonValue::object([("name", script.into()), ("path", script.into())])));
+            }
+            frames.push(JsonValue::Object(frame));
+            level += 1;
+        }
+        let total = frames.len() as i64;
+        JsonValue::object([("stackFrames", frames.into()), ("totalFrames", total.into())])
+    }
+
+    /// Reads the string at `idx`, which must already be a string.
+    unsafe fn raw_string(l: *mut lua_State, idx: c_int) -> String {
+        let mut len = 0;
+        let s = ffi::lua_tolstring(l, idx, &mut len);
+        String::from_utf8_lossy(std::slice::from_raw_parts(s.cast(), len)).into_owned()
+    }
+    unsafe fn error_string(l: *mut lua_State, idx: c_int) -> String {
+        if ffi::lua_type(l, idx) == ffi::LUA_TSTRING { Self::raw_string(l, idx) } else { Self::value_string(l, idx) }
+    }
+    /// Formats the value at `idx` without running any code which could raise an error.
+    unsafe fn value_string(l: *mut lua_State, idx: c_int) -> String {
+        let idx = if idx < 0 { ffi::lua_gettop(l) + idx + 1 } else { idx };
+        match ffi::lua_type(l, idx) {
+            ffi::LUA_TNIL => "nil".into(),
+            ffi::LUA_TBOOLEAN => (ffi::lua_toboolean(l, idx) != 0).to_string(),
+            ffi::LUA_TNUMBER => {
+                let n = ffi::lua_tonumberx(l, idx, std::ptr::null_mut());
+                if n.fract() == 0.0 && n.abs() < 1e15 { format!("{}", n as i64) } else { n.to_string() }
+            }
+            ffi::LUA_TSTRING => format!("{:?}", Self::raw_string(l, idx)),
+            _ => {
+                // tostring may run __tostring metamethods, which are only safe to call protected.
+                ffi::lua_checkstack(l, 2);
+                ffi::lua_pushcfunction(l, tostring_cfn);
+                ffi::lua_pushvalue(l, idx);
+                let s = if ffi::lua_pcall(l, 1, 1, 0) == ffi::LUA_OK {
+                    Self::raw_string(l, -1)
+                } else {
+                    "<error>".into()
+                };
+                ffi::lua_pop(l, 1);
+                s
+            }
+        }
+    }
+    /// Describes the value at the top of the stack as a DAP variable, tables can be expanded.
+    unsafe fn variable(context: &mut PauseContext, l: *mut lua_State, name: String) -> JsonValue {
+        let type_name = cstr_to_string(ffi::lua_typename(l, ffi::lua_type(l, -1))).unwrap_or_default();
+        let mut reference = 0;
+        if ffi::lua_type(l, -1) == ffi::LUA_TTABLE {
+            let r = ffi::lua_ref(l, -1);
+            context.refs.push(r);
+            context.handles.push(VariableHandle::Table(r));
+            reference = context.handles.len() as i64;
+        }
+        JsonValue::object([
+            ("name", name.into()),
+            ("value", Self::value_string(l, -1).into()),
+            ("type", type_name.into()),
+            ("variablesReference", reference.into())
+        ])
+    }
+    unsafe fn variables(&self, context: &mut PauseContext, handle: VariableHandle) -> Vec<JsonValue> {
+        const MAX_VARIABLES: usize = 1000;
+        let l = context.thread;
+        ffi::lua_checkstack(l, 4);
+        let mut variables = Vec::new();
+        match handle {
+            VariableHandle::Locals(level) => {
+                for n in 1.. {
+                    let name = ffi::lua_getlocal(l, level, n);
+                    if name.is_null() {
+                        break;
+                    }
+                    let name = cstr_to_string(name).unwrap_or_default();
+                    // Names in parentheses are compiler temporaries.
+                    if !name.starts_with('(') {
+                        variables.push(Self::variable(context, l, name));
+                    }
+                    ffi::lua_pop(l, 1);
+                }
+            }
+            VariableHandle::Upvalues(level) => {
+                let mut ar: lua_Debug = std::mem::zeroed();
+                if ffi::lua_getinfo(l, level, c"f".as_ptr(), &mut ar) != 0 {
+                    for n in 1.. {
+                        let name = ffi::lua_getupvalue(l, -1, n);
+                        if name.is_null() {
+                            break;
+                        }
+                        let name = cstr_to_string(name).filter(|x| !x.is_empty()).unwrap_or_else(|| format!("upvalue {}", n));
+                        variables.push(Self::variable(context, l, name));
+                        ffi::lua_pop(l, 1);
+                    }
+                    ffi::lua_pop(l, 1);
+                }
+            }
+            VariableHandle::Table(r) => {
+                ffi::lua_rawgeti(l, LUA_REGISTRYINDEX, r as _);
+                let table = ffi::lua_gettop(l);
+                ffi::lua_pushnil(l);
+                while ffi::lua_next(l, table) != 0 && variables.len() < MAX_VARIABLES {
+                    let name = if ffi::lua_type(l, -2) == ffi::LUA_TSTRING {
+                        Self::raw_string(l, -2)
+                    } else {
+                        format!("[{}]", Self::value_string(l, -2))
+                    };
+                    variables.push(Self::variable(context, l, name));
+                    ffi::lua_pop(l, 1);
+                }
+                ffi::lua_settop(l, table - 1);
+            }
+        }
+        variables
+    }
+
+    /// Evaluates an expression in the paused thread, in the scope of a frame if one is given.
+    unsafe fn evaluate(&self, context: &mut PauseContext, level: Option<c_int>, expression: &str) -> Result<JsonValue, String> {
+        let compiler = Compiler::new().set_debug_level(2);
+        let bytecode = compiler.compile(format!("return {}", expression))
+            .or_else(|_| compiler.compile(expression))
+            .map_err(|err| err.to_string())?;
+        let l = context.thread;
+        ffi::lua_checkstack(l, 8);
+        let top = ffi::lua_gettop(l);
+        if ffi::luau_load(l, c"=evaluate".as_ptr(), bytecode.as_ptr().cast(), bytecode.len(), 0) != 0 {
+            let err = Self::error_string(l, -1);
+            ffi::lua_settop(l, top);
+            return Err(err);
+        }
+        if let Some(level) = level {
+            Self::push_frame_environment(l, level);
+            ffi::lua_setfenv(l, -2);
+        }
+        self.evaluating.store(true, Relaxed);
+        let status = ffi::lua_pcall(l, 0, 1, 0);
+        self.evaluating.store(false, Relaxed);
+        let result = if status == ffi::LUA_OK {
+            let variable = Self::variable(context, l, String::new());
+            Ok(JsonValue::object([
+                ("result", variable.get("value").clone()),
+                ("type", variable.get("type").clone()),
+                ("variablesReference", variable.get("variablesReference").clone())
+            ]))
+        } else {
+            Err(Self::error_string(l, -1))
+        };
+        ffi::lua_settop(l, top);
+        result
+    }
+    /// Pushes a table holding the locals and upvalues of a frame, falling back to the frame's environment.
+    unsafe fn push_frame_environment(l: *mut lua_State, level: c_int) {
+        ffi::lua_createtable(l, 0, 0);
+        let env = ffi::lua_gettop(l);
+        let mut ar: lua_Debug = std::mem::zeroed();
+        if ffi::lua_getinfo(l, level, c"f".as_ptr(), &mut ar) != 0 {
+            for n in 1.. {
+                let name = ffi::lua_getupvalue(l, -1, n);
+                if name.is_null() {
+                    break;
+                }
+                if *name != 0 {
+                    ffi::lua_setfield(l, env, name);
+                } else {
+                    ffi::lua_pop(l, 1);
+                }
+            }
+            ffi::lua_createtable(l, 0, 1);
+            ffi::lua_getfenv(l, -2);
+            ffi::lua_setfield(l, -2, c"__index".as_ptr());
+            ffi::lua_setmetatable(l, env);
+            ffi::lua_pop(l, 1);
+        }
+        // Locals are set last, so they shadow upvalues and inner scopes shadow outer ones.
+        for n in 1.. {
+            let name = ffi::lua_getlocal(l, level, n);
+            if name.is_null() {
+                break;
+            }
+            if *name != b'(' as _ {
+                ffi::lua_setfield(l, env, name);
+            } else {
+                ffi::lua_pop(l, 1);
+            }
+        }
+    }
+}
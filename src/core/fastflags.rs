@@ -28,7 +28,11 @@ pub enum FastFlag {
     IsStudio,                 // bool
     DebugMode,                // bool
 
-    SignalBehavior            // int
+    SignalBehavior,           // int, value of an Enum.SignalBehavior item
+
+    LuauHeapLimitMB,          // int
+
+    ParallelLuau              // bool
 }
 union FlagInternal {
     bool_value: bool,
@@ -56,7 +60,8 @@ impl FlagInternal {
             FastFlag::PlaceId |
             FastFlag::PlaceVersion |
             FastFlag::PrivateServerOwnerId |
-            FastFlag::SignalBehavior => unsafe { self.int_value },
+            FastFlag::SignalBehavior |
+            FastFlag::LuauHeapLimitMB => unsafe { self.int_value },
             _ => panic!("Invalid flag")
         }
     }
@@ -75,7 +80,8 @@ impl FlagInternal {
             FastFlag::GlobalsReadonly |
             FastFlag::IsClient |
             FastFlag::IsStudio |
-            FastFlag::DebugMode => unsafe { self.bool_value },
+            FastFlag::DebugMode |
+            FastFlag::ParallelLuau => unsafe { self.bool_value },
             _ => panic!("Invalid flag")
         }
     }
@@ -101,7 +107,8 @@ impl FlagInternal {
             FastFlag::PlaceId |
             FastFlag::PlaceVersion |
             FastFlag::PrivateServerOwnerId |
-            FastFlag::SignalBehavior => self.int_value = v,
+            FastFlag::SignalBehavior |
+            FastFlag::LuauHeapLimitMB => self.int_value = v,
             _ => panic!("Invalid flag")
         }
     }
@@ -120,7 +127,8 @@ impl FlagInternal {
             FastFlag::GlobalsReadonly |
             FastFlag::IsClient |
             FastFlag::IsStudio |
-            FastFlag::DebugMode => self.bool_value = v,
+            FastFlag::DebugMode |
+            FastFlag::ParallelLuau => self.bool_value = v,
             _ => panic!("Invalid flag")
         }
     }
@@ -136,14 +144,16 @@ impl FlagInternal {
             FastFlag::PlaceId |
             FastFlag::PlaceVersion |
             FastFlag::PrivateServerOwnerId |
-            FastFlag::SignalBehavior => unsafe { FastFlagValue::Int(self.int_value) },
+            FastFlag::SignalBehavior |
+            FastFlag::LuauHeapLimitMB => unsafe { FastFlagValue::Int(self.int_value) },
             FastFlag::TargetFPS |
             FastFlag::TargetPhysicsFPS => unsafe { FastFlagValue::Float(self.float_value) },
             FastFlag::VSync |
             FastFlag::GlobalsReadonly |
             FastFlag::IsClient |
             FastFlag::DebugMode |
-            FastFlag::IsStudio => unsafe { FastFlagValue::Bool(self.bool_value) }
+            FastFlag::IsStudio |
+            FastFlag::ParallelLuau => unsafe { FastFlagValue::Bool(self.bool_value) }
         }
     }
 }
@@ -171,7 +181,13 @@ impl FastFlag {
             Self::IsStudio => FlagInternal { bool_value: false },
             Self::DebugMode => FlagInternal { bool_value: true },
             
-            Self::SignalBehavior => FlagInternal { int_value: 0 }
+            Self::SignalBehavior => FlagInternal { int_value: 0 },
+
+            // Per state, 0 disables the cap.
+            Self::LuauHeapLimitMB => FlagInternal { int_value: 1024 },
+
+            // Runs the desynchronized phase of Actors on worker threads, serially when disabled.
+            Self::ParallelLuau => FlagInternal { bool_value: true }
         }
     }
     pub fn get_default(self) -> FastFlagValue {
@@ -278,7 +294,14 @@ impl FastFlags {
                 .access().as_ref().unwrap_unchecked()
                 .get_global_lock_state());
         }
-        self.get_flag_mut_internal(flag).set_int(flag, v)
+        self.get_flag_mut_internal(flag).set_int(flag, v);
+        if flag == FastFlag::LuauHeapLimitMB {
+            unsafe {
+                self.vm.as_ref().unwrap_unchecked()
+                    .access().as_ref().unwrap_unchecked()
+                    .apply_memory_limit();
+            }
+        }
     }
     pub fn set_float(&self, flag: FastFlag, v: f64) {
         unsafe {
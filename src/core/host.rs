@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use godot::global::{godot_print, godot_print_rich};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum HostMessageKind {
+    /// Regular script output, e.g. `print`.
+    Output,
+    Info,
+    Warning,
+    Error,
+    /// Runtime diagnostics which are not produced by scripts.
+    Debug
+}
+
+/// The environment a RobloxVM is running in. Every message the VM emits goes through its host.
+///
+/// Hosts can be called from any thread holding the VM, so they must be thread-safe.
+pub trait IVMHost: Send + Sync + Debug {
+    fn print(&self, kind: HostMessageKind, message: &str);
+}
+
+/// Host used when running inside of Godot, prints to the editor output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GodotVMHost;
+
+impl IVMHost for GodotVMHost {
+    fn print(&self, kind: HostMessageKind, message: &str) {
+        match kind {
+            HostMessageKind::Output |
+            HostMessageKind::Debug => godot_print!("{}", message),
+            HostMessageKind::Info => godot_print_rich!("[color=blue]{}[/color]", message),
+            HostMessageKind::Warning => godot_print_rich!("[color=yellow]{}[/color]", message),
+            HostMessageKind::Error => godot_print_rich!("[color=red]{}[/color]", message)
+        }
+    }
+}
+
+/// Host printing to the process' standard output and error, usable without a Godot engine instance.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutVMHost {
+    /// Whether [`HostMessageKind::Debug`] messages should be printed.
+    pub print_debug: bool
+}
+
+impl IVMHost for StdoutVMHost {
+    fn print(&self, kind: HostMessageKind, message: &str) {
+        let _ = match kind {
+            HostMessageKind::Output => writeln!(std::io::stdout(), "{}", message),
+            HostMessageKind::Info => writeln!(std::io::stdout(), "[info] {}", message),
+            HostMessageKind::Warning => writeln!(std::io::stderr(), "[warn] {}", message),
+            HostMessageKind::Error => writeln!(std::io::stderr(), "[error] {}", message),
+            HostMessageKind::Debug if self.print_debug => writeln!(std::io::stdout(), "[debug] {}", message),
+            HostMessageKind::Debug => Ok(())
+        };
+    }
+}
+
+/// Host recording every message in memory. Clones share the same buffer, so one can be kept
+/// to inspect the output after handing the host to the VM.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryVMHost {
+    messages: Arc<Mutex<Vec<(HostMessageKind, String)>>>
+}
+
+impl MemoryVMHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn messages(&self) -> Vec<(HostMessageKind, String)> {
+        self.messages.lock().unwrap().clone()
+    }
+    /// Messages of a kind, in the order they were emitted.
+    pub fn messages_of_kind(&self, kind: HostMessageKind) -> Vec<String> {
+        self.messages.lock().unwrap().iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, message)| message.clone())
+            .collect()
+    }
+    pub fn take_messages(&self) -> Vec<(HostMessageKind, String)> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+}
+
+impl IVMHost for MemoryVMHost {
+    fn print(&self, kind: HostMessageKind, message: &str) {
+        self.messages.lock().unwrap().push((kind, message.to_owned()));
+    }
+}
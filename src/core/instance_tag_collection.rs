@@ -1,12 +1,16 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::instance::WeakManagedInstance;
+use crate::instance::{ManagedInstance, WeakManagedInstance};
+use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal};
 
 use super::RwLock;
 
 #[derive(Default, Debug)]
 pub(crate) struct InstanceTagCollectionTable {
     main: RwLock<HashMap<String, RwLock<HashSet<WeakManagedInstance>>>>,
+    // CollectionService signals, fired when a tagged instance enters or leaves the DataModel.
+    added_signals: RwLock<HashMap<String, ManagedRBXScriptSignal>>,
+    removed_signals: RwLock<HashMap<String, ManagedRBXScriptSignal>>
 }
 
 impl InstanceTagCollectionTable {
@@ -30,10 +34,45 @@ impl InstanceTagCollectionTable {
             unsafe { table.unwrap_unchecked() }.write().unwrap().remove(instance);
         }
     }
+    /// Every live instance with `tag`, wherever it is.
+    pub(crate) fn get_tagged(&self, tag: &str) -> Vec<ManagedInstance> {
+        self.main.read().unwrap().get(tag)
+            .map(|x| x.read().unwrap().iter().filter_map(|x| x.upgrade()).collect())
+            .unwrap_or_default()
+    }
+    /// Every tag some live instance for which `filter` is true has.
+    pub(crate) fn get_tags(&self, filter: impl Fn(&ManagedInstance) -> bool) -> Vec<String> {
+        self.main.read().unwrap().iter()
+            .filter(|(_, x)| x.read().unwrap().iter().filter_map(|x| x.upgrade()).any(|x| filter(&x)))
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+    pub(crate) fn get_added_signal(&self, tag: String) -> ManagedRBXScriptSignal {
+        self.added_signals.write().unwrap().entry(tag).or_insert_with(RBXScriptSignal::new).clone()
+    }
+    pub(crate) fn get_removed_signal(&self, tag: String) -> ManagedRBXScriptSignal {
+        self.removed_signals.write().unwrap().entry(tag).or_insert_with(RBXScriptSignal::new).clone()
+    }
+    /// Whether any CollectionService signal can still be listened to, otherwise nothing needs to be fired.
+    /// Signals which only the table holds and which nothing listens to are dropped first.
+    pub(crate) fn has_signals(&self) -> bool {
+        let mut has_signals = false;
+        for signals in [&self.added_signals, &self.removed_signals] {
+            let mut signals = signals.write().unwrap();
+            signals.retain(|_, x| x.strong_count() > 1 || x.read().has_listeners());
+            has_signals |= !signals.is_empty();
+        }
+        has_signals
+    }
+    /// The added or removed signal of `tag`, if something asked for it.
+    pub(crate) fn find_signal(&self, tag: &str, added: bool) -> Option<ManagedRBXScriptSignal> {
+        let signals = if added { &self.added_signals } else { &self.removed_signals };
+        signals.read().unwrap().get(tag).cloned()
+    }
     pub fn garbage_collect(&self) {
         for (_, tbl) in self.main.read().unwrap().iter() {
             tbl.write().unwrap()
                 .retain(|x| !x.dead());
         }
     }
-}
\ No newline at end of file
+}
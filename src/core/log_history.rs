@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::userdata::enums::MessageType;
+
+/// Messages kept for LogService:GetLogHistory, older ones are dropped first.
+const MAX_LOG_HISTORY: usize = 1000;
+/// Messages kept until [`LogHistory::take_pending`] is called, older ones are dropped first.
+const MAX_PENDING_LOG_MESSAGES: usize = 1000;
+
+/// A message printed by a script or on behalf of one.
+#[derive(Clone, Debug)]
+pub struct LogMessage {
+    pub message: String,
+    pub message_type: MessageType,
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64
+}
+
+/// Everything printed through the VM, both the history scripts can read and the messages the host application hasn't received yet.
+#[derive(Debug, Default)]
+pub struct LogHistory {
+    history: VecDeque<LogMessage>,
+    pending: VecDeque<LogMessage>
+}
+
+impl LogMessage {
+    pub fn new(message: String, message_type: MessageType) -> LogMessage {
+        LogMessage {
+            message,
+            message_type,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs_f64()).unwrap_or_default()
+        }
+    }
+}
+
+impl LogHistory {
+    pub fn push(&mut self, message: LogMessage) {
+        if self.history.len() >= MAX_LOG_HISTORY {
+            self.history.pop_front();
+        }
+        if self.pending.len() >= MAX_PENDING_LOG_MESSAGES {
+            self.pending.pop_front();
+        }
+        self.history.push_back(message.clone());
+        self.pending.push_back(message);
+    }
+    /// Messages from the oldest to the newest.
+    pub fn history(&self) -> impl Iterator<Item = &LogMessage> {
+        self.history.iter()
+    }
+    /// Takes the messages pushed since the last call, oldest first.
+    pub fn take_pending(&mut self) -> Vec<LogMessage> {
+        self.pending.drain(..).collect()
+    }
+}
@@ -0,0 +1,92 @@
+use std::ffi::c_int;
+
+use r2g_mlua::ffi;
+use r2g_mlua::prelude::*;
+
+use crate::instance::{ManagedInstance, WeakManagedInstance};
+
+use super::{FastFlag, LuauState};
+
+/// Luau supports 256 memory categories, 0 is left for everything not owned by a script.
+const FIRST_SCRIPT_CATEGORY: usize = 1;
+/// Shared by every script once the other categories run out.
+const SHARED_SCRIPT_CATEGORY: u8 = 255;
+
+/// Memory categories of the scripts running in a state, allocations made by a thread are charged to its category.
+#[derive(Debug, Default)]
+pub(super) struct MemoryCategories {
+    scripts: Vec<WeakManagedInstance>
+}
+
+/// Heap usage of a single state, in bytes.
+#[derive(Clone, Debug)]
+pub struct StateMemoryUsage {
+    pub used: usize,
+    /// Zero when the state has no cap.
+    pub limit: usize,
+    /// Bytes charged to each running script, allocations made outside of scripts are not included.
+    pub scripts: Vec<(ManagedInstance, usize)>
+}
+
+impl LuauState {
+    fn category_bytes(&self, category: u8) -> usize {
+        let mut bytes = 0;
+        unsafe {
+            let _ = self.lua.exec_raw::<()>((), |l| bytes = ffi::lua_totalbytes(l, category as c_int));
+        }
+        bytes
+    }
+    fn get_memory_category(&mut self, script: &WeakManagedInstance) -> u8 {
+        if let Some(i) = self.memory.scripts.iter().position(|x| x == script) {
+            return (i + FIRST_SCRIPT_CATEGORY) as u8;
+        }
+        // Categories of destroyed scripts are reused once everything they allocated is collected.
+        let reusable = (0..self.memory.scripts.len())
+            .find(|i| self.memory.scripts[*i].dead() && self.category_bytes((i + FIRST_SCRIPT_CATEGORY) as u8) == 0);
+        if let Some(i) = reusable {
+            self.memory.scripts[i] = script.clone();
+            (i + FIRST_SCRIPT_CATEGORY) as u8
+        } else if self.memory.scripts.len() + FIRST_SCRIPT_CATEGORY < SHARED_SCRIPT_CATEGORY as usize {
+            self.memory.scripts.push(script.clone());
+            (self.memory.scripts.len() - 1 + FIRST_SCRIPT_CATEGORY) as u8
+        } else {
+            SHARED_SCRIPT_CATEGORY
+        }
+    }
+    /// Charges the allocations of `thread`, and of the threads it creates, to `script`.
+    pub(super) fn set_thread_memory_category(&mut self, thread: &LuaThread, script: Option<&WeakManagedInstance>) {
+        let category = script.map(|x| self.get_memory_category(x)).unwrap_or(0);
+        unsafe {
+            let _ = self.lua.exec_raw::<()>(thread, |l| ffi::lua_setmemcat(ffi::lua_tothread(l, 1), category as c_int));
+        }
+    }
+    /// Applies the LuauHeapLimitMB flag to this state.
+    pub(super) fn apply_memory_limit(&self) {
+        let limit = self.flags().get_int(FastFlag::LuauHeapLimitMB).max(0) as usize * 1024 * 1024;
+        let _ = self.lua.set_memory_limit(limit);
+    }
+
+    /// Bytes currently allocated by this state.
+    pub fn get_memory_usage(&self) -> usize {
+        self.lua.used_memory()
+    }
+    /// Bytes allocated by threads of `script` which are still alive. Scripts sharing the last category are not told apart.
+    pub fn get_script_memory_usage(&self, script: &ManagedInstance) -> usize {
+        let script = script.downgrade();
+        self.memory.scripts.iter()
+            .position(|x| *x == script)
+            .map(|i| self.category_bytes((i + FIRST_SCRIPT_CATEGORY) as u8))
+            .unwrap_or(0)
+    }
+    pub fn get_memory_stats(&self) -> StateMemoryUsage {
+        let limit = self.flags().get_int(FastFlag::LuauHeapLimitMB).max(0) as usize * 1024 * 1024;
+        StateMemoryUsage {
+            used: self.get_memory_usage(),
+            limit,
+            scripts: self.memory.scripts.iter()
+                .enumerate()
+                .filter_map(|(i, x)| Some((x.upgrade()?, self.category_bytes((i + FIRST_SCRIPT_CATEGORY) as u8))))
+                .collect()
+        }
+    }
+}
@@ -1,17 +1,28 @@
 pub mod alloc;
 mod rc;
 mod security;
-pub(crate) mod debug;
+mod debug;
 mod state;
+mod require;
+mod memory;
+mod bytecode_cache;
+mod profiler;
+mod script_error;
+mod log_history;
+mod timer_queue;
+mod thread_pool;
 mod scheduler;
 mod vm;
 mod inheritance;
 mod pointers;
 mod instance_repl_table;
 mod instance_tag_collection;
+mod class_database;
 mod rw_lock;
 mod watchdog;
+mod clock;
 mod fastflags;
+mod host;
 pub mod lua_macros;
 mod assert_gdext_api;
 
@@ -23,11 +34,23 @@ pub use rc::*;
 pub use vm::RobloxVM;
 pub use rw_lock::*;
 pub use state::{LuauState, registry_keys, get_current_identity, get_state, get_state_with_rwlock, get_thread_identity, ThreadIdentity};
-pub use scheduler::{ITaskScheduler, TaskScheduler, get_task_scheduler_from_lua, ParallelDispatch, GlobalTaskScheduler};
+pub use scheduler::{ITaskScheduler, TaskScheduler, get_task_scheduler_from_lua, ParallelDispatch, GlobalTaskScheduler,
+    CycleStats, QueueCounts, QueueKind, QueuedThread, TaskSchedulerSnapshot};
 pub use security::*;
+pub use class_database::*;
 pub use fastflags::*;
+pub use require::{RequireAlias, RequireConfig};
+pub use memory::StateMemoryUsage;
+pub use bytecode_cache::{BytecodeCache, CompileMode};
+pub use debug::{Debugger, DebugTransport, JsonValue};
+pub(crate) use debug::ChunkOwner;
+pub use profiler::{Profiler, ProfilerSample};
+pub use script_error::ScriptError;
+pub use log_history::{LogHistory, LogMessage};
+pub use host::{IVMHost, HostMessageKind, GodotVMHost, StdoutVMHost, MemoryVMHost};
 pub(self) use pointers::*;
 pub use watchdog::Watchdog;
+pub use clock::Clock;
 
 /// Provides a way to ignore borrowck for a specific borrow.
 /// **This function has been deprecated:** Under normal circumstances, this should never be done. This is only a temporary solution to a problem that requires more effort to fix properly.
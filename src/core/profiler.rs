@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use std::sync::Mutex;
+
+use r2g_mlua::ffi::lua_clock;
+use r2g_mlua::prelude::*;
+use r2g_mlua::Debug;
+
+use super::debug::{script_name, JsonValue};
+use super::{get_current_identity, get_state};
+
+/// A single sample of the call stack of a state.
+#[derive(Clone, Debug)]
+pub struct ProfilerSample {
+    /// Seconds since the profiler was started.
+    pub time: f64,
+    pub state: u32,
+    /// Full name of the script the running thread belongs to, if any.
+    pub script: Option<String>,
+    /// Frames of the call stack, from the outermost to the innermost.
+    pub stack: Vec<String>
+}
+
+#[derive(Debug, Default)]
+struct ProfilerData {
+    interval: f64,
+    started: f64,
+    last_sample: HashMap<usize, f64>,
+    /// Profiler ids and display names of the states, by LuauState pointer.
+    states: HashMap<usize, (u32, String)>,
+    /// The latest samples, the oldest ones are dropped once the capacity is reached.
+    samples: VecDeque<ProfilerSample>
+}
+
+/// Default number of samples a profiler keeps.
+const DEFAULT_CAPACITY: usize = 1_000_000;
+
+/// Sampling profiler for the Luau code of a VM. Like the watchdog, it is driven by the interrupt of each state,
+/// so only code reaching a safepoint (calls and loop iterations) is sampled.
+#[derive(Debug)]
+pub struct Profiler {
+    running: AtomicBool,
+    capacity: AtomicUsize,
+    data: Mutex<ProfilerData>
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler {
+            running: AtomicBool::new(false),
+            capacity: AtomicUsize::new(DEFAULT_CAPACITY),
+            data: Mutex::default()
+        }
+    }
+}
+
+fn frame_label(debug: &Debug) -> String {
+    let source = debug.source();
+    let name = debug.names().name.unwrap_or("<anonymous>".into());
+    if source.what == "C" {
+        format!("{} [C]", name)
+    } else {
+        let chunk = source.source.unwrap_or_default();
+        format!("{} ({}:{})", name, script_name(&chunk), source.line_defined.unwrap_or_default())
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running.load(Relaxed)
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Relaxed)
+    }
+    /// Sets how many samples are kept, dropping the oldest ones which don't fit.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Relaxed);
+        let mut data = self.data.lock().unwrap();
+        let excess = data.samples.len().saturating_sub(capacity);
+        data.samples.drain(..excess);
+    }
+    /// Clears the previous samples and starts sampling every `interval` seconds.
+    pub(super) fn start(&self, interval: f64) {
+        let mut data = self.data.lock().unwrap();
+        data.interval = interval;
+        data.started = unsafe { lua_clock() };
+        data.last_sample.clear();
+        data.samples.clear();
+        self.running.store(true, Relaxed);
+    }
+    pub(super) fn stop(&self) {
+        self.running.store(false, Relaxed);
+    }
+    pub(super) fn register_state(&self, state: usize, name: String) {
+        let mut data = self.data.lock().unwrap();
+        let id = data.states.len() as u32;
+        data.states.entry(state).or_insert((id, name));
+    }
+    /// Sets the interrupt of `lua` to sample its call stack.
+    pub(super) fn install(lua: &Lua) {
+        lua.set_interrupt(|lua| {
+            let state = get_state(lua);
+            state.get_profiler().sample(lua, &raw const *state as usize);
+            Ok(LuaVmState::Continue)
+        });
+    }
+
+    fn sample(&self, lua: &Lua, state: usize) {
+        if !self.is_running() {
+            return;
+        }
+        let now = unsafe { lua_clock() };
+        {
+            let mut data = self.data.lock().unwrap();
+            let interval = data.interval;
+            let last = data.last_sample.entry(state).or_insert(f64::NEG_INFINITY);
+            if now - *last < interval {
+                return;
+            }
+            *last = now;
+        }
+        let mut stack: Vec<String> = (0..)
+            .map_while(|level| lua.inspect_stack(level))
+            .map(|x| frame_label(&x))
+            .collect();
+        stack.reverse();
+        let script = get_current_identity(lua)
+            .and_then(|x| x.script.as_ref()?.upgrade())
+            .and_then(|x| x.get_full_name().ok());
+        let mut data = self.data.lock().unwrap();
+        let Some(&(id, _)) = data.states.get(&state) else { return };
+        let time = now - data.started;
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        if data.samples.len() >= capacity {
+            data.samples.pop_front();
+        }
+        data.samples.push_back(ProfilerSample { time, state: id, script, stack });
+    }
+
+    pub fn samples(&self) -> Vec<ProfilerSample> {
+        self.data.lock().unwrap().samples.iter().cloned().collect()
+    }
+    pub fn clear(&self) {
+        self.data.lock().unwrap().samples.clear();
+    }
+    /// Exports the samples in the collapsed stack format, one `frame;frame;frame count` line per unique stack,
+    /// as read by flamegraph.pl, inferno and speedscope. The outermost frame is the script.
+    pub fn to_collapsed_stacks(&self) -> String {
+        let mut stacks: BTreeMap<String, usize> = BTreeMap::new();
+        for sample in self.data.lock().unwrap().samples.iter() {
+            let mut key = sample.script.clone().unwrap_or("<unknown>".into());
+            for frame in sample.stack.iter() {
+                key.push(';');
+                key.push_str(&frame.replace(';', ":"));
+            }
+            *stacks.entry(key).or_default() += 1;
+        }
+        stacks.into_iter().fold(String::new(), |mut out, (stack, count)| {
+            let _ = writeln!(out, "{} {}", stack, count);
+            out
+        })
+    }
+    /// Exports the samples as Chrome trace event JSON, viewable in chrome://tracing or Perfetto.
+    /// Consecutive samples sharing frames are merged into a single event per frame, each state is a thread.
+    pub fn to_chrome_trace(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let to_us = |seconds: f64| JsonValue::Number((seconds * 1e6).round());
+        let mut events: Vec<JsonValue> = data.states.values()
+            .map(|(id, name)| JsonValue::object([
+                ("name", "thread_name".into()),
+                ("ph", "M".into()),
+                ("pid", 1.into()),
+                ("tid", (*id as i64).into()),
+                ("args", JsonValue::object([("name", name.as_str().into())]))
+            ]))
+            .collect();
+        let mut by_state: BTreeMap<u32, Vec<&ProfilerSample>> = BTreeMap::new();
+        for sample in data.samples.iter() {
+            by_state.entry(sample.state).or_default().push(sample);
+        }
+        for (state, samples) in by_state {
+            let mut open: Vec<(&str, f64)> = Vec::new();
+            let mut end = 0.0;
+            let mut close = |open: &mut Vec<(&str, f64)>, keep: usize, end: f64| {
+                while open.len() > keep {
+                    let (name, start) = open.pop().unwrap();
+                    events.push(JsonValue::object([
+                        ("name", name.into()),
+                        ("cat", "luau".into()),
+                        ("ph", "X".into()),
+                        ("ts", to_us(start)),
+                        ("dur", to_us(end - start)),
+                        ("pid", 1.into()),
+                        ("tid", (state as i64).into())
+                    ]));
+                }
+            };
+            for sample in samples {
+                // Samples further apart than an interval mean the state was idle in between.
+                if sample.time - end > data.interval {
+                    close(&mut open, 0, end);
+                }
+                let frames: Vec<&str> = std::iter::once(sample.script.as_deref().unwrap_or("<unknown>"))
+                    .chain(sample.stack.iter().map(String::as_str))
+                    .collect();
+                let common = open.iter().zip(frames.iter()).take_while(|(a, b)| a.0 == **b).count();
+                close(&mut open, common, end);
+                open.extend(frames[common..].iter().map(|x| (*x, sample.time)));
+                end = sample.time + data.interval;
+            }
+            close(&mut open, 0, end);
+        }
+        JsonValue::object([("traceEvents", events.into()), ("displayTimeUnit", "ms".into())]).to_string()
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+
+use nanoserde::DeJson;
+use r2g_mlua::prelude::*;
+
+use crate::instance::{ManagedInstance, ModuleScript, WeakManagedInstance};
+
+use super::ParallelDispatch::Default;
+use super::{get_state, get_task_scheduler_from_lua, registry_keys, LuauState};
+
+/// State of a ModuleScript in a single LuauState's module cache.
+#[derive(Debug)]
+pub(super) enum ModuleStatus {
+    /// The module is being run by `thread`, other requiring threads are parked in `waiting`.
+    Loading {
+        thread: LuaThread,
+        waiting: Vec<LuaThread>
+    },
+    Loaded(LuaValue),
+    Failed
+}
+
+/// Modules of a single LuauState. Modules loaded from the filesystem are backed by a ModuleScript
+/// created on first require, so they share the cache with the ones from the instance tree.
+#[derive(Debug, Default)]
+pub(super) struct ModuleCache {
+    status: HashMap<WeakManagedInstance, ModuleStatus>,
+    /// The module each parked thread is waiting on, to find cycles going through several threads.
+    waiting_on: HashMap<*const c_void, (LuaThread, WeakManagedInstance)>,
+    files: HashMap<PathBuf, ManagedInstance>,
+    file_paths: HashMap<WeakManagedInstance, PathBuf>,
+    /// Aliases of the `.luaurc` of each directory, read once per state.
+    luaurc_aliases: HashMap<PathBuf, HashMap<String, RequireAlias>>
+}
+
+/// Target of a require alias, `@name/...` resolves against it.
+#[derive(Debug, Clone)]
+pub enum RequireAlias {
+    Instance(WeakManagedInstance),
+    Directory(PathBuf)
+}
+
+/// VM-wide configuration of string requires.
+#[derive(Debug, Default)]
+pub struct RequireConfig {
+    root: Option<PathBuf>,
+    aliases: HashMap<String, RequireAlias>,
+    // Canonical directories of the aliases added by the host, which modules can be loaded from.
+    host_directories: Vec<PathBuf>
+}
+
+#[derive(DeJson)]
+struct LuauRc {
+    aliases: Option<HashMap<String, String>>
+}
+
+/// A position string requires are resolved against.
+#[derive(Debug, Clone)]
+enum RequireLocation {
+    Instance(ManagedInstance),
+    Directory(PathBuf)
+}
+
+const REQUIRE_INVALID_ARGUMENT: &str = "Attempted to call require with invalid argument(s).";
+const REQUIRE_RECURSIVE: &str = "Requested module was required recursively";
+const REQUIRE_FAILED: &str = "Requested module experienced an error while loading";
+const REQUIRE_RESULT_COUNT: &str = "Module code did not return exactly one value";
+
+// The module has to run inside of Luau code so it is able to yield, native functions can't yield across calls.
+// The outer function binds the script string requires are resolved from.
+const REQUIRE_SOURCE: &str = r#"
+local begin_require, finish_require, fail_require = ...
+local yield, pack, error, pcall = coroutine.yield, table.pack, error, pcall
+return function(context)
+    return function(module)
+        local status, value, resolved = begin_require(context, module)
+        while status == "waiting" do
+            yield()
+            status, value, resolved = begin_require(context, module)
+        end
+        if status == "loaded" then
+            return value
+        elseif status == "error" then
+            error(value, 2)
+        end
+        local result = pack(pcall(value))
+        if not result[1] then
+            error(fail_require(resolved, result[2], false), 2)
+        elseif result.n ~= 2 then
+            error(fail_require(resolved, nil, true), 2)
+        end
+        finish_require(resolved, result[2])
+        return result[2]
+    end
+end
+"#;
+
+const LUAURC_NAME: &str = ".luaurc";
+const MODULE_EXTENSIONS: [&str; 2] = ["luau", "lua"];
+
+fn read_luaurc_aliases(dir: &Path) -> LuaResult<HashMap<String, RequireAlias>> {
+    let path = dir.join(LUAURC_NAME);
+    if !path.is_file() {
+        return Ok(HashMap::new());
+    }
+    let source = std::fs::read_to_string(&path)
+        .map_err(|err| LuaError::RuntimeError(format!("could not read {}: {}", path.display(), err)))?;
+    let rc = LuauRc::deserialize_json(source.as_str())
+        .map_err(|err| LuaError::RuntimeError(format!("could not parse {}: {}", path.display(), err)))?;
+    Ok(rc.aliases.unwrap_or_default().into_iter()
+        .map(|(name, target)| (name.to_lowercase(), RequireAlias::Directory(dir.join(target))))
+        .collect())
+}
+
+impl RequireConfig {
+    /// Sets the directory context-less requires resolve against and loads the aliases of its `.luaurc`.
+    /// Aliases added with [`RequireConfig::add_alias`] take priority over the ones in the file.
+    /// Module files can only be loaded from inside of the root or of a directory alias added with `add_alias`.
+    pub fn set_root(&mut self, root: PathBuf) -> LuaResult<()> {
+        let root = root.canonicalize()
+            .map_err(|err| LuaError::RuntimeError(format!("invalid require root {}: {}", root.display(), err)))?;
+        for (name, alias) in read_luaurc_aliases(&root)? {
+            self.aliases.entry(name).or_insert(alias);
+        }
+        self.root = Some(root);
+        Ok(())
+    }
+    pub fn get_root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+    /// Adds an alias, names are case-insensitive. Modules can only be loaded from a directory alias
+    /// if the directory exists when it is added.
+    pub fn add_alias(&mut self, name: &str, alias: RequireAlias) {
+        if let RequireAlias::Directory(dir) = &alias {
+            if let Ok(dir) = dir.canonicalize() {
+                self.host_directories.push(dir);
+            }
+        }
+        self.aliases.insert(name.to_lowercase(), alias);
+    }
+    pub fn get_alias(&self, name: &str) -> Option<&RequireAlias> {
+        self.aliases.get(&name.to_lowercase())
+    }
+    /// Whether `path`, which must be canonical, is inside of the root or of a directory alias added with `add_alias`.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.root.iter()
+            .chain(self.host_directories.iter())
+            .any(|x| path.starts_with(x))
+    }
+}
+
+impl ModuleCache {
+    /// Gets the ModuleScript backing a module file, creating it on first use.
+    fn get_file_module(&mut self, path: PathBuf) -> LuaResult<ManagedInstance> {
+        if let Some(module) = self.files.get(&path) {
+            return Ok(module.clone());
+        }
+        let source = std::fs::read_to_string(&path)
+            .map_err(|err| LuaError::RuntimeError(format!("could not read module {}: {}", path.display(), err)))?;
+        let name = match path.file_stem().and_then(|x| x.to_str()) {
+            Some("init") => path.parent().and_then(|x| x.file_name()),
+            _ => path.file_stem()
+        }.map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+
+        let module = ModuleScript::new();
+        module.set_name(name)?;
+        module.clone().cast_from_unsized::<ModuleScript>().unwrap().set_source(source);
+        self.file_paths.insert(module.downgrade(), path.clone());
+        self.files.insert(path, module.clone());
+        Ok(module)
+    }
+    fn get_luaurc_aliases(&mut self, dir: &Path) -> LuaResult<&HashMap<String, RequireAlias>> {
+        if !self.luaurc_aliases.contains_key(dir) {
+            let aliases = read_luaurc_aliases(dir)?;
+            self.luaurc_aliases.insert(dir.to_path_buf(), aliases);
+        }
+        Ok(&self.luaurc_aliases[dir])
+    }
+    /// Whether `thread` waiting on `module` would never wake up, because the thread loading it waits,
+    /// directly or through other threads, on a module `thread` is loading.
+    fn would_deadlock(&self, thread: &LuaThread, module: &WeakManagedInstance) -> bool {
+        let mut module = module;
+        // A chain can't be longer than the number of modules, a longer one is a cycle `thread` isn't part of.
+        for _ in 0..=self.status.len() {
+            let Some(ModuleStatus::Loading { thread: loader, .. }) = self.status.get(module) else {
+                return false;
+            };
+            if loader.to_pointer() == thread.to_pointer() {
+                return true;
+            }
+            match self.waiting_on.get(&loader.to_pointer()) {
+                Some((_, next)) => module = next,
+                None => return false
+            }
+        }
+        false
+    }
+}
+
+fn child_location(location: RequireLocation, name: &str, path: &str) -> LuaResult<RequireLocation> {
+    match location {
+        RequireLocation::Instance(instance) => {
+            for child in instance.get_children()? {
+                if child.get_name() == name {
+                    return Ok(RequireLocation::Instance(child));
+                }
+            }
+            Err(LuaError::RuntimeError(format!(
+                "could not resolve require path \"{}\": {} is not a valid member of {}", path, name, instance.get_full_name()?
+            )))
+        }
+        RequireLocation::Directory(dir) => Ok(RequireLocation::Directory(dir.join(name)))
+    }
+}
+
+fn parent_location(location: RequireLocation, path: &str) -> LuaResult<RequireLocation> {
+    let parent = match &location {
+        RequireLocation::Instance(instance) => instance.get_parent().map(RequireLocation::Instance),
+        RequireLocation::Directory(dir) => dir.parent().map(|x| RequireLocation::Directory(x.to_path_buf()))
+    };
+    parent.ok_or_else(|| LuaError::RuntimeError(format!("could not resolve require path \"{}\": location has no parent", path)))
+}
+
+/// Gets the directories `./` and `@self` refer to inside of a module file.
+fn file_module_locations(file: &Path) -> (PathBuf, PathBuf) {
+    let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+    if file.file_stem().and_then(|x| x.to_str()) == Some("init") {
+        (dir.parent().map(Path::to_path_buf).unwrap_or_default(), dir)
+    } else {
+        (dir, file.with_extension(""))
+    }
+}
+
+fn find_alias(lua: &Lua, context_file: Option<&Path>, name: &str) -> LuaResult<RequireLocation> {
+    // .luaurc files closer to the requiring file take priority over the VM-wide configuration.
+    // Only the directories modules can be loaded from are searched.
+    if let Some(file) = context_file {
+        let name = name.to_lowercase();
+        for dir in file.ancestors().skip(1).take_while(|x| get_state(lua).get_vm().get_require_config().contains(x)) {
+            if let Some(RequireAlias::Directory(dir)) = get_state(lua).modules.get_luaurc_aliases(dir)?.get(&name) {
+                return Ok(RequireLocation::Directory(dir.clone()));
+            }
+        }
+    }
+    let vm = get_state(lua).get_vm();
+    match vm.get_require_config().get_alias(name) {
+        Some(RequireAlias::Directory(dir)) => Ok(RequireLocation::Directory(dir.clone())),
+        Some(RequireAlias::Instance(instance)) => instance.upgrade()
+            .map(RequireLocation::Instance)
+            .ok_or_else(|| LuaError::RuntimeError(format!("require alias @{} points to a destroyed instance", name))),
+        None => Err(LuaError::RuntimeError(format!("@{} is not a valid alias", name)))
+    }
+}
+
+/// Resolves a require-by-string path, relative paths are resolved against `context`.
+fn resolve_module_path(lua: &Lua, context: Option<ManagedInstance>, path: &str) -> LuaResult<ManagedInstance> {
+    let context_file = context.as_ref()
+        .and_then(|x| get_state(lua).modules.file_paths.get(&x.downgrade()).cloned());
+    let mut components = path.split('/');
+    let prefix = components.next().unwrap_or_default();
+
+    let location_of_context = || -> LuaResult<RequireLocation> {
+        match (&context_file, &context) {
+            (Some(file), _) => Ok(RequireLocation::Directory(file_module_locations(file).0)),
+            (None, Some(script)) => parent_location(RequireLocation::Instance(script.clone()), path),
+            (None, None) => get_state(lua).get_vm().get_require_config().get_root()
+                .map(|x| RequireLocation::Directory(x.to_path_buf()))
+                .ok_or_else(|| LuaError::RuntimeError(
+                    format!("could not resolve require path \"{}\": no script or require root to resolve against", path)
+                ))
+        }
+    };
+    let mut location = match prefix {
+        "." => location_of_context()?,
+        ".." => parent_location(location_of_context()?, path)?,
+        "@self" => match (&context_file, &context) {
+            (Some(file), _) => RequireLocation::Directory(file_module_locations(file).1),
+            (None, Some(script)) => RequireLocation::Instance(script.clone()),
+            (None, None) => return Err(LuaError::RuntimeError(
+                format!("could not resolve require path \"{}\": @self can only be used inside of a script", path)
+            ))
+        },
+        alias if alias.len() > 1 && alias.starts_with('@') => find_alias(lua, context_file.as_deref(), &alias[1..])?,
+        _ => return Err(LuaError::RuntimeError(
+            "require path must start with a valid prefix: ./, ../, or @".into()
+        ))
+    };
+    for component in components {
+        location = match component {
+            "" | "." => return Err(LuaError::RuntimeError(format!("require path \"{}\" contains an empty component", path))),
+            ".." => parent_location(location, path)?,
+            name => child_location(location, name, path)?
+        };
+    }
+
+    match location {
+        RequireLocation::Instance(instance) if instance.get_class_name() == "ModuleScript" => Ok(instance),
+        RequireLocation::Instance(instance) => Err(LuaError::RuntimeError(
+            format!("could not resolve require path \"{}\": {} is not a ModuleScript", path, instance.get_full_name()?)
+        )),
+        RequireLocation::Directory(dir) => {
+            let candidates = MODULE_EXTENSIONS.iter().map(|ext| dir.with_extension(ext))
+                .chain(MODULE_EXTENSIONS.iter().map(|ext| dir.join("init").with_extension(ext)));
+            for candidate in candidates {
+                if !candidate.is_file() {
+                    continue;
+                }
+                let Ok(file) = candidate.canonicalize() else {
+                    continue;
+                };
+                // `..` components and symlinks can't reach files outside of the require root.
+                if !get_state(lua).get_vm().get_require_config().contains(&file) {
+                    return Err(LuaError::RuntimeError(
+                        format!("could not resolve require path \"{}\": {} is outside of the require root", path, file.display())
+                    ));
+                }
+                return get_state(lua).modules.get_file_module(file);
+            }
+            Err(LuaError::RuntimeError(format!("could not resolve require path \"{}\": no module found at {}", path, dir.display())))
+        }
+    }
+}
+
+fn get_module_script(module: LuaValue, lua: &Lua) -> LuaResult<(ManagedInstance, WeakManagedInstance)> {
+    ManagedInstance::from_lua(module, lua).ok()
+        .filter(|x| x.get_class_name() == "ModuleScript")
+        .map(|x| { let weak = x.downgrade(); (x, weak) })
+        .ok_or_else(|| LuaError::RuntimeError(REQUIRE_INVALID_ARGUMENT.into()))
+}
+
+fn begin_require(lua: &Lua, (context, module): (Option<ManagedInstance>, LuaValue)) -> LuaResult<(&'static str, LuaValue, LuaValue)> {
+    let module = match module {
+        LuaValue::String(path) => resolve_module_path(lua, context, &path.to_str()?),
+        module => get_module_script(module, lua).map(|(x, _)| x)
+    };
+    let resolved = match &module {
+        Ok(module) => module.clone().into_lua(lua)?,
+        Err(_) => LuaNil
+    };
+    match module.and_then(|module| try_begin_require(lua, module)) {
+        Ok((status, value)) => Ok((status, value, resolved)),
+        Err(LuaError::RuntimeError(message)) => Ok(("error", LuaValue::String(lua.create_string(message)?), resolved)),
+        Err(err) => Err(err)
+    }
+}
+
+fn try_begin_require(lua: &Lua, module: ManagedInstance) -> LuaResult<(&'static str, LuaValue)> {
+    let weak = module.downgrade();
+    let state = get_state(lua);
+    let current = lua.current_thread();
+    state.modules.waiting_on.remove(&current.to_pointer());
+    match state.modules.status.get(&weak) {
+        Some(ModuleStatus::Loaded(value)) => return Ok(("loaded", value.clone())),
+        Some(ModuleStatus::Failed) => return Err(LuaError::RuntimeError(REQUIRE_FAILED.into())),
+        Some(ModuleStatus::Loading { thread, .. }) => {
+            // The loading thread was killed without settling the module.
+            if !matches!(thread.status(), LuaThreadStatus::Resumable | LuaThreadStatus::Running) {
+                settle_module(lua, weak, ModuleStatus::Failed)?;
+                return Err(LuaError::RuntimeError(REQUIRE_FAILED.into()));
+            }
+            if state.modules.would_deadlock(&current, &weak) {
+                return Err(LuaError::RuntimeError(REQUIRE_RECURSIVE.into()));
+            }
+            if let Some(ModuleStatus::Loading { waiting, .. }) = state.modules.status.get_mut(&weak) {
+                waiting.push(current.clone());
+            }
+            state.modules.waiting_on.insert(current.to_pointer(), (current, weak));
+            return Ok(("waiting", LuaNil));
+        }
+        None => ()
+    }
+    let source = module.clone().cast_from_unsized::<ModuleScript>().unwrap().get_source();
+    let chunk_name = match state.modules.file_paths.get(&weak) {
+        Some(path) => format!("<module at {}>", path.display()),
+        None => format!("<module at {}>", module.get_full_name()?)
+    };
+    let env = state.create_script_env(module.clone())?;
+    match state.compile_script(&module, chunk_name.as_str(), source.as_str(), env) {
+        Ok(func) => {
+            state.modules.status.insert(weak, ModuleStatus::Loading { thread: current, waiting: Vec::new() });
+            Ok(("load", LuaValue::Function(func)))
+        }
+        Err(err) => {
+            state.get_vm().log_err(lua,
+                format!("Error occured while compiling module {}: {}", module.get_full_name()?, err).into_lua_multi(lua)?
+            );
+            state.modules.status.insert(weak, ModuleStatus::Failed);
+            Err(LuaError::RuntimeError(REQUIRE_FAILED.into()))
+        }
+    }
+}
+
+/// Stores the result of a module and wakes up the threads which were waiting on it.
+fn settle_module(lua: &Lua, weak: WeakManagedInstance, status: ModuleStatus) -> LuaResult<()> {
+    let modules = &mut get_state(lua).modules;
+    let previous = modules.status.insert(weak, status);
+    if let Some(ModuleStatus::Loading { waiting, .. }) = previous {
+        for thread in waiting.iter() {
+            modules.waiting_on.remove(&thread.to_pointer());
+        }
+        for thread in waiting {
+            if thread.status() == LuaThreadStatus::Resumable {
+                get_task_scheduler_from_lua(lua).defer_thread(thread, Default)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails the modules `thread` was loading when it is killed, so the threads waiting on them wake up.
+pub(super) fn release_module_thread(lua: &Lua, thread: &LuaThread) -> LuaResult<()> {
+    let modules = &mut get_state(lua).modules;
+    modules.waiting_on.remove(&thread.to_pointer());
+    let loading: Vec<WeakManagedInstance> = modules.status.iter()
+        .filter(|(_, status)| matches!(status, ModuleStatus::Loading { thread: loader, .. } if loader.to_pointer() == thread.to_pointer()))
+        .map(|(module, _)| module.clone())
+        .collect();
+    for module in loading {
+        settle_module(lua, module, ModuleStatus::Failed)?;
+    }
+    Ok(())
+}
+
+fn finish_require(lua: &Lua, (module, value): (LuaValue, LuaValue)) -> LuaResult<()> {
+    let (_, weak) = get_module_script(module, lua)?;
+    settle_module(lua, weak, ModuleStatus::Loaded(value))
+}
+
+fn fail_require(lua: &Lua, (module, err, bad_result): (LuaValue, LuaValue, bool)) -> LuaResult<&'static str> {
+    let (module, weak) = get_module_script(module, lua)?;
+    settle_module(lua, weak, ModuleStatus::Failed)?;
+    if bad_result {
+        return Ok(REQUIRE_RESULT_COUNT);
+    }
+    get_state(lua).get_vm().log_err(lua,
+        format!("Error occured while loading module {}: {}", module.get_full_name()?, err.to_string()?).into_lua_multi(lua)?
+    );
+    Ok(REQUIRE_FAILED)
+}
+
+impl LuauState {
+    /// Creates the function returning `require` functions bound to a script.
+    pub(super) fn create_require_factory(&mut self) -> LuaResult<LuaFunction> {
+        let lua = self.get_lua();
+        lua.load(REQUIRE_SOURCE)
+            .set_name("=require")
+            .call((
+                lua.create_function(begin_require)?,
+                lua.create_function(finish_require)?,
+                lua.create_function(fail_require)?
+            ))
+    }
+    /// Creates a `require` function, string paths are resolved relative to `script`.
+    pub fn create_require_function(&mut self, script: Option<ManagedInstance>) -> LuaResult<LuaFunction> {
+        self.get_lua()
+            .named_registry_value::<LuaFunction>(registry_keys::REQUIRE_FACTORY)?
+            .call(script)
+    }
+}
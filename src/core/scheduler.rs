@@ -1,18 +1,105 @@
-use std::{ffi::c_int, mem::take, ptr::slice_from_raw_parts};
+use std::{collections::HashMap, ffi::{c_int, c_void}, mem::take, sync::Mutex, time::Instant};
 
-use r2g_mlua::{ffi::{self, luaL_checknumber, lua_State, lua_gettop, lua_pushnumber, lua_resume, lua_settop, lua_tothread, lua_type, lua_typename, lua_xmove, lua_yield, LUA_ERRRUN}, prelude::*};
+use r2g_mlua::{ffi::{self, luaL_checknumber, lua_State, lua_pushnumber, lua_settop, lua_tothread, lua_yield}, prelude::*};
 use crate::instance::WeakManagedInstance;
+use crate::userdata::MarshalledValues;
 
-use super::{borrowck_ignore_mut, get_state, get_thread_identity, registry_keys, RobloxVM, RwLockWriteGuard};
+use super::require::release_module_thread;
+use super::script_error::resume_thread;
+use super::timer_queue::{Timer, TimerKind, TimerQueue};
+use super::thread_pool::ThreadPool;
+use super::{borrowck_ignore_mut, get_state, get_thread_identity, registry_keys, FastFlag, LuauState, RobloxVM, RwLockWriteGuard, Trc, GLOBAL_LOCKS_OF_THREAD};
 
 #[derive(Debug)]
 pub struct TaskScheduler {
     defer_threads: [Vec<(LuaThread, u32)>; 2],
-    delay_threads: [Vec<(LuaThread, u32, f64)>; 2],
-    // Wait threads need to be resumed wih how much time it actually elapsed since they got pushed.
-    // The first one is for when it needs to resume, the second one when it got pushed.
-    wait_threads: [Vec<(LuaThread, u32, f64, f64)>; 2], 
-    parallel_dispatch: bool
+    // Threads sleeping in task.delay and task.wait.
+    timers: [TimerQueue; 2],
+    // Stats of the last completed cycles, and the threads resumed since the scheduler was created.
+    cycle_stats: [CycleStats; 2],
+    defer_resumes: [usize; 2],
+    delay_resumes: [usize; 2],
+    parallel_dispatch: bool,
+    // Signal callbacks of this state fired from other states, see ITaskScheduler::defer_foreign_func.
+    foreign_calls: Mutex<Vec<ForeignCall>>,
+    // Handlers of signals fired in deferred mode, resumed at the next invocation point.
+    deferred_events: Vec<DeferredEvent>,
+    // The signal whose deferred handler is running, with its re-entrancy depth.
+    event_context: Option<(usize, u32)>,
+    // Threads parked on a signal, with the token of their wait, see ITaskScheduler::park.
+    signal_waits: HashMap<*const c_void, u64>,
+    next_signal_wait: u64
+}
+
+/// How many times in a row the handlers of a deferred signal can fire it again.
+const MAX_EVENT_REENTRANCY_DEPTH: u32 = 10;
+
+#[derive(Debug)]
+struct DeferredEvent {
+    thread: LuaThread,
+    args: u32,
+    signal: usize,
+    depth: u32
+}
+
+#[derive(Debug)]
+enum ForeignTarget {
+    Function(LuaFunction),
+    /// A thread parked on a signal, with the token of its wait.
+    Thread(LuaThread, u64)
+}
+
+#[derive(Debug)]
+struct ForeignCall {
+    target: ForeignTarget,
+    args: MarshalledValues,
+    parallel: ParallelDispatch
+}
+
+/// Work done by the last defer and delay cycles of one dispatch table.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CycleStats {
+    pub defer_resumes: usize,
+    pub delay_resumes: usize,
+    /// Wall time spent in the cycles, in seconds.
+    pub defer_time: f64,
+    pub delay_time: f64
+}
+
+/// Threads queued in one dispatch table.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct QueueCounts {
+    pub deferred: usize,
+    pub delayed: usize,
+    pub waiting: usize
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QueueKind {
+    Deferred,
+    Delayed,
+    Waiting
+}
+
+#[derive(Clone, Debug)]
+pub struct QueuedThread {
+    pub kind: QueueKind,
+    /// Either Synchronized or Desynchronized.
+    pub dispatch: ParallelDispatch,
+    /// The script owning the thread, if any.
+    pub script: Option<WeakManagedInstance>,
+    /// Clock time the thread resumes at, for delayed and waiting threads.
+    pub wake_time: Option<f64>
+}
+
+/// Read-only state of a task scheduler, see [`ITaskScheduler::snapshot`].
+#[derive(Clone, Default, Debug)]
+pub struct TaskSchedulerSnapshot {
+    pub synchronized: QueueCounts,
+    pub desynchronized: QueueCounts,
+    pub synchronized_cycle: CycleStats,
+    pub desynchronized_cycle: CycleStats,
+    pub threads: Vec<QueuedThread>
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -45,12 +132,19 @@ impl TaskScheduler {
     pub fn as_dyn_mut(&mut self) -> &mut dyn ITaskScheduler {
         unsafe { &mut *((&raw mut *self) as *mut dyn ITaskScheduler) }
     }
-    pub(crate) const fn new() -> TaskScheduler {
+    pub(crate) fn new() -> TaskScheduler {
         TaskScheduler {
             defer_threads: [Vec::new(),Vec::new()],
-            delay_threads: [Vec::new(),Vec::new()],
-            wait_threads: [Vec::new(),Vec::new()],
-            parallel_dispatch: false
+            timers: [TimerQueue::default(), TimerQueue::default()],
+            cycle_stats: [CycleStats::default(); 2],
+            defer_resumes: [0; 2],
+            delay_resumes: [0; 2],
+            parallel_dispatch: false,
+            foreign_calls: Mutex::new(Vec::new()),
+            deferred_events: Vec::new(),
+            event_context: None,
+            signal_waits: HashMap::new(),
+            next_signal_wait: 0
         }
     }
 }
@@ -60,8 +154,8 @@ impl ITaskScheduler for TaskScheduler {
 }
 impl dyn ITaskScheduler {
     #[inline(always)]
-    fn clock() -> f64 {
-        unsafe { ffi::lua_clock() }
+    fn clock(lua: &Lua) -> f64 {
+        get_state(lua).get_clock().now()
     }
     #[inline(always)]
     fn dispatch_to_table(&self, parallel: ParallelDispatch) -> usize {
@@ -73,11 +167,30 @@ impl dyn ITaskScheduler {
     }
     pub fn spawn_func(&self, lua: &Lua, func: LuaFunction, args: impl IntoLuaMulti) -> LuaResult<LuaThread> {
         let thread = lua.create_thread(func)?;
-        let _: LuaResult<()> = thread.resume(args);
+        self.resume_with(lua, thread.clone(), args)?;
         Ok(thread)
     }
-    pub fn spawn_thread(&self, thr: LuaThread) -> LuaResult<LuaThread> {
-        let _ = thr.resume::<()>(());
+    /// Resumes `thread` right away with `args`.
+    pub fn resume_with(&self, lua: &Lua, thread: LuaThread, args: impl IntoLuaMulti) -> LuaResult<()> {
+        unsafe {
+            lua.exec_raw::<()>((thread, args), |lua_raw: *mut lua_State| {
+                let args_count = ffi::lua_gettop(lua_raw) - 1;
+                let thread = ffi::lua_tothread(lua_raw, 1);
+                ffi::lua_xmove(lua_raw, thread, args_count);
+                resume_thread(lua, lua_raw, thread, args_count);
+                ffi::lua_settop(lua_raw, 0);
+            })
+        }
+    }
+    pub fn spawn_thread(&self, lua: &Lua, thr: LuaThread) -> LuaResult<LuaThread> {
+        if thr.status() == LuaThreadStatus::Resumable {
+            unsafe {
+                lua.exec_raw::<()>((thr.clone(),), |lua_raw: *mut lua_State| {
+                    resume_thread(lua, lua_raw, lua_tothread(lua_raw, 1), 0);
+                    lua_settop(lua_raw, 0);
+                })?;
+            }
+        }
         Ok(thr)
     }
     pub fn defer_high_priority<F, A, R>(&mut self, lua: &Lua, args: impl IntoLuaMulti, parallel: ParallelDispatch, f: F) -> LuaResult<LuaThread> 
@@ -125,8 +238,17 @@ impl dyn ITaskScheduler {
         Ok(thread)
     }
     pub fn defer_func<'a, 'b>(&'a mut self, lua: &'b Lua, func: LuaFunction, args: impl IntoLuaMulti, parallel: ParallelDispatch) -> LuaResult<LuaThread> {
-        let parallel = self.dispatch_to_table(parallel);
         let thread = lua.create_thread(func)?;
+        self.defer_thread_with(lua, thread, args, parallel)
+    }
+    /// Defers `thread`, resuming it with `args`.
+    pub fn defer_thread_with(&mut self, lua: &Lua, thread: LuaThread, args: impl IntoLuaMulti, parallel: ParallelDispatch) -> LuaResult<LuaThread> {
+        let parallel = self.dispatch_to_table(parallel);
+        let args_count = Self::move_args(lua, &thread, args)?;
+        self.get_task_scheduler_mut().defer_threads[parallel].push((thread.clone(), args_count));
+        Ok(thread)
+    }
+    fn move_args(lua: &Lua, thread: &LuaThread, args: impl IntoLuaMulti) -> LuaResult<u32> {
         let mut args_count: i32 = 0;
         unsafe { 
             lua.exec_raw::<()>((thread.clone(), args),|lua_raw: *mut lua_State| {
@@ -137,9 +259,108 @@ impl dyn ITaskScheduler {
                 ffi::lua_settop(lua_raw, 0); // clear stack
             })?;
         }
-        self.get_task_scheduler_mut().defer_threads[parallel].push((thread.clone(), args_count as u32));
+        Ok(args_count as u32)
+    }
+    /// Queues `thread` as a handler of `signal` fired in deferred mode. Synchronized handlers are resumed at the next invocation point,
+    /// which is the end of the current resumption of the scheduler, and errors once handlers of `signal` fired it again too many times.
+    pub fn defer_event(&mut self, lua: &Lua, thread: LuaThread, args: impl IntoLuaMulti, parallel: ParallelDispatch, signal: usize) -> LuaResult<LuaThread> {
+        if self.dispatch_to_table(parallel) == 1 {
+            return self.defer_thread_with(lua, thread, args, parallel);
+        }
+        let depth = match self.get_task_scheduler().event_context {
+            Some((current, depth)) if current == signal => depth + 1,
+            _ => 1
+        };
+        if depth > MAX_EVENT_REENTRANCY_DEPTH {
+            return Err(LuaError::RuntimeError("Maximum event re-entrancy depth exceeded".into()));
+        }
+        let args = Self::move_args(lua, &thread, args)?;
+        self.get_task_scheduler_mut().deferred_events.push(DeferredEvent { thread: thread.clone(), args, signal, depth });
         Ok(thread)
     }
+    /// Parks the current thread on a signal, until the signal resumes it or `timeout` seconds passed.
+    /// Returns the token the signal passes to [`Self::unpark`].
+    pub fn park(&mut self, lua: &Lua, timeout: Option<f64>) -> u64 {
+        let thread = lua.current_thread();
+        let dispatch = self.dispatch_to_table(ParallelDispatch::Default);
+        let task = self.get_task_scheduler_mut();
+        let token = task.next_signal_wait;
+        task.next_signal_wait += 1;
+        task.signal_waits.insert(thread.to_pointer(), token);
+        if let Some(timeout) = timeout {
+            let now = Self::clock(lua);
+            self.get_task_scheduler_mut().timers[dispatch].push(thread, TimerKind::Timeout, now + timeout);
+        }
+        token
+    }
+    /// Whether `thread` is still in the wait `token` started by [`Self::park`].
+    pub fn is_parked(&self, thread: &LuaThread, token: u64) -> bool {
+        self.get_task_scheduler().signal_waits.get(&thread.to_pointer()) == Some(&token)
+    }
+    /// Ends the wait `token` of `thread` before it is resumed. False if the thread left that wait, e.g. because it timed out.
+    pub fn unpark(&mut self, thread: &LuaThread, token: u64) -> bool {
+        if !self.is_parked(thread, token) {
+            return false;
+        }
+        let task = self.get_task_scheduler_mut();
+        task.signal_waits.remove(&thread.to_pointer());
+        for timers in &mut task.timers {
+            timers.cancel_timeout(thread);
+        }
+        true
+    }
+    /// Resumes the queued handlers of deferred signals, and the handlers of the signals they fire.
+    /// Handlers are synchronized, so they wait for the next synchronized resumption when queued in parallel.
+    pub fn resume_deferred_events(&mut self, lua: &Lua) -> LuaResult<()> {
+        if self.get_task_scheduler().parallel_dispatch {
+            return Ok(());
+        }
+        while !self.get_task_scheduler().deferred_events.is_empty() && !self.watchdog_check(lua) {
+            let events = take(&mut self.get_task_scheduler_mut().deferred_events);
+            for DeferredEvent { thread, args, signal, depth } in events {
+                if thread.status() != LuaThreadStatus::Resumable {
+                    continue;
+                }
+                let context = self.get_task_scheduler_mut().event_context.replace((signal, depth));
+                let result = unsafe {
+                    lua.exec_raw::<()>((thread,), |lua_raw: *mut lua_State| {
+                        let thread = lua_tothread(lua_raw, 1);
+                        resume_thread(lua, lua_raw, thread, args as i32);
+                        lua_settop(lua_raw, 0);
+                    })
+                };
+                self.get_task_scheduler_mut().event_context = context;
+                result?;
+                self.get_task_scheduler_mut().defer_resumes[0] += 1;
+            }
+        }
+        Ok(())
+    }
+    /// Defers `func`, which belongs to the state of this scheduler, from another state. Can be called from any thread,
+    /// `args` are recreated in this state at the start of its next synchronized defer cycle.
+    pub fn defer_foreign_func(&self, func: LuaFunction, args: MarshalledValues, parallel: ParallelDispatch) {
+        self.get_task_scheduler().foreign_calls.lock().unwrap().push(ForeignCall { target: ForeignTarget::Function(func), args, parallel });
+    }
+    /// Same as [`Self::defer_foreign_func`], for a thread of this state parked on a signal with `token`.
+    pub fn defer_foreign_resume(&self, thread: LuaThread, token: u64, args: MarshalledValues, parallel: ParallelDispatch) {
+        self.get_task_scheduler().foreign_calls.lock().unwrap().push(ForeignCall { target: ForeignTarget::Thread(thread, token), args, parallel });
+    }
+    fn defer_foreign_calls(&mut self, lua: &Lua) -> LuaResult<()> {
+        let calls = take(&mut *self.get_task_scheduler().foreign_calls.lock().unwrap());
+        for ForeignCall { target, args, parallel } in calls {
+            let args = args.into_lua_multi(lua)?;
+            match target {
+                ForeignTarget::Function(func) => self.defer_func(lua, func, args, parallel)?,
+                ForeignTarget::Thread(thread, token) => {
+                    if !self.unpark(&thread, token) {
+                        continue;
+                    }
+                    self.defer_thread_with(lua, thread, args, parallel)?
+                }
+            };
+        }
+        Ok(())
+    }
     pub fn defer_thread(&mut self, thread: LuaThread, parallel: ParallelDispatch) -> LuaResult<LuaThread> {
         let parallel = self.dispatch_to_table(parallel);
         self.get_task_scheduler_mut().defer_threads[parallel].push((thread.clone(), 0));
@@ -158,12 +379,12 @@ impl dyn ITaskScheduler {
                 ffi::lua_settop(lua_raw, 0); // clear stack
             })?;
         }
-        self.get_task_scheduler_mut().delay_threads[parallel].push((thread.clone(), args_count as u32, Self::clock() + seconds));
+        self.get_task_scheduler_mut().timers[parallel].push(thread.clone(), TimerKind::Delay(args_count as u32), Self::clock(lua) + seconds);
         Ok(thread)
     }
-    pub fn delay_thread(&mut self, thread: LuaThread, parallel: ParallelDispatch, seconds: f64) -> LuaResult<LuaThread> {
+    pub fn delay_thread(&mut self, lua: &Lua, thread: LuaThread, parallel: ParallelDispatch, seconds: f64) -> LuaResult<LuaThread> {
         let parallel = self.dispatch_to_table(parallel);
-        self.get_task_scheduler_mut().delay_threads[parallel].push((thread.clone(), 0, Self::clock() + seconds));
+        self.get_task_scheduler_mut().timers[parallel].push(thread.clone(), TimerKind::Delay(0), Self::clock(lua) + seconds);
         Ok(thread)
     }
     pub fn defer_single_cycle<'a>(&mut self, lua: &'a Lua, parallel: bool) -> LuaResult<bool> {
@@ -173,102 +394,138 @@ impl dyn ITaskScheduler {
             if thread.status() == LuaThreadStatus::Resumable {
                 lua.exec_raw::<()>((thread,), |lua_raw: *mut lua_State| {
                     let thread = lua_tothread(lua_raw, 1);
-                    let mut _nres = 0;
-                    if lua_resume(thread, lua_raw, args as i32, &raw mut _nres) == LUA_ERRRUN {
-                        lua_xmove(thread, lua_raw, 1);
-                        if lua_gettop(lua_raw) == 0 {
-                            ffi::lua_pushstring(lua_raw, "unknown error".as_ptr() as *const i8);
-                        }
-                        dbg!(lua_gettop(lua_raw));
-                        let typename = std::ffi::CStr::from_ptr(lua_typename(lua_raw, lua_type(lua_raw, -1)));
-                        dbg!(typename);
-                        let mut len = 0;
-                        let string_slice= 
-                            slice_from_raw_parts(ffi::lua_tolstring(lua_raw, -1, &raw mut len).cast(), len).as_ref().unwrap();
-                        let string = std::str::from_utf8(string_slice).unwrap();
-                        get_state(lua).get_vm().log_err(
-                            IntoLuaMulti::into_lua_multi(format!("Error in thread 0x{:x} during defer: {}", thread as usize, string), lua).unwrap()
-                        );
-                    }
+                    resume_thread(lua, lua_raw, thread, args as i32);
                     lua_settop(lua_raw, 0);
                 })?;
+                self.get_task_scheduler_mut().defer_resumes[parallel as usize] += 1;
+                self.resume_deferred_events(lua)?;
             }
         }}
         Ok(!self.get_task_scheduler_mut().defer_threads[parallel as usize].is_empty())
     }
     pub fn delay_single_cycle<'a>(&mut self, lua: &'a Lua, parallel: bool) -> LuaResult<bool> {
         self.get_task_scheduler_mut().parallel_dispatch = parallel;
-        let mut min = f64::MAX;
-        // Delay
-        {
-            let threads = take(&mut self.get_task_scheduler_mut().delay_threads[parallel as usize]);
-            let new_threads  = &mut self.get_task_scheduler_mut().delay_threads[parallel as usize];
-            new_threads.reserve(threads.len());
-            for (thread, args, time ) in threads { unsafe {
-                if Self::clock() >= time {
-                    if thread.status() == LuaThreadStatus::Resumable {
-                        lua.exec_raw::<()>((thread,), |lua_raw: *mut lua_State| {
-                            let thread = lua_tothread(lua_raw, 1);
-                            let mut _nres = 0;
-                            lua_resume(thread, lua_raw, args as i32, &raw mut _nres);
-                            lua_settop(lua_raw, 0);
-                        })?;
-                    }
-                } else if thread.status() == LuaThreadStatus::Resumable{
-                    min = min.min(time);
-                    new_threads.push((thread, args, time));
-                }
-            }}
+        let now = Self::clock(lua);
+        // Timers pushed by the threads resumed here wait for the next cycle, even if they are already due.
+        let mut due = Vec::new();
+        while let Some(timer) = self.get_task_scheduler_mut().timers[parallel as usize].pop_due(now) {
+            due.push(timer);
         }
-        // Wait
-        {
-            let threads = take(&mut self.get_task_scheduler_mut().wait_threads[parallel as usize]);
-            let new_threads  = &mut self.get_task_scheduler_mut().wait_threads[parallel as usize];
-            new_threads.reserve(threads.len());
-            for (thread, args, time , started) in threads { unsafe {
-                debug_assert!(thread.status() != LuaThreadStatus::Running);
-                if Self::clock() >= time {
-                    if thread.status() == LuaThreadStatus::Resumable {
-                        lua.exec_raw::<()>((thread,), |lua_raw: *mut lua_State| {
-                            let thread = lua_tothread(lua_raw, 1);
-                            let mut _nres = 0;
-                            lua_pushnumber(thread, Self::clock()-started);
-                            lua_resume(thread, lua_raw, 1, &raw mut _nres);
-                            lua_settop(lua_raw, 0);
-                        })?;
+        for Timer { thread, kind, .. } in due { unsafe {
+            debug_assert!(thread.status() != LuaThreadStatus::Running);
+            if thread.status() != LuaThreadStatus::Resumable {
+                continue;
+            }
+            if kind == TimerKind::Timeout {
+                self.get_task_scheduler_mut().signal_waits.remove(&thread.to_pointer());
+            }
+            lua.exec_raw::<()>((thread,), |lua_raw: *mut lua_State| {
+                let thread = lua_tothread(lua_raw, 1);
+                match kind {
+                    TimerKind::Delay(args) => resume_thread(lua, lua_raw, thread, args as i32),
+                    TimerKind::Timeout => {
+                        ffi::lua_pushnil(thread);
+                        resume_thread(lua, lua_raw, thread, 1)
+                    },
+                    TimerKind::Wait(started) => {
+                        lua_pushnumber(thread, now-started);
+                        resume_thread(lua, lua_raw, thread, 1)
                     }
-                } else if thread.status() == LuaThreadStatus::Resumable{
-                    min = min.min(time);
-                    new_threads.push((thread, args, time, started));
-                }
-            }}
-        }
-
-        Ok(min > Self::clock())
+                };
+                lua_settop(lua_raw, 0);
+            })?;
+            self.get_task_scheduler_mut().delay_resumes[parallel as usize] += 1;
+            self.resume_deferred_events(lua)?;
+        }}
+        Ok(self.get_task_scheduler_mut().timers[parallel as usize].next_wake_time().is_none_or(|x| x > Self::clock(lua)))
     }
     #[inline]
     fn watchdog_check(&self, lua: &Lua) -> bool {
         unsafe { get_state(lua).watchdog_check() }
     }
     pub fn defer_cycle<'a>(&mut self, lua: &'a Lua, parallel: bool) -> LuaResult<()> {
+        let started = Instant::now();
+        let resumes = self.get_task_scheduler().defer_resumes[parallel as usize];
+        if !parallel {
+            self.defer_foreign_calls(lua)?;
+            // Signals fired between frames, e.g. by the host.
+            self.resume_deferred_events(lua)?;
+        }
         while self.defer_single_cycle(lua, parallel)? && !self.watchdog_check(lua) {}
+        let task = self.get_task_scheduler_mut();
+        task.cycle_stats[parallel as usize].defer_resumes = task.defer_resumes[parallel as usize] - resumes;
+        task.cycle_stats[parallel as usize].defer_time = started.elapsed().as_secs_f64();
         Ok(())
     }
     pub fn delay_cycle(&mut self, lua: &Lua, parallel: bool) -> LuaResult<()> {
-        while self.delay_single_cycle(lua, parallel)? && !self.watchdog_check(lua) {}
+        let started = Instant::now();
+        let resumes = self.get_task_scheduler().delay_resumes[parallel as usize];
+        // A virtual clock doesn't move while the cycle runs, there is nothing to wait for.
+        let clock = get_state(lua).get_clock();
+        while self.delay_single_cycle(lua, parallel)? && !clock.is_virtual() && !self.watchdog_check(lua) {}
+        let task = self.get_task_scheduler_mut();
+        task.cycle_stats[parallel as usize].delay_resumes = task.delay_resumes[parallel as usize] - resumes;
+        task.cycle_stats[parallel as usize].delay_time = started.elapsed().as_secs_f64();
         Ok(())
     }
+    /// Counts and owners of the queued threads, with the stats of the last cycles.
+    pub fn snapshot(&self, lua: &Lua) -> TaskSchedulerSnapshot {
+        let task = self.get_task_scheduler();
+        let mut snapshot = TaskSchedulerSnapshot {
+            synchronized_cycle: task.cycle_stats[0],
+            desynchronized_cycle: task.cycle_stats[1],
+            ..Default::default()
+        };
+        for (parallel, dispatch) in [(0, ParallelDispatch::Synchronized), (1, ParallelDispatch::Desynchronized)] {
+            let script = |thread: &LuaThread| get_thread_identity(lua, thread).and_then(|x| x.script.clone());
+            let mut threads: Vec<QueuedThread> = task.defer_threads[parallel].iter()
+                .map(|(thread, _)| thread)
+                .chain(task.deferred_events.iter().filter(|_| parallel == 0).map(|x| &x.thread))
+                .map(|thread| QueuedThread { kind: QueueKind::Deferred, dispatch, script: script(thread), wake_time: None })
+                .collect();
+            threads.extend(task.timers[parallel].iter()
+                .map(|timer| QueuedThread {
+                    kind: match timer.kind {
+                        TimerKind::Delay(_) => QueueKind::Delayed,
+                        TimerKind::Wait(_) | TimerKind::Timeout => QueueKind::Waiting
+                    },
+                    dispatch,
+                    script: script(&timer.thread),
+                    wake_time: Some(timer.wake_time)
+                })
+            );
+            let counts = if parallel == 0 { &mut snapshot.synchronized } else { &mut snapshot.desynchronized };
+            for thread in &threads {
+                match thread.kind {
+                    QueueKind::Deferred => counts.deferred += 1,
+                    QueueKind::Delayed => counts.delayed += 1,
+                    QueueKind::Waiting => counts.waiting += 1
+                }
+            }
+            snapshot.threads.append(&mut threads);
+        }
+        snapshot
+    }
     pub fn is_desynchronized(&self) -> bool {
         self.get_task_scheduler().parallel_dispatch
     }
     pub fn cancel(&mut self, lua: &Lua, thread: &LuaThread) -> LuaResult<()> {
         match thread.status() {
-            LuaThreadStatus::Resumable => thread.close(),
+            LuaThreadStatus::Resumable => {
+                let task = self.get_task_scheduler_mut();
+                for timers in &mut task.timers {
+                    timers.cancel(thread);
+                }
+                task.signal_waits.remove(&thread.to_pointer());
+                thread.close()?;
+                release_module_thread(lua, thread)
+            },
             LuaThreadStatus::Running => {
                 let clone = thread.clone();
-                self.defer_high_priority(lua, (), ParallelDispatch::Default, move |_, ()|
-                    clone.close()
-                )?;
+                self.defer_high_priority(lua, (), ParallelDispatch::Default, move |lua, ()| {
+                    clone.close()?;
+                    release_module_thread(lua, &clone)
+                })?;
                 unsafe {
                     let _: () = lua.exec_raw((), |lua: *mut lua_State| {
                         ffi::lua_yield(lua, 0);
@@ -289,8 +546,8 @@ impl dyn ITaskScheduler {
                 .filter(|x| x.1.script.as_ref().map(|x| *script == *x).unwrap_or(false))
                 .map(|(thread, _)| thread.clone())
                 .collect();
-            v.append(&mut self.get_task_scheduler().delay_threads[parallel].iter()
-                .map(|(thread, _, _)| (thread, get_thread_identity(lua, thread)))
+            v.append(&mut self.get_task_scheduler().timers[parallel].iter()
+                .map(|timer| (&timer.thread, get_thread_identity(lua, &timer.thread)))
                 .filter(|x| x.1.is_some())
                 .map(|x| (x.0, unsafe { x.1.unwrap_unchecked() }))
                 .filter(|x| x.1.script.as_ref().map(|x| *script == *x).unwrap_or(false))
@@ -307,9 +564,8 @@ impl dyn ITaskScheduler {
     pub(super) fn push_wait(lua: &Lua, time: f64) -> LuaResult<()> {
         let task = get_task_scheduler_from_lua(lua);
         let dispatch = task.dispatch_to_table(ParallelDispatch::Default);
-        task.get_task_scheduler_mut().wait_threads[dispatch].push((
-            lua.current_thread(), 0, time+Self::clock(), Self::clock()
-        ));
+        let now = Self::clock(lua);
+        task.get_task_scheduler_mut().timers[dispatch].push(lua.current_thread(), TimerKind::Wait(now), time+now);
         Ok(())
     }
     pub(super) fn push_sync_desync(lua: &Lua, parallel: bool) -> LuaResult<bool> {
@@ -318,7 +574,7 @@ impl dyn ITaskScheduler {
         if dispatch == parallel as usize {
             Ok(false)
         } else {
-            task.get_task_scheduler_mut().defer_threads[dispatch].push((lua.current_thread(), 0));
+            task.get_task_scheduler_mut().defer_threads[parallel as usize].push((lua.current_thread(), 0));
             Ok(true)
         }
     }
@@ -353,6 +609,17 @@ impl dyn ITaskScheduler {
     }
 }
 
+/// A state handed to a worker thread for the parallel phase.
+struct StatePointer(*mut LuauState);
+
+unsafe impl Send for StatePointer {}
+
+impl StatePointer {
+    fn get(&self) -> *mut LuauState {
+        self.0
+    }
+}
+
 #[derive(Debug, Default)]
 #[repr(transparent)]
 pub struct GlobalTaskScheduler {
@@ -369,6 +636,7 @@ impl GlobalTaskScheduler {
         
         // SAFETY: This function avoids the borrow checker since the main state outlives global task scheduler.
         let main_state = unsafe { borrowck_ignore_mut(vm.get_main_state()) };
+        let main_state_ptr = &raw mut *main_state;
 
         let lua = main_state.get_lua().clone();
         let task = main_state.get_task_scheduler_mut();
@@ -377,9 +645,19 @@ impl GlobalTaskScheduler {
         vm.watchdog_reset();
         task.defer_cycle(&lua, false)?;
         task.delay_cycle(&lua, false)?;
+        let states = vm.get_all_states();
+        for state in states.iter() {
+            let state = unsafe { state.access() };
+            if state != main_state_ptr {
+                // SAFETY: Actor states are only used by the thread holding the VM outside of the parallel phase.
+                unsafe { Self::serial_cycle(state)? };
+            }
+        }
+        Self::parallel_phase(&mut vm, &states)?;
+        vm.fire_queued_signals(&lua);
         // todo! run service events
 
-        vm.get_all_states().iter().for_each(|state| {
+        states.iter().for_each(|state| {
             let write = state.write();
             write.gc();
         });
@@ -389,6 +667,68 @@ impl GlobalTaskScheduler {
         drop(vm);
         Ok(())
     }
+    unsafe fn serial_cycle(state: *mut LuauState) -> LuaResult<()> {
+        let state = state.as_mut().unwrap_unchecked();
+        let lua = state.get_lua().clone();
+        let task = state.get_task_scheduler_mut();
+        task.defer_cycle(&lua, false)?;
+        task.delay_cycle(&lua, false)
+    }
+    unsafe fn parallel_cycle(state: *mut LuauState) -> LuaResult<()> {
+        let state = state.as_mut().unwrap_unchecked();
+        let lua = state.get_lua().clone();
+        let task = state.get_task_scheduler_mut();
+        let result = task.defer_cycle(&lua, true).and_then(|_| task.delay_cycle(&lua, true));
+        // Code running between frames, e.g. signals fired by the host, is synchronized.
+        task.get_task_scheduler_mut().parallel_dispatch = false;
+        result
+    }
+    /// Resumes the desynchronized threads of every state, each state on its own worker thread unless the ParallelLuau flag is disabled.
+    /// Instance locks are enabled for the duration of the phase, and the VM lock is released so workers can read it.
+    fn parallel_phase(vm: &mut RwLockWriteGuard<RobloxVM>, states: &[Trc<LuauState>]) -> LuaResult<()> {
+        let states: Vec<*mut LuauState> = states.iter()
+            .map(|x| unsafe { x.access() })
+            .filter(|x| {
+                let task = unsafe { x.as_ref().unwrap_unchecked() }.get_task_scheduler().get_task_scheduler();
+                !task.defer_threads[1].is_empty() || task.timers[1].len() != 0
+            })
+            .collect();
+        if states.is_empty() {
+            return Ok(());
+        }
+        if states.len() == 1 || !vm.flags().get_bool(FastFlag::ParallelLuau) {
+            for state in states {
+                unsafe { Self::parallel_cycle(state)? };
+            }
+            return Ok(());
+        }
+        let global_lock = vm.get_global_lock();
+        let pool: *const ThreadPool = vm.get_thread_pool();
+        // Lua errors can't leave the worker thread, only their message does.
+        let jobs: Vec<Box<dyn FnOnce() -> Result<(), String> + Send>> = states.into_iter()
+            .map(|state| {
+                let state = StatePointer(state);
+                let global_lock = global_lock.clone();
+                Box::new(move || {
+                    GLOBAL_LOCKS_OF_THREAD.with_borrow_mut(|x| x.push(global_lock));
+                    // SAFETY: Every state is resumed by a single worker, states don't share their Lua VM.
+                    let result = unsafe { Self::parallel_cycle(state.get()) };
+                    GLOBAL_LOCKS_OF_THREAD.with_borrow_mut(|x| x.pop());
+                    result.map_err(|x| x.to_string())
+                }) as Box<dyn FnOnce() -> Result<(), String> + Send>
+            })
+            .collect();
+        unsafe { vm.set_global_lock_state(true) };
+        vm.set_parallel_phase(true);
+        let results = {
+            let _release = vm.guard_release();
+            // SAFETY: The pool is owned by the VM, which outlives the phase.
+            unsafe { pool.as_ref().unwrap_unchecked() }.run(jobs)
+        };
+        vm.set_parallel_phase(false);
+        unsafe { vm.set_global_lock_state(false) };
+        results.into_iter().collect::<Result<(), String>>().map_err(LuaError::RuntimeError)
+    }
     pub fn new() -> GlobalTaskScheduler {
         GlobalTaskScheduler {
             task: TaskScheduler::new()
@@ -0,0 +1,90 @@
+use std::ffi::{c_int, c_void, CStr};
+use std::fmt::Write;
+
+use r2g_mlua::ffi::{self, lua_Debug, lua_State};
+use r2g_mlua::prelude::*;
+
+use crate::instance::WeakManagedInstance;
+
+use super::debug::script_name;
+use super::get_state;
+
+/// An error raised by a thread which nothing caught.
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    pub message: String,
+    /// Frames from the innermost to the outermost, in the `debug.traceback` format, one per line.
+    pub stack_trace: String,
+    /// The script the thread belongs to, if any.
+    pub script: Option<WeakManagedInstance>
+}
+
+unsafe fn cstr_to_str<'a>(s: *const std::ffi::c_char) -> Option<&'a str> {
+    (!s.is_null()).then(|| CStr::from_ptr(s).to_str().unwrap_or("?"))
+}
+
+/// Formats the call stack of `thread`, which is kept after a failed resume.
+pub(super) unsafe fn traceback(thread: *mut lua_State) -> String {
+    let mut trace = String::new();
+    let mut ar: lua_Debug = std::mem::zeroed();
+    let mut level = 0;
+    while ffi::lua_getinfo(thread, level, c"sln".as_ptr(), &mut ar) != 0 {
+        level += 1;
+        if cstr_to_str(ar.what) == Some("C") {
+            continue;
+        }
+        let _ = write!(trace, "{}:{}", script_name(cstr_to_str(ar.source).unwrap_or("?")), ar.currentline);
+        if let Some(name) = cstr_to_str(ar.name) {
+            let _ = write!(trace, " function {}", name);
+        }
+        trace.push('\n');
+    }
+    trace
+}
+
+/// Converts the value on top of the stack of `l` like `tostring`, replacing it with the result.
+/// The value's `__tostring` runs protected, if it fails the name of the value's type is used instead.
+unsafe fn tostring_protected(l: *mut lua_State) -> String {
+    unsafe extern "C-unwind" fn tostring(l: *mut lua_State) -> c_int {
+        ffi::luaL_tolstring(l, 1, std::ptr::null_mut());
+        1
+    }
+    let type_name = cstr_to_str(ffi::luaL_typename(l, -1)).unwrap_or("?").to_owned();
+    ffi::lua_checkstack(l, 2);
+    ffi::lua_pushcfunction(l, tostring);
+    ffi::lua_insert(l, -2);
+    if ffi::lua_pcall(l, 1, 1, 0) != ffi::LUA_OK {
+        return type_name;
+    }
+    let mut len = 0;
+    let message = ffi::lua_tolstring(l, -1, &raw mut len);
+    String::from_utf8_lossy(std::slice::from_raw_parts(message.cast(), len)).into_owned()
+}
+
+/// Resumes `thread` with the `nargs` values on top of its stack, reporting the error it raises if it fails.
+/// `from` must be the thread running the scheduler, its stack is left untouched.
+pub(super) unsafe fn resume_thread(lua: &Lua, from: *mut lua_State, thread: *mut lua_State, nargs: c_int) -> c_int {
+    let mut _nres = 0;
+    let status = ffi::lua_resume(thread, from, nargs, &raw mut _nres);
+    if status != ffi::LUA_OK && status != ffi::LUA_YIELD {
+        report_thread_error(lua, from, thread);
+    }
+    status
+}
+
+/// Reports the error on top of the stack of `thread` through [`super::RobloxVM::report_script_error`].
+pub(super) unsafe fn report_thread_error(lua: &Lua, from: *mut lua_State, thread: *mut lua_State) {
+    let stack_trace = traceback(thread);
+    let top = ffi::lua_gettop(from);
+    let message = if ffi::lua_gettop(thread) == 0 {
+        "unknown error".to_owned()
+    } else {
+        ffi::lua_xmove(thread, from, 1);
+        tostring_protected(from)
+    };
+    ffi::lua_settop(from, top);
+    let state = get_state(lua);
+    let script = state.get_thread_identity_pointer(thread as *const c_void)
+        .and_then(|x| x.script.clone());
+    state.get_vm().report_script_error(lua, ScriptError { message, stack_trace, script });
+}
@@ -2,13 +2,12 @@ use std::mem::MaybeUninit;
 use std::{collections::HashMap, ffi::c_void, mem::transmute, ptr::addr_of_mut};
 use std::ptr::null_mut;
 
-use godot::global::godot_print;
 use r2g_mlua::{prelude::*, ChunkMode, Compiler};
 use super::scheduler::ITaskScheduler;
 use super::ParallelDispatch::{Default, Synchronized};
-use super::{borrowck_ignore, FastFlag, FastFlags, RwLock, RwLockReadGuard, RwLockWriteGuard, TaskScheduler, Trc};
-use super::{security::ThreadIdentityType, vm::RobloxVM};
-use crate::instance::WeakManagedInstance;
+use super::{borrowck_ignore, inheritance_cast_to, BytecodeCache, ChunkOwner, Clock, CompileMode, Debugger, Profiler, FastFlag, FastFlags, HostMessageKind, IVMHost, RwLock, RwLockReadGuard, RwLockWriteGuard, TaskSchedulerSnapshot, Trc, Weak};
+use super::{security::ThreadIdentityType, vm::RobloxVM, require::ModuleCache, memory::MemoryCategories};
+use crate::instance::{IServiceProvider, ManagedInstance, WeakManagedInstance};
 use crate::userdata::register_userdata_singletons;
 
 pub mod registry_keys {
@@ -16,17 +15,42 @@ pub mod registry_keys {
     pub const STATE_REGISTRYKEY: &'static str = "__state__";
     pub(super) const TASK_PUSH_WAIT: &'static str = "__task_push_wait__";
     pub(super) const TASK_PUSH_SYNC_DESYNC: &'static str = "__task_push_sync_desync__";
+    pub(crate) const REQUIRE_FACTORY: &'static str = "__require_factory__";
+    pub(crate) const ENUMS: &'static str = "__enums__";
+    pub(crate) const SIGNAL_WAIT: &str = "__signal_wait__";
+    pub(crate) const SIGNAL_PUSH_WAIT: &str = "__signal_push_wait__";
+    pub(crate) const INSTANCE_WAIT_FOR_CHILD: &str = "__instance_wait_for_child__";
+    pub(crate) const INSTANCE_PUSH_WAIT_FOR_CHILD: &str = "__instance_push_wait_for_child__";
+    pub(crate) const INSTANCE_USERDATA_CACHE: &str = "__instance_userdata_cache__";
+    pub(crate) const INSTANCE_METHODS: &str = "__instance_methods__";
+    pub(crate) const INSTANCE_CLASS_METHODS: &str = "__instance_class_methods__";
+    pub(crate) const INSTANCE_GET_MEMBER: &str = "__instance_get_member__";
+    pub(crate) const INSTANCE_PARALLEL_GUARD: &str = "__instance_parallel_guard__";
+    pub(crate) const INSTANCE_FIND_NAMECALL_METHOD: &str = "__instance_find_namecall_method__";
+
+    /// Pushes the registry value `key` from a C function, the value `Lua::named_registry_value` returns.
+    pub(crate) unsafe fn push_value(state: *mut r2g_mlua::ffi::lua_State, key: &str) {
+        r2g_mlua::ffi::lua_pushlstring(state, key.as_ptr().cast(), key.len());
+        r2g_mlua::ffi::lua_rawget(state, r2g_mlua::ffi::LUA_REGISTRYINDEX);
+    }
 }
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ThreadIdentity {
     pub security_identity: ThreadIdentityType,
     pub script: Option<WeakManagedInstance>,
 }
+/// Same pointer as the STATE_REGISTRYKEY registry value, readable without calling into Lua.
+struct StatePointer(*mut LuauState);
+
 #[derive(Debug)]
 pub struct LuauState {
     vm: *mut RwLock<RobloxVM>,
-    lua: Lua,
+    /// The Trc the state lives in, set by `init`.
+    this: Option<Weak<LuauState>>,
+    pub(super) lua: Lua,
     threads: HashMap<*const c_void, ThreadIdentity>,
+    pub(super) modules: ModuleCache,
+    pub(super) memory: MemoryCategories,
     task: MaybeUninit<Box<dyn ITaskScheduler>>
 }
 impl LuauState {
@@ -45,21 +69,20 @@ impl LuauState {
         if is_running {
             lua.gc_stop();
         }
-        let state;
-        unsafe {
-            let reg: LuaLightUserData = lua.named_registry_value(registry_keys::STATE_REGISTRYKEY)?;
-            state = reg.0.cast::<LuauState>().as_mut().unwrap();
-        }
+        // Destroyed events are sent while the GC sweeps, the registry can't be read without allocating.
+        let state = unsafe {
+            lua.app_data_ref::<StatePointer>().unwrap().0.as_mut().unwrap()
+        };
         match event {
             LuaThreadEventInfo::Created(parent) => {
-                godot_print!("[thread_events] new thread created: thread: 0x{:x} by thread: 0x{:x}",lua.current_thread().to_pointer() as isize,parent.to_pointer() as isize);
+                state.get_host().print(HostMessageKind::Debug, &format!("[thread_events] new thread created: thread: 0x{:x} by thread: 0x{:x}",lua.current_thread().to_pointer() as isize,parent.to_pointer() as isize));
                 let iden = state.threads.get(&parent.to_pointer());
                 if iden.is_some() {
                     state.threads.insert(lua.current_thread().to_pointer(), iden.unwrap().clone());
                 }
             }
             LuaThreadEventInfo::Destroyed(thread_ptr) => {
-                godot_print!("[thread_events] thread destroyed: thread: 0x{:x}",thread_ptr as isize);
+                state.get_host().print(HostMessageKind::Debug, &format!("[thread_events] thread destroyed: thread: 0x{:x}",thread_ptr as isize));
                 state.threads.remove(&thread_ptr);
             }
         }
@@ -81,27 +104,38 @@ impl LuauState {
         self.get_thread_identity_pointer_pointer_mut(thread.to_pointer().cast())
     }
     pub fn set_thread_identity(&mut self, thread: LuaThread, identity: ThreadIdentity) {
+        self.set_thread_memory_category(&thread, identity.script.as_ref());
         self.threads.insert(thread.to_pointer().cast(), identity);
     }
     unsafe fn register_globals(&mut self) {
         self.lua.globals().raw_set("print", self.lua.create_function(|lua, args: LuaMultiValue| {
-            let vm = Self::get_vm_from_lua(lua);
-            vm.read().unwrap().log_message(args);
+            get_state(lua).get_vm().log_message(lua, args);
             Ok(())
         }).unwrap()).unwrap();
         self.lua.globals().raw_set("warn", self.lua.create_function(|lua, args: LuaMultiValue| {
-            let vm = Self::get_vm_from_lua(lua);
-            vm.read().unwrap().log_warn(args);
+            get_state(lua).get_vm().log_warn(lua, args);
             Ok(())
         }).unwrap()).unwrap();
-        self.lua.globals().raw_set("game", self.vm.as_ref().unwrap_unchecked().read().unwrap().get_game_instance()).unwrap();
+        let clock = self.lua.create_function(|lua, ()| Ok(get_state(lua).get_clock().now())).unwrap();
+        self.lua.globals().raw_set("time", clock.clone()).unwrap();
+        self.lua.globals().raw_get::<LuaTable>("os").unwrap().raw_set("clock", clock).unwrap();
+        let require_factory = self.create_require_factory().unwrap();
+        self.lua.set_named_registry_value(registry_keys::REQUIRE_FACTORY, require_factory).unwrap();
+        let require = self.create_require_function(None).unwrap();
+        self.lua.globals().raw_set("require", require).unwrap();
+        let game = self.vm.as_ref().unwrap_unchecked().read().unwrap().get_game_instance();
+        // Workspace always exists, the first state to register its globals creates it.
+        let workspace = inheritance_cast_to!(&*game, dyn IServiceProvider).unwrap()
+            .get_service(&self.lua, "Workspace".into()).unwrap();
+        self.lua.globals().raw_set("game", game).unwrap();
+        self.lua.globals().raw_set("workspace", workspace).unwrap();
         // Task scheduler registration
         {
             type DynTaskScheduler = dyn ITaskScheduler;
             let task = self.lua.create_table().unwrap();
             task.raw_set("spawn", self.lua.create_function(|lua, (thread_or_func,mv): (LuaValue, LuaMultiValue)| {
                 match thread_or_func {
-                    LuaValue::Thread(thread) => get_state(borrowck_ignore(lua)).get_task_scheduler().spawn_thread(thread),
+                    LuaValue::Thread(thread) => get_state(borrowck_ignore(lua)).get_task_scheduler().spawn_thread(lua, thread),
                     LuaValue::Function(func) => get_state(borrowck_ignore(lua)).get_task_scheduler().spawn_func(lua, func, mv),
                     _ => Err(LuaError::RuntimeError("invalid argument #1 to 'spawn' (thread or function expected)".into()))
                 }
@@ -115,7 +149,7 @@ impl LuauState {
             }).unwrap()).unwrap();
             task.raw_set("delay", self.lua.create_function(|lua, (time,thread_or_func,mv): (f64,LuaValue,LuaMultiValue)| {
                 match thread_or_func {
-                    LuaValue::Thread(thread) => get_state(borrowck_ignore(lua)).get_task_scheduler_mut().delay_thread(thread, Default, time),
+                    LuaValue::Thread(thread) => get_state(borrowck_ignore(lua)).get_task_scheduler_mut().delay_thread(lua, thread, Default, time),
                     LuaValue::Function(func) => get_state(borrowck_ignore(lua)).get_task_scheduler_mut().delay_func(lua, func, mv, Synchronized, time),
                     _ => Err(LuaError::RuntimeError("invalid argument #2 to 'delay' (thread or function expected)".into()))
                 }
@@ -148,37 +182,34 @@ impl LuauState {
         self.lua.set_named_registry_value(registry_keys::VM_REGISTRYKEY, LuaLightUserData(self.vm.cast())).unwrap();
         let state_userdata = LuaLightUserData(addr_of_mut!(*self).cast());
         self.lua.set_named_registry_value(registry_keys::STATE_REGISTRYKEY, state_userdata).unwrap();
+        let state_pointer = StatePointer(addr_of_mut!(*self));
+        self.lua.set_app_data(state_pointer);
         
+        // Userdata types are registered before the globals, which already hold instances.
+        register_userdata_singletons(&mut self.lua).unwrap();
         self.register_globals();
         
         self.lua.sandbox(true).unwrap();
         self.lua.enable_jit(false);
-        register_userdata_singletons(&mut self.lua).unwrap();
         self.lua.globals().set_readonly(self.flags().get_bool(FastFlag::GlobalsReadonly));
         self.lua.set_thread_event_callback(Self::thread_event_callback);
+        self.apply_memory_limit();
         self.lua.gc_stop();
     }
-    pub(super) unsafe fn init(&mut self, ptr: *mut RwLock<RobloxVM>, task: Box<dyn ITaskScheduler>) {
+    pub(super) unsafe fn init(&mut self, ptr: *mut RwLock<RobloxVM>, this: Weak<LuauState>, task: Box<dyn ITaskScheduler>) {
         self.vm = ptr;
+        self.this = Some(this);
         self.task = MaybeUninit::new(task);
         self._init();
     }
-    #[doc(hidden)]
-    pub(super) fn new(ptr: *mut RwLock<RobloxVM>) -> LuauState {
-        let mut state = LuauState {
-            vm: ptr,
-            lua: Lua::new(),
-            threads: HashMap::default(),
-            task: MaybeUninit::new(Box::new(TaskScheduler::new()))
-        };
-        unsafe {state._init();}
-        state
-    }
     pub(super) unsafe fn new_uninit() -> LuauState {
         LuauState {
             vm: null_mut(),
+            this: None,
             lua: Lua::new(),
             threads: HashMap::default(),
+            modules: ModuleCache::default(),
+            memory: MemoryCategories::default(),
             task: MaybeUninit::uninit()
         }
     }
@@ -193,6 +224,31 @@ impl LuauState {
         }
     }
 
+    /// Gets the host of the VM. This is thread-safe even with `.access()` and does not lock the VM.
+    #[inline(always)]
+    pub fn get_host(&self) -> &dyn IVMHost {
+        unsafe { self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().get_host() }
+    }
+    /// Gets the bytecode cache shared by every state of the VM. This is thread-safe even with `.access()`.
+    #[inline(always)]
+    pub fn get_bytecode_cache(&self) -> &BytecodeCache {
+        unsafe { self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().get_bytecode_cache() }
+    }
+    /// Gets the profiler of the VM. This is thread-safe even with `.access()`.
+    #[inline(always)]
+    pub fn get_profiler(&self) -> &Profiler {
+        unsafe { self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().get_profiler() }
+    }
+    /// Gets the clock of the VM. This is thread-safe even with `.access()`.
+    #[inline(always)]
+    pub fn get_clock(&self) -> &Clock {
+        unsafe { self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().get_clock() }
+    }
+    /// Gets the debugger attached to the VM, if any. This is thread-safe even with `.access()`.
+    #[inline(always)]
+    pub fn get_debugger(&self) -> Option<&Debugger> {
+        unsafe { self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().get_debugger() }
+    }
     pub(super) unsafe fn watchdog_check(&self) -> bool {
         self.vm.as_ref().unwrap_unchecked().access().as_ref().unwrap_unchecked().watchdog_check()
     }
@@ -224,36 +280,55 @@ impl LuauState {
             .set_userdata_types(Self::get_userdata_types().into_iter().map(|x| String::from(*x)).collect())
             .set_type_info_level(1)
     }
+    /// Loads `chunk` compiled with `mode`, reusing the VM's bytecode cache.
+    fn load_cached(&mut self, mode: CompileMode, owner: ChunkOwner, chunk_name: &str, chunk: &str, env: LuaTable) -> LuaResult<LuaFunction> {
+        let cache = self.get_bytecode_cache();
+        let compile = || match mode {
+            CompileMode::Debug => Self::get_debug_compiler(),
+            CompileMode::Release | CompileMode::Native => Self::get_release_compiler()
+        }.compile(chunk);
+        let load = |bytecode: &[u8]| {
+            self.lua.enable_jit(mode == CompileMode::Native);
+            let f = self.lua
+                .load(bytecode)
+                .set_name(chunk_name)
+                .set_mode(ChunkMode::Binary)
+                .set_environment(env.clone())
+                .into_function();
+            self.lua.enable_jit(false);
+            f
+        };
+        let func = load(&cache.get_or_compile(mode, chunk, compile)?).or_else(|_| {
+            // Bytecode loaded from disk may come from an incompatible Luau version.
+            cache.invalidate(mode, chunk);
+            load(&cache.get_or_compile(mode, chunk, compile)?)
+        })?;
+        let state = addr_of_mut!(*self);
+        if let Some(debugger) = self.get_debugger() {
+            unsafe { self.lua.exec_raw::<()>(&func, |l| debugger.on_chunk_loaded(state, l, owner, chunk_name))? };
+        }
+        Ok(func)
+    }
     pub fn compile_jit(&mut self, chunk_name: &str, chunk: &str, env: LuaTable) -> LuaResult<LuaFunction> {
-        let v = Self::get_release_compiler()
-            .compile(chunk)?;
-        self.lua.enable_jit(true);
-        let f = self.lua
-            .load(v)
-            .set_name(chunk_name)
-            .set_mode(ChunkMode::Binary)
-            .set_environment(env)
-            .into_function();
-        self.lua.enable_jit(false);
-        f
+        self.load_cached(CompileMode::Native, ChunkOwner::Code(chunk_name.to_owned()), chunk_name, chunk, env)
     }
     pub fn compile_release(&mut self, chunk_name: &str, chunk: &str, env: LuaTable) -> LuaResult<LuaFunction> {
-        let v = Self::get_release_compiler()
-            .compile(chunk)?;
-        self.lua.load(v)
-            .set_name(chunk_name)
-            .set_mode(ChunkMode::Binary)
-            .set_environment(env)
-            .into_function()
+        self.load_cached(CompileMode::Release, ChunkOwner::Code(chunk_name.to_owned()), chunk_name, chunk, env)
     }
     pub fn compile_debug(&mut self, chunk_name: &str, chunk: &str, env: LuaTable) -> LuaResult<LuaFunction> {
-        let v = Self::get_debug_compiler()
-            .compile(chunk)?;
-        self.lua.load(v)
-            .set_name(chunk_name)
-            .set_mode(ChunkMode::Binary)
-            .set_environment(env)
-            .into_function()
+        self.load_cached(CompileMode::Debug, ChunkOwner::Code(chunk_name.to_owned()), chunk_name, chunk, env)
+    }
+    /// Compiles a script's source, picking the compiler from the DebugMode flag and the `--!native` directive.
+    /// The debugger keeps the chunk until `script` is compiled again or stops.
+    pub fn compile_script(&mut self, script: &ManagedInstance, chunk_name: &str, chunk: &str, env: LuaTable) -> LuaResult<LuaFunction> {
+        let mode = if self.flags().get_bool(FastFlag::DebugMode) {
+            CompileMode::Debug
+        } else if chunk.lines().any(|x| x == "--!native") {
+            CompileMode::Native
+        } else {
+            CompileMode::Release
+        };
+        self.load_cached(mode, ChunkOwner::Script(script.downgrade()), chunk_name, chunk, env)
     }
     pub fn create_env_from_global(&mut self) -> LuaResult<LuaTable> {
         let lua = self.get_lua();
@@ -266,6 +341,13 @@ impl LuauState {
 
         Ok(table)
     }
+    /// Creates the environment of a script, with `script` and a `require` resolving paths relative to it.
+    pub fn create_script_env(&mut self, script: ManagedInstance) -> LuaResult<LuaTable> {
+        let env = self.create_env_from_global()?;
+        env.raw_set("require", self.create_require_function(Some(script.clone()))?)?;
+        env.raw_set("script", script)?;
+        Ok(env)
+    }
     #[inline]
     pub fn get_task_scheduler_mut(&mut self) -> &mut dyn ITaskScheduler {
         // SAFETY: The state must be initialized
@@ -276,6 +358,10 @@ impl LuauState {
         // SAFETY: The state must be initialized
         unsafe { &**self.task.assume_init_ref() }
     }
+    pub fn get_task_scheduler_snapshot(&self) -> TaskSchedulerSnapshot {
+        // SAFETY: The state must be initialized
+        unsafe { self.task.assume_init_ref() }.snapshot(&self.lua)
+    }
     #[inline(always)]
     pub const fn flags(&self) -> &'static FastFlags {
         FastFlags::from_vm(self.vm)
@@ -334,9 +420,8 @@ pub fn get_state(l: &Lua) -> &mut LuauState {
     }
     state
 }
-pub fn get_state_with_rwlock(l: &Lua) -> &Trc<LuauState> {
-    let state = get_state(l);
-    let ptr = &raw mut *state;
-    let vm = state.get_vm();
-    vm.get_state_with_rwlock(ptr).map(|x| unsafe {&*x}).unwrap()
+pub fn get_state_with_rwlock(l: &Lua) -> Trc<LuauState> {
+    get_state(l).this.as_ref()
+        .and_then(|x| x.upgrade())
+        .expect("a state running Lua code is initialized and alive")
 }
\ No newline at end of file
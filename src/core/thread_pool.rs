@@ -0,0 +1,79 @@
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{available_parallelism, Builder, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Worker threads running the parallel phase of a frame. Workers are kept alive for the lifetime of the VM.
+#[derive(Debug)]
+pub(super) struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>
+}
+
+impl ThreadPool {
+    /// Creates one worker per available core.
+    pub fn new() -> ThreadPool {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..available_parallelism().map(|x| x.get()).unwrap_or(1))
+            .map(|i| {
+                let receiver = receiver.clone();
+                Builder::new()
+                    .name(format!("Luau worker {}", i))
+                    .spawn(move || Self::worker(receiver))
+                    .expect("failed to spawn a Luau worker thread")
+            })
+            .collect();
+        ThreadPool { sender: Some(sender), workers }
+    }
+    fn worker(receiver: Arc<Mutex<Receiver<Job>>>) {
+        loop {
+            let job = receiver.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return
+            }
+        }
+    }
+    /// Runs every job on the workers and returns their results once all of them finished.
+    /// A panic in a job is resumed on the calling thread after the other jobs are done.
+    pub fn run<'a, R: Send + 'a>(&self, jobs: Vec<Box<dyn FnOnce() -> R + Send + 'a>>) -> Vec<R> {
+        let count = jobs.len();
+        let (done, results) = channel::<(usize, Result<R, Box<dyn Any + Send>>)>();
+        for (i, job) in jobs.into_iter().enumerate() {
+            let done = done.clone();
+            let job: Box<dyn FnOnce() + Send + 'a> = Box::new(move || {
+                let _ = done.send((i, catch_unwind(AssertUnwindSafe(job))));
+            });
+            // SAFETY: The borrows of the job outlive it, this function doesn't return until every job sent its result.
+            let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Job>(job) };
+            self.sender.as_ref().unwrap().send(job).expect("Luau worker threads exited");
+        }
+        drop(done);
+        let mut ordered: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        let mut panic = None;
+        for _ in 0..count {
+            let (i, result) = results.recv().expect("a Luau worker thread exited while running a job");
+            match result {
+                Ok(x) => ordered[i] = Some(x),
+                Err(x) => panic = Some(x)
+            }
+        }
+        if let Some(panic) = panic {
+            resume_unwind(panic);
+        }
+        ordered.into_iter().map(|x| x.unwrap()).collect()
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::ffi::c_void;
+
+use r2g_mlua::prelude::*;
+
+/// How a sleeping thread is resumed once its timer fires.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimerKind {
+    /// `task.delay`, resumed with the given number of arguments already on its stack.
+    Delay(u32),
+    /// `task.wait`, resumed with the time elapsed since it started waiting, which is stored here.
+    Wait(f64),
+    /// A thread parked on a signal which timed out, resumed with nil.
+    Timeout
+}
+
+#[derive(Clone, Debug)]
+pub struct Timer {
+    pub thread: LuaThread,
+    pub kind: TimerKind,
+    pub wake_time: f64
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct TimerKey {
+    wake_time: f64,
+    id: u64
+}
+
+impl Eq for TimerKey {}
+
+impl PartialOrd for TimerKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerKey {
+    // Timers waking up at the same time fire in the order they were pushed.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.wake_time.total_cmp(&other.wake_time).then(self.id.cmp(&other.id))
+    }
+}
+
+/// Sleeping threads ordered by wake time. A thread can have several timers, e.g. when `task.delay` is called twice
+/// with it, it is then resumed once per timer.
+/// Cancelled timers only leave the heap once they reach its top, or when too many of them pile up.
+#[derive(Debug, Default)]
+pub(super) struct TimerQueue {
+    heap: BinaryHeap<Reverse<TimerKey>>,
+    timers: HashMap<u64, Timer>,
+    threads: HashMap<*const c_void, Vec<u64>>,
+    next_id: u64
+}
+
+impl TimerQueue {
+    pub fn push(&mut self, thread: LuaThread, kind: TimerKind, wake_time: f64) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.threads.entry(thread.to_pointer()).or_default().push(id);
+        self.timers.insert(id, Timer { thread, kind, wake_time });
+        self.heap.push(Reverse(TimerKey { wake_time, id }));
+    }
+    /// Removes every timer of `thread`.
+    pub fn cancel(&mut self, thread: &LuaThread) {
+        let Some(ids) = self.threads.remove(&thread.to_pointer()) else { return };
+        for id in ids {
+            self.timers.remove(&id);
+        }
+        if self.heap.len() > self.timers.len() * 2 + 64 {
+            let timers = &self.timers;
+            self.heap.retain(|x| timers.contains_key(&x.0.id));
+        }
+    }
+    /// Removes the timeout of `thread` parking on a signal, keeping its other timers.
+    pub fn cancel_timeout(&mut self, thread: &LuaThread) {
+        let Some(ids) = self.threads.get_mut(&thread.to_pointer()) else { return };
+        let timers = &mut self.timers;
+        ids.retain(|id| {
+            let timeout = timers.get(id).is_some_and(|x| x.kind == TimerKind::Timeout);
+            if timeout {
+                timers.remove(id);
+            }
+            !timeout
+        });
+        if ids.is_empty() {
+            self.threads.remove(&thread.to_pointer());
+        }
+    }
+    fn skip_cancelled(&mut self) {
+        while self.heap.peek().is_some_and(|x| !self.timers.contains_key(&x.0.id)) {
+            self.heap.pop();
+        }
+    }
+    /// Wake time of the earliest timer, if any.
+    pub fn next_wake_time(&mut self) -> Option<f64> {
+        self.skip_cancelled();
+        self.heap.peek().map(|x| x.0.wake_time)
+    }
+    /// Removes the earliest timer if it is due at `now`.
+    pub fn pop_due(&mut self, now: f64) -> Option<Timer> {
+        if self.next_wake_time()? > now {
+            return None;
+        }
+        let id = self.heap.pop()?.0.id;
+        let timer = self.timers.remove(&id)?;
+        let pointer = timer.thread.to_pointer();
+        if let Some(ids) = self.threads.get_mut(&pointer) {
+            ids.retain(|x| *x != id);
+            if ids.is_empty() {
+                self.threads.remove(&pointer);
+            }
+        }
+        Some(timer)
+    }
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+    /// Pending timers, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Timer> {
+        self.timers.values()
+    }
+}
@@ -1,21 +1,39 @@
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
+use std::cell::Cell;
 use std::mem::MaybeUninit;
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering::Relaxed;
-use std::sync::Arc;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::{Arc, Mutex};
 use std::thread::panicking;
 use std::marker::PhantomPinned;
 
-use godot::global::godot_print_rich;
-use godot::{builtin::Variant, global::{godot_print, print_rich, printt}, meta::ToGodot};
+use godot::{builtin::Variant, meta::ToGodot};
 use r2g_mlua::prelude::*;
 
-use crate::core::scheduler::GlobalTaskScheduler;
-use crate::instance::{DataModel, ManagedInstance};
+use crate::core::scheduler::{get_task_scheduler_from_lua, GlobalTaskScheduler, TaskScheduler};
+use crate::instance::{DataModel, LogService, ManagedInstance, ScriptContext};
+use crate::userdata::enums::MessageType;
 
 use super::state::LuauState;
-use super::{FastFlag, FastFlagValue, FastFlags, InstanceReplicationTable, InstanceTagCollectionTable, RwLock, Trc, Watchdog, Weak, GLOBAL_LOCKS_OF_THREAD};
+use super::thread_pool::ThreadPool;
+use super::{FastFlag, FastFlagValue, FastFlags, GodotVMHost, HostMessageKind, IVMHost, InstanceReplicationTable, LogHistory, LogMessage, InstanceTagCollectionTable, RequireConfig, BytecodeCache, Clock, Debugger, DebugTransport, Profiler, ScriptError, StateMemoryUsage, TaskSchedulerSnapshot,
+    ParallelDispatch, RwLock, ThreadIdentity, ThreadIdentityType, Trc, Watchdog, Weak, GLOBAL_LOCKS_OF_THREAD};
+
+/// Reported errors kept until [`RobloxVM::take_script_errors`] is called, older ones are dropped first.
+const MAX_PENDING_SCRIPT_ERRORS: usize = 256;
+
+thread_local! {
+    /// Set while this thread fires LogService.MessageOut, other threads can still fire it meanwhile.
+    static FIRING_MESSAGE_OUT: Cell<bool> = const { Cell::new(false) };
+    /// Set while this thread fires ScriptContext.Error.
+    static REPORTING_ERROR: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A signal a desynchronized thread would have fired, fired by [`RobloxVM::fire_queued_signals`] once synchronized.
+enum QueuedSignal {
+    MessageOut(String, MessageType),
+    Error(ScriptError)
+}
 
 pub struct RobloxVM {
     main_state: Trc<LuauState>,
@@ -25,9 +43,20 @@ pub struct RobloxVM {
     flags: MaybeUninit<FastFlags>,
     data_model: MaybeUninit<ManagedInstance>,
     global_lock: Arc<AtomicBool>,
+    /// Set while worker threads run the parallel phase with the VM lock released.
+    parallel_phase: AtomicBool,
+    host: Box<dyn IVMHost>,
+    require_config: RequireConfig,
+    bytecode_cache: BytecodeCache,
+    debugger: Option<Debugger>,
+    profiler: Profiler,
+    script_errors: Mutex<Vec<ScriptError>>,
+    log_history: Mutex<LogHistory>,
+    queued_signals: Mutex<Vec<QueuedSignal>>,
 
-    states_locks: HashMap<*mut LuauState, *const Trc<LuauState>>,
     
+    clock: Clock,
+    thread_pool: Option<ThreadPool>,
     hard_wd: Watchdog,
     soft_wd: Watchdog,
 
@@ -58,15 +87,30 @@ pub(crate) fn args_to_string(args: LuaMultiValue, delimiter: &str) -> String {
 
 impl RobloxVM {
     pub fn new(flags_table: Option<Vec<(FastFlag, FastFlagValue)>>) -> Box<RwLock<RobloxVM>> {
+        Self::new_with_host(flags_table, Box::new(GodotVMHost))
+    }
+    /// Creates a VM which reports all of its output to `host` instead of Godot.
+    /// This is the entry point for running the runtime without a Godot engine instance.
+    pub fn new_with_host(flags_table: Option<Vec<(FastFlag, FastFlagValue)>>, host: Box<dyn IVMHost>) -> Box<RwLock<RobloxVM>> {
         unsafe {
             let mut vm = Box::new(RwLock::new(RobloxVM {
                 main_state: Trc::new(LuauState::new_uninit()),
                 states: Vec::new(),
-                states_locks: HashMap::new(),
                 global_lock: Arc::new(AtomicBool::new(true)),
+                parallel_phase: AtomicBool::new(false),
+                host,
+                require_config: RequireConfig::default(),
+                bytecode_cache: BytecodeCache::new(),
+                debugger: None,
+                profiler: Profiler::new(),
+                script_errors: Mutex::new(Vec::new()),
+                log_history: Mutex::new(LogHistory::default()),
+                queued_signals: Mutex::new(Vec::new()),
                 instances: InstanceReplicationTable::default(),
                 instances_tag_collection: InstanceTagCollectionTable::default(),
                 data_model: MaybeUninit::uninit(),
+                clock: Clock::new_real_time(),
+                thread_pool: None,
                 hard_wd: Watchdog::new_timeout(10.0),
                 soft_wd: Watchdog::new_timeout(1.0/60.0),
                 _pin: PhantomPinned::default(),
@@ -81,53 +125,247 @@ impl RobloxVM {
                 vm.get_mut().flags.assume_init_mut()
                     .initialize_with_table(table);
             }
-            let main_state_ptr = vm.get_mut().main_state.access();
-            let main_state_lock_ptr = &raw const vm.get_mut().main_state;
-            vm.get_mut().states_locks.insert(main_state_ptr, main_state_lock_ptr);
 
-            vm.get_mut().main_state.access().as_mut().unwrap_unchecked().init(vm_ptr, Box::new(GlobalTaskScheduler::new()));
-            godot_print!("RobloxVM instance created.");
+            let main_state = vm.get_mut().main_state.downgrade();
+            vm.get_mut().main_state.access().as_mut().unwrap_unchecked().init(vm_ptr, main_state, Box::new(GlobalTaskScheduler::new()));
+            vm.get_mut().host.print(HostMessageKind::Debug, "RobloxVM instance created.");
             vm
         }
     }
-    pub fn log_message(&self, args: LuaMultiValue) {
-        let v = args_to_variant(args);
-        printt(&v);
+    /// Prints `message` through the host and records it in the log history, firing LogService.MessageOut.
+    /// Messages printed while desynchronized are fired at the next synchronized point instead.
+    pub fn log(&self, lua: &Lua, kind: HostMessageKind, message: String) {
+        self.host.print(kind, &message);
+        let Some(message_type) = MessageType::from_host_message_kind(kind) else { return };
+        self.log_history.lock().unwrap().push(LogMessage::new(message.clone(), message_type));
+        if get_task_scheduler_from_lua(lua).is_desynchronized() {
+            self.queued_signals.lock().unwrap().push(QueuedSignal::MessageOut(message, message_type));
+        } else {
+            self.fire_message_out(lua, message, message_type);
+        }
+    }
+    /// Messages printed while MessageOut handlers run on this thread are not fired again, so a handler printing can't call itself forever.
+    fn fire_message_out(&self, lua: &Lua, message: String, message_type: MessageType) {
+        if FIRING_MESSAGE_OUT.replace(true) {
+            return;
+        }
+        let log_service = self.get_game_instance()
+            .find_first_child_of_class("LogService".into()).ok().flatten()
+            .and_then(|x| x.cast_from_unsized::<LogService>().ok());
+        if let Some(log_service) = log_service {
+            let _ = log_service.message_out.write().fire(lua, (message, message_type));
+        }
+        FIRING_MESSAGE_OUT.set(false);
+    }
+    pub fn log_message(&self, lua: &Lua, args: LuaMultiValue) {
+        self.log(lua, HostMessageKind::Output, args_to_string(args, "\t"));
+    }
+    pub fn log_info(&self, lua: &Lua, args: LuaMultiValue) {
+        self.log(lua, HostMessageKind::Info, args_to_string(args, "\t"));
     }
-    pub fn log_info(&self, args: LuaMultiValue) {
-        let mut string = args_to_string(args, "\t");
-        string = "[color=blue]".to_owned() + &string;
-        string = string + "[/color]";
-        let v: [Variant; 1] = [string.to_variant()];
-        print_rich(&v)
+    pub fn log_warn(&self, lua: &Lua, args: LuaMultiValue) {
+        self.log(lua, HostMessageKind::Warning, args_to_string(args, "\t"));
     }
-    pub fn log_warn(&self, args: LuaMultiValue) {
-        let mut string = args_to_string(args, "\t");
-        string = "[color=yellow]".to_owned() + &string;
-        string = string + "[/color]";
-        let v: [Variant; 1] = [string.to_variant()];
-        print_rich(&v)
+    pub fn log_err(&self, lua: &Lua, args: LuaMultiValue) {
+        self.log(lua, HostMessageKind::Error, args_to_string(args, "\t"));
     }
-    pub fn log_err(&self, args: LuaMultiValue) {
-        let mut string = args_to_string(args, "\t");
-        string = "[color=red]".to_owned() + &string;
-        string = string + "[/color]";
-        let v: [Variant; 1] = [string.to_variant()];
-        print_rich(&v)
+    /// Prints an error no script caught and fires ScriptContext.Error with it.
+    /// Errors raised while desynchronized are fired at the next synchronized point instead.
+    pub fn report_script_error(&self, lua: &Lua, error: ScriptError) {
+        self.log(lua, HostMessageKind::Error, error.message.clone());
+        self.log(lua, HostMessageKind::Info, format!("Stack Begin\n{}Stack End", error.stack_trace));
+        {
+            let mut errors = self.script_errors.lock().unwrap();
+            if errors.len() >= MAX_PENDING_SCRIPT_ERRORS {
+                errors.remove(0);
+            }
+            errors.push(error.clone());
+        }
+        if get_task_scheduler_from_lua(lua).is_desynchronized() {
+            self.queued_signals.lock().unwrap().push(QueuedSignal::Error(error));
+        } else {
+            self.fire_script_error(lua, error);
+        }
+    }
+    /// Errors raised while ScriptContext.Error handlers run on this thread are only printed, so a failing handler can't report itself forever.
+    fn fire_script_error(&self, lua: &Lua, error: ScriptError) {
+        if REPORTING_ERROR.replace(true) {
+            return;
+        }
+        let script_context = self.get_game_instance()
+            .find_first_child_of_class("ScriptContext".into()).ok().flatten()
+            .and_then(|x| x.cast_from_unsized::<ScriptContext>().ok());
+        if let Some(script_context) = script_context {
+            let script = error.script.and_then(|x| x.upgrade());
+            let _ = script_context.error.write().fire(lua, (error.message, error.stack_trace, script));
+        }
+        REPORTING_ERROR.set(false);
+    }
+    /// Fires the MessageOut and ScriptContext.Error signals queued while desynchronized, in the order they were raised.
+    pub(super) fn fire_queued_signals(&self, lua: &Lua) {
+        let queued = std::mem::take(&mut *self.queued_signals.lock().unwrap());
+        for signal in queued {
+            match signal {
+                QueuedSignal::MessageOut(message, message_type) => self.fire_message_out(lua, message, message_type),
+                QueuedSignal::Error(error) => self.fire_script_error(lua, error)
+            }
+        }
+    }
+    /// Takes the errors reported since the last call, oldest first.
+    pub fn take_script_errors(&self) -> Vec<ScriptError> {
+        std::mem::take(&mut *self.script_errors.lock().unwrap())
+    }
+    /// Messages printed so far, oldest first.
+    pub fn get_log_history(&self) -> Vec<LogMessage> {
+        self.log_history.lock().unwrap().history().cloned().collect()
+    }
+    /// Takes the messages printed since the last call, oldest first.
+    pub fn take_log_messages(&self) -> Vec<LogMessage> {
+        self.log_history.lock().unwrap().take_pending()
+    }
+    /// SAFETY: Always allowed, even from .access(). Hosts are required to be thread-safe.
+    #[inline(always)]
+    pub fn get_host(&self) -> &dyn IVMHost {
+        &*self.host
+    }
+    /// SAFETY: Always allowed, even from .access(). The clock is internally synchronized.
+    #[inline(always)]
+    pub fn get_clock(&self) -> &Clock {
+        &self.clock
+    }
+    /// Replaces the time source of the VM. Threads already waiting keep their deadline, which is read in the new clock's time.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+    /// SAFETY: Always allowed, even from .access(). The cache is internally synchronized.
+    #[inline(always)]
+    pub fn get_bytecode_cache(&self) -> &BytecodeCache {
+        &self.bytecode_cache
+    }
+    /// SAFETY: Always allowed, even from .access(). The debugger is internally synchronized.
+    #[inline(always)]
+    pub fn get_debugger(&self) -> Option<&Debugger> {
+        self.debugger.as_ref()
+    }
+    /// Starts a Debug Adapter Protocol server on `transport` and attaches it to every state of the VM.
+    /// Only one debugger can be attached at a time.
+    pub fn attach_debugger(&mut self, transport: DebugTransport) -> std::io::Result<()> {
+        if self.debugger.is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "a debugger is already attached"));
+        }
+        let debugger = Debugger::new(transport)?;
+        for state in self.get_all_states() {
+            unsafe { debugger.register_state(state.access()) };
+        }
+        self.debugger = Some(debugger);
+        Ok(())
+    }
+    /// SAFETY: Always allowed, even from .access(). The profiler is internally synchronized.
+    #[inline(always)]
+    pub fn get_profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+    /// Starts sampling the call stacks of every state each `interval` seconds, discarding the previous samples.
+    pub fn start_profiler(&mut self, interval: f64) {
+        self.profiler.start(interval);
+        // A tripped watchdog owns the interrupt, resetting it installs the profiler.
+        let tripped = self.hard_wd.check();
+        for (name, state) in self.state_names() {
+            self.profiler.register_state(unsafe { state.access() } as usize, name);
+            if !tripped {
+                Profiler::install(unsafe { state.access().as_mut().unwrap_unchecked() }.get_lua());
+            }
+        }
+    }
+    /// Stops sampling, the samples are kept until the profiler is started again.
+    pub fn stop_profiler(&mut self) {
+        self.profiler.stop();
+        // A tripped watchdog owns the interrupt until it is reset.
+        if !self.hard_wd.check() {
+            for state in self.get_all_states() {
+                unsafe { state.access().as_mut().unwrap_unchecked() }.get_lua().remove_interrupt();
+            }
+        }
+    }
+    /// Reapplies the LuauHeapLimitMB flag to every state.
+    pub(super) fn apply_memory_limit(&self) {
+        for state in self.get_all_states() {
+            unsafe { state.access().as_ref().unwrap_unchecked() }.apply_memory_limit();
+        }
+    }
+    /// Bytes allocated by all states of the VM.
+    pub fn get_memory_usage(&self) -> usize {
+        self.get_all_states().iter()
+            .map(|x| unsafe { x.access().as_ref().unwrap_unchecked() }.get_memory_usage())
+            .sum()
+    }
+    /// Bytes allocated by `script` in every state it ran in.
+    pub fn get_script_memory_usage(&self, script: &ManagedInstance) -> usize {
+        self.get_all_states().iter()
+            .map(|x| unsafe { x.access().as_ref().unwrap_unchecked() }.get_script_memory_usage(script))
+            .sum()
+    }
+    /// Memory usage of each state, named like in the profiler.
+    pub fn get_memory_stats(&self) -> Vec<(String, StateMemoryUsage)> {
+        self.state_names().into_iter()
+            .map(|(name, state)| (name, unsafe { state.access().as_ref().unwrap_unchecked() }.get_memory_stats()))
+            .collect()
+    }
+    /// Task scheduler of each state, named like in the profiler.
+    /// Only taken at synchronized points, `None` while worker threads run the parallel phase.
+    pub fn get_task_scheduler_snapshots(&self) -> Option<Vec<(String, TaskSchedulerSnapshot)>> {
+        if self.parallel_phase.load(Acquire) {
+            return None;
+        }
+        // SAFETY: Holding the VM outside of the parallel phase, no other thread runs a state.
+        Some(self.state_names().into_iter()
+            .map(|(name, state)| (name, unsafe { state.access().as_ref().unwrap_unchecked() }.get_task_scheduler_snapshot()))
+            .collect())
+    }
+    /// Configuration used to resolve string requires in every state of the VM.
+    pub fn get_require_config(&self) -> &RequireConfig {
+        &self.require_config
+    }
+    pub fn get_require_config_mut(&mut self) -> &mut RequireConfig {
+        &mut self.require_config
+    }
+    /// Compiles a chunk on the main state and defers it on the next cycle with `UserInit` identity.
+    pub fn push_code(&mut self, chunk_name: &str, chunk: &str) -> LuaResult<LuaThread> {
+        let state = self.get_main_state();
+        let env = state.create_env_from_global()?;
+        let func = state.compile_jit(chunk_name, chunk, env)?;
+        let lua = state.get_lua().clone();
+        let thread = get_task_scheduler_from_lua(&lua)
+            .defer_func(&lua, func, (), ParallelDispatch::Synchronized)?;
+        state.set_thread_identity(thread.clone(), ThreadIdentity {
+            security_identity: ThreadIdentityType::UserInit,
+            script: None
+        });
+        Ok(thread)
+    }
+    /// Advances the VM by a single frame, running every resumable thread in the task scheduler.
+    pub fn step(vm: &RwLock<RobloxVM>, delta: f64) -> LuaResult<()> {
+        let write = vm.write()
+            .map_err(|_| LuaError::RuntimeError("RobloxVM lock is poisoned".into()))?;
+        if let Some(debugger) = write.get_debugger() {
+            debugger.poll(&write);
+        }
+        GlobalTaskScheduler::frame_step(write, delta)
     }
     pub fn get_main_state(&mut self) -> &mut LuauState {
         unsafe { &mut *self.main_state.access() }
     }
-    pub(super) fn get_state_with_rwlock(&self, ptr: *mut LuauState) -> Option<*const Trc<LuauState>> {
-        self.states_locks.get(&ptr).map(|x| *x)
-    }
     unsafe fn watchdog_trip_state(state: *mut LuauState) {
         state.as_mut().unwrap_unchecked().get_lua().set_interrupt(
             |_| Err(LuaError::RuntimeError("script exhausted maximum execution time".into()))
         );
     }
-    fn watchdog_reset_state(state: &mut LuauState) {
-        state.get_lua().remove_interrupt();
+    fn watchdog_reset_state(state: &mut LuauState, profiler: &Profiler) {
+        if profiler.is_running() {
+            Profiler::install(state.get_lua());
+        } else {
+            state.get_lua().remove_interrupt();
+        }
     }
     pub fn watchdog_trip(&self) {
         self.hard_wd.trip();
@@ -145,13 +383,13 @@ impl RobloxVM {
     }
     pub fn watchdog_reset(&mut self) {
         if self.hard_wd.check() {
-            Self::watchdog_reset_state(unsafe { self.main_state.access().as_mut().unwrap_unchecked() });
+            Self::watchdog_reset_state(unsafe { self.main_state.access().as_mut().unwrap_unchecked() }, &self.profiler);
             for i in self.states.iter()
             .map(|x| x.upgrade())
             .filter(|x| x.is_some())
             .map(|x| x.unwrap()) 
             {
-                Self::watchdog_reset_state(i.write().borrow_mut());
+                Self::watchdog_reset_state(i.write().borrow_mut(), &self.profiler);
             }
         }
         self.hard_wd.reset();
@@ -186,6 +424,18 @@ impl RobloxVM {
         self.global_lock.store(state, Relaxed);
     }
     #[inline(always)]
+    pub(super) fn get_global_lock(&self) -> Arc<AtomicBool> {
+        self.global_lock.clone()
+    }
+    /// Set by the thread holding the VM around releasing it to the workers of the parallel phase.
+    pub(super) fn set_parallel_phase(&self, running: bool) {
+        self.parallel_phase.store(running, Release);
+    }
+    /// Worker threads of the parallel phase, created on first use.
+    pub(super) fn get_thread_pool(&mut self) -> &ThreadPool {
+        self.thread_pool.get_or_insert_with(ThreadPool::new)
+    }
+    #[inline(always)]
     pub(crate) fn push_global_lock_atomic(&self) {
         GLOBAL_LOCKS_OF_THREAD.with_borrow_mut(|x| x.push(self.global_lock.clone()));
     }
@@ -197,9 +447,17 @@ impl RobloxVM {
         let self_rwlock = unsafe {
             self.main_state.access().as_ref().unwrap_unchecked().get_vm_ptr()
         };
-        let state = LuauState::new(self_rwlock);
-        let rc = Trc::new(state);
+        // The state registers its own address in Lua, it must only be initialized once it has been moved into the Trc.
+        let rc = Trc::new(unsafe { LuauState::new_uninit() });
+        unsafe { rc.access().as_mut().unwrap_unchecked().init(self_rwlock, rc.downgrade(), Box::new(TaskScheduler::new())) };
         self.states.push(rc.downgrade());
+        if let Some(debugger) = self.debugger.as_ref() {
+            unsafe { debugger.register_state(rc.access()) };
+        }
+        if self.profiler.is_running() {
+            self.profiler.register_state(unsafe { rc.access() } as usize, Self::actor_name(self.states.len() - 1));
+            Profiler::install(unsafe { rc.access().as_mut().unwrap_unchecked() }.get_lua());
+        }
         rc
     }
     pub fn get_main_state_rc(&self) -> Trc<LuauState> {
@@ -213,15 +471,28 @@ impl RobloxVM {
             .chain(std::iter::once(self.main_state.clone()))
             .collect()
     }
+    fn actor_name(index: usize) -> String {
+        format!("Actor {}", index + 1)
+    }
+    /// Every live state with the name the profiler and the stats show for it: "Main", or "Actor n" for the n-th state
+    /// created for an Actor.
+    pub fn state_names(&self) -> Vec<(String, Trc<LuauState>)> {
+        self.states.iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some((Self::actor_name(i), x.upgrade()?)))
+            .chain(std::iter::once(("Main".to_owned(), self.main_state.clone())))
+            .collect()
+    }
 }
 
 impl Drop for RobloxVM {
     fn drop(&mut self) {
         if panicking() {
-            godot_print_rich!("[color=red][b]ERROR: RobloxVM:[/b] Abnormal exit (panicking() == true)[/color]\n[color=gray]   at RobloxVM::drop() ({}:{})[/color]", file!(), line!());
+            self.host.print(HostMessageKind::Error, 
+                &format!("ERROR: RobloxVM: Abnormal exit (panicking() == true)\n   at RobloxVM::drop() ({}:{})", file!(), line!()));
         }
         self.states.clear();
         unsafe { self.flags.assume_init_drop() };
-        godot_print!("RobloxVM instance destroyed.");
+        self.host.print(HostMessageKind::Debug, "RobloxVM instance destroyed.");
     }
 }
\ No newline at end of file
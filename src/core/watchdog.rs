@@ -1,25 +1,26 @@
 use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::time::Instant;
 
-use r2g_mlua::ffi::lua_clock;
-
+/// Trips once `timeout` seconds of wall time passed since the last reset. It doesn't follow the VM's [`super::Clock`],
+/// so a virtual clock can't keep a script which never yields from being stopped.
 #[derive(Debug)]
 pub struct Watchdog {
-    watchdog: f64,
+    watchdog: Instant,
     timeout: f64,
     flag: AtomicBool
 }
 
 impl Watchdog {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            watchdog: 0f64,
+            watchdog: Instant::now(),
             timeout: 0f64,
             flag: AtomicBool::new(false)
         }
     }
-    pub const fn new_timeout(timeout: f64) -> Self {
+    pub fn new_timeout(timeout: f64) -> Self {
         Self {
-            watchdog: 0f64,
+            watchdog: Instant::now(),
             timeout,
             flag: AtomicBool::new(false)
         }
@@ -28,10 +29,6 @@ impl Watchdog {
     pub fn set_timeout(&mut self, timeout: f64) {
         self.timeout = timeout;
     }
-    #[inline(always)]
-    fn clock() -> f64 {
-        unsafe {lua_clock()}
-    }
     #[inline]
     pub fn trip(&self) {
         self.flag.store(true, Relaxed);
@@ -41,7 +38,7 @@ impl Watchdog {
         if self.flag.load(Relaxed) {
             true
         } else {
-            let flag = self.timeout + self.watchdog < Self::clock() && self.timeout != 0f64;
+            let flag = self.timeout < self.watchdog.elapsed().as_secs_f64() && self.timeout != 0f64;
             if flag {
                 self.trip()
             }
@@ -50,7 +47,7 @@ impl Watchdog {
     }
     #[inline]
     pub fn reset(&mut self) {
-        self.watchdog = Self::clock();
+        self.watchdog = Instant::now();
         self.flag.store(false, Relaxed);
     }
     #[inline]
@@ -64,4 +61,4 @@ impl Default for Watchdog {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,9 @@
 use std::{collections::HashMap, mem::transmute};
 
 use bevy_reflect::Typed;
-use godot::{classes::Engine, global::Error, prelude::*};
+use godot::{classes::{Engine, ProjectSettings}, global::Error, prelude::*};
 
-use crate::core::{borrowck_ignore_mut, get_state, FastFlag, FastFlagValue, GlobalTaskScheduler, ParallelDispatch::Synchronized, RobloxVM, RwLock, ThreadIdentity, ThreadIdentityType};
+use crate::core::{get_state, Clock, CycleStats, DebugTransport, QueueCounts, FastFlag, FastFlagValue, ParallelDispatch::Synchronized, RobloxVM, RwLock};
 
 /// The RobloxVM node, holding either a client or a server state, depending on the startup flags.
 /// 
@@ -16,6 +16,22 @@ pub struct RobloxVMNode {
     /// [b]Note:[/b] This is only loaded on startup! At runtime, you have to use the [method set_fast_flag_async] and [method get_fast_flag] methods.
     #[export]
     startup_flags: Dictionary,
+    /// Directory string requires without a script resolve against, its [code].luaurc[/code] aliases are loaded on startup.
+    /// Accepts both [code]res://[/code] and absolute paths, leave empty to only allow requires relative to scripts.
+    #[export(dir)]
+    require_root: GString,
+    /// File compiled bytecode is loaded from on startup and saved to when the node exits the tree, so later sessions skip compiling unchanged scripts.
+    /// Leave empty to keep the cache in memory only.
+    #[export(file)]
+    bytecode_cache_path: GString,
+    /// Port a Debug Adapter Protocol server listens on for debugging scripts, on [code]127.0.0.1[/code] only.
+    /// Leave at [code]0[/code] to not start the server.
+    #[export(range = (0.0, 65535.0))]
+    debugger_port: i32,
+    /// Token clients must pass as the [code]token[/code] argument of their [code]attach[/code] or [code]launch[/code] request.
+    /// The server only starts when both the port and the token are set.
+    #[export]
+    debugger_token: GString,
 
     base: Base<Node>,
 }
@@ -35,6 +51,10 @@ impl INode for RobloxVMNode {
         RobloxVMNode {
             vm: None,
             startup_flags: dict,
+            require_root: GString::new(),
+            bytecode_cache_path: GString::new(),
+            debugger_port: 0,
+            debugger_token: GString::new(),
             base: owner,
         }
     }
@@ -72,21 +92,81 @@ impl INode for RobloxVMNode {
             }
         }
         
-        self.vm = Some(RobloxVM::new(Some(flags_table)));
+        let vm = RobloxVM::new(Some(flags_table));
+        if !self.require_root.is_empty() {
+            let root = ProjectSettings::singleton().globalize_path(&self.require_root).to_string();
+            if let Err(e) = vm.write().unwrap().get_require_config_mut().set_root(root.into()) {
+                godot_error!("RobloxVMNode: failed to set require root: {}", e);
+            }
+        }
+        if !self.bytecode_cache_path.is_empty() {
+            let path = ProjectSettings::singleton().globalize_path(&self.bytecode_cache_path).to_string();
+            if let Err(e) = vm.read().unwrap().get_bytecode_cache().load(path.as_ref()) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    godot_error!("RobloxVMNode: failed to load bytecode cache: {}", e);
+                }
+            }
+        }
+        if self.debugger_port != 0 && self.debugger_token.is_empty() {
+            godot_error!("RobloxVMNode: not starting the debugger, debugger_token is empty");
+        } else if self.debugger_port != 0 {
+            let transport = DebugTransport::Tcp { port: self.debugger_port as u16, token: self.debugger_token.to_string() };
+            if let Err(e) = vm.write().unwrap().attach_debugger(transport) {
+                godot_error!("RobloxVMNode: failed to start debugger: {}", e);
+            }
+        }
+        self.vm = Some(vm);
+    }
+
+    fn exit_tree(&mut self) {
+        if let Some(vm) = self.vm.as_ref() {
+            if !self.bytecode_cache_path.is_empty() {
+                let path = ProjectSettings::singleton().globalize_path(&self.bytecode_cache_path).to_string();
+                if let Err(e) = vm.read().unwrap().get_bytecode_cache().save(path.as_ref()) {
+                    godot_error!("RobloxVMNode: failed to save bytecode cache: {}", e);
+                }
+            }
+        }
     }
 
     fn process(&mut self, delta: f64) {
-        if let Some(vm) = self.vm.as_mut() {
-            let write = vm.write()
-                .inspect_err(|_| godot_error!("RobloxVMNode: failed to acquire write lock on RobloxVM"))
-                .unwrap();
-            GlobalTaskScheduler::frame_step(write, delta).unwrap();
+        let Some(vm) = self.vm.as_mut() else { return };
+        RobloxVM::step(vm, delta)
+            .inspect_err(|_| godot_error!("RobloxVMNode: failed to step RobloxVM"))
+            .unwrap();
+        let errors = vm.read().unwrap().take_script_errors();
+        let messages = vm.read().unwrap().take_log_messages();
+        for error in errors {
+            let script = error.script
+                .and_then(|x| x.upgrade())
+                .and_then(|x| x.get_full_name().ok())
+                .unwrap_or_default();
+            self.base_mut().emit_signal("script_error", &[
+                error.message.to_variant(),
+                error.stack_trace.to_variant(),
+                script.to_variant()
+            ]);
+        }
+        for message in messages {
+            self.base_mut().emit_signal("message_out", &[
+                message.message.to_variant(),
+                (message.message_type as i64).to_variant(),
+                message.timestamp.to_variant()
+            ]);
         }
     }
 }
 
 #[godot_api]
 impl RobloxVMNode {
+    /// Emitted after a frame for every error no script caught, like [code]ScriptContext.Error[/code].
+    /// [param script] is the full name of the script the error comes from, or empty if there is none.
+    #[signal]
+    fn script_error(message: GString, stack_trace: GString, script: GString);
+    /// Emitted after a frame for every message printed, like [code]LogService.MessageOut[/code].
+    /// [param message_type] is the value of the [code]Enum.MessageType[/code] item, [param timestamp] is in seconds since the Unix epoch.
+    #[signal]
+    fn message_out(message: GString, message_type: i64, timestamp: f64);
     /// Sets a fast flag to a new value asynchronously, returns an error if the flag name is invalid.
     /// [b]Note:[/b] If provided an invalid type, it will panic inside the task scheduler.
     #[func]
@@ -164,27 +244,154 @@ impl RobloxVMNode {
             }
         })().unwrap_or_else(|e| e)
     }
+    /// Starts the script profiler, sampling the call stacks of every script every [param interval_msec] milliseconds.
+    /// Previous samples are discarded.
+    #[func]
+    fn start_profiler(&mut self, interval_msec: f64) {
+        if let Some(vm) = self.vm.as_ref() {
+            vm.write().unwrap().start_profiler(interval_msec / 1000.0);
+        }
+    }
+    /// Stops the script profiler. Its samples are kept until it is started again.
+    #[func]
+    fn stop_profiler(&mut self) {
+        if let Some(vm) = self.vm.as_ref() {
+            vm.write().unwrap().stop_profiler();
+        }
+    }
+    #[func]
+    fn is_profiling(&self) -> bool {
+        self.vm.as_ref().is_some_and(|vm| vm.read().unwrap().get_profiler().is_running())
+    }
+    /// Returns the profiler samples in the collapsed stack format, which flamegraph tools can render.
+    #[func]
+    fn get_profile_collapsed_stacks(&self) -> GString {
+        self.vm.as_ref()
+            .map(|vm| vm.read().unwrap().get_profiler().to_collapsed_stacks())
+            .unwrap_or_default()
+            .into()
+    }
+    /// Returns the profiler samples as Chrome trace event JSON, which can be opened in Perfetto or [code]chrome://tracing[/code].
+    #[func]
+    fn get_profile_chrome_trace(&self) -> GString {
+        self.vm.as_ref()
+            .map(|vm| vm.read().unwrap().get_profiler().to_chrome_trace())
+            .unwrap_or_default()
+            .into()
+    }
+    /// Returns the Luau heap usage in bytes, as [code]{ "total": int, "states": { name: { "used": int, "limit": int, "scripts": { full_name: int } } } }[/code].
+    /// A limit of 0 means the state has no cap.
+    #[func]
+    fn get_memory_usage(&self) -> Dictionary {
+        let mut usage = Dictionary::new();
+        let Some(vm) = self.vm.as_ref() else { return usage };
+        let vm = vm.read().unwrap();
+        let mut states = Dictionary::new();
+        for (name, stats) in vm.get_memory_stats() {
+            let mut scripts = Dictionary::new();
+            for (script, bytes) in stats.scripts {
+                scripts.set(script.get_full_name().unwrap_or_default(), bytes as i64);
+            }
+            let mut state = Dictionary::new();
+            state.set("used", stats.used as i64);
+            state.set("limit", stats.limit as i64);
+            state.set("scripts", scripts);
+            states.set(name, state);
+        }
+        usage.set("total", vm.get_memory_usage() as i64);
+        usage.set("states", states);
+        usage
+    }
+    /// Returns the queued threads of each state's task scheduler and the work done by its last cycles, as
+    /// [code]{ name: { "synchronized": queue, "desynchronized": queue, "threads": [ { "queue": String, "dispatch": String, "script": String, "wake_time": float } ] } }[/code],
+    /// where a queue is [code]{ "deferred": int, "delayed": int, "waiting": int, "defer_resumes": int, "delay_resumes": int, "defer_time": float, "delay_time": float }[/code].
+    /// [param script] is empty for threads not owned by a script, [param wake_time] is [code]null[/code] for deferred threads.
+    /// Empty when called while the workers of the parallel phase run.
+    #[func]
+    fn get_task_scheduler_stats(&self) -> Dictionary {
+        let mut states = Dictionary::new();
+        let Some(vm) = self.vm.as_ref() else { return states };
+        let queue = |counts: QueueCounts, cycle: CycleStats| {
+            let mut queue = Dictionary::new();
+            queue.set("deferred", counts.deferred as i64);
+            queue.set("delayed", counts.delayed as i64);
+            queue.set("waiting", counts.waiting as i64);
+            queue.set("defer_resumes", cycle.defer_resumes as i64);
+            queue.set("delay_resumes", cycle.delay_resumes as i64);
+            queue.set("defer_time", cycle.defer_time);
+            queue.set("delay_time", cycle.delay_time);
+            queue
+        };
+        for (name, snapshot) in vm.read().unwrap().get_task_scheduler_snapshots().unwrap_or_default() {
+            let threads: Array<Dictionary> = snapshot.threads.into_iter()
+                .map(|thread| {
+                    let mut entry = Dictionary::new();
+                    entry.set("queue", format!("{:?}", thread.kind));
+                    entry.set("dispatch", format!("{:?}", thread.dispatch));
+                    entry.set("script", thread.script
+                        .and_then(|x| x.upgrade())
+                        .and_then(|x| x.get_full_name().ok())
+                        .unwrap_or_default());
+                    entry.set("wake_time", thread.wake_time.map(|x| x.to_variant()).unwrap_or_default());
+                    entry
+                })
+                .collect();
+            let mut state = Dictionary::new();
+            state.set("synchronized", queue(snapshot.synchronized, snapshot.synchronized_cycle));
+            state.set("desynchronized", queue(snapshot.desynchronized, snapshot.desynchronized_cycle));
+            state.set("threads", threads);
+            states.set(name, state);
+        }
+        states
+    }
+    /// Switches the VM between real time and a virtual clock which only moves with [method advance_clock].
+    /// The new clock continues from the current time. Scheduler waits follow the virtual clock, the watchdogs keep using wall time.
+    #[func]
+    fn set_virtual_clock(&mut self, enabled: bool) {
+        let Some(vm) = self.vm.as_ref() else { return };
+        let mut vm = vm.write().unwrap();
+        let now = vm.get_clock().now();
+        if vm.get_clock().is_virtual() != enabled {
+            vm.set_clock(if enabled { Clock::new_virtual(now) } else { Clock::new_real_time_at(now) });
+        }
+    }
+    /// Moves the virtual clock forward by [param seconds], returns [code]false[/code] if the VM runs in real time.
+    #[func]
+    fn advance_clock(&self, seconds: f64) -> bool {
+        self.vm.as_ref().is_some_and(|vm| vm.read().unwrap().get_clock().advance(seconds))
+    }
+    /// Returns the time of the VM's clock in seconds, as returned by [code]time()[/code] in scripts.
+    #[func]
+    fn get_clock_time(&self) -> f64 {
+        self.vm.as_ref().map(|vm| vm.read().unwrap().get_clock().now()).unwrap_or_default()
+    }
+    /// Returns the messages printed so far, oldest first, like [code]LogService:GetLogHistory()[/code].
+    /// Each entry is [code]{ "message": String, "message_type": int, "timestamp": float }[/code].
+    #[func]
+    fn get_log_history(&self) -> Array<Dictionary> {
+        let Some(vm) = self.vm.as_ref() else { return Array::new() };
+        vm.read().unwrap().get_log_history().into_iter()
+            .map(|message| {
+                let mut entry = Dictionary::new();
+                entry.set("message", message.message);
+                entry.set("message_type", message.message_type as i64);
+                entry.set("timestamp", message.timestamp);
+                entry
+            })
+            .collect()
+    }
     /// Pushes Lua code to the task scheduler and runs it on the next deferred cycle.
     #[func]
     fn push_code(&mut self, chunk: GString) -> Error {
-        self.vm.as_mut().map(|vm| {
+        if let Some(vm) = self.vm.as_mut() {
             let mut write = vm.write()
                 .inspect_err(|_| godot_error!("RobloxVMNode: failed to acquire write lock on RobloxVM"))
                 .map_err(|_| Error::ERR_CANT_ACQUIRE_RESOURCE)
                 .unwrap();
-            let state = write.get_main_state();
-            let env = state.create_env_from_global().unwrap();
-            let func = state.compile_jit("<godot>", chunk.to_string().as_str(), env).unwrap();
-            let lua = unsafe {(&raw const *state.get_lua()).as_ref().unwrap_unchecked()};
-            let thr = unsafe { borrowck_ignore_mut(state) }.get_task_scheduler_mut()
-                .defer_func(lua, func, (), Synchronized)
-                .inspect_err(|_| godot_error!("RobloxVMNode: failed to defer on task scheduler"))
+            write.push_code("<godot>", chunk.to_string().as_str())
+                .inspect_err(|e| godot_error!("RobloxVMNode: failed to push code: {}", e))
                 .unwrap();
-            state.set_thread_identity(thr, ThreadIdentity {
-                security_identity: ThreadIdentityType::UserInit,
-                script: None
-            });
-        });
+        }
         Error::OK
     }
 }
\ No newline at end of file
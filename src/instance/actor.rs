@@ -1,149 +1,77 @@
 use std::collections::HashMap;
 
-use super::{pvinstance::IPVInstance, DynInstance, IInstance, IInstanceComponent, IModel, IObject, InstanceComponent, ManagedInstance, ModelComponent, PVInstanceComponent};
-use crate::{core::{get_state_with_rwlock, lua_macros::{lua_getter, lua_invalid_argument}, IWeak, InheritanceBase, InheritanceTableBuilder, Irc, LuauState, ParallelDispatch::{Desynchronized, Synchronized}, RobloxVM, RwLock, RwLockReadGuard, RwLockWriteGuard, Trc}, userdata::{ManagedRBXScriptSignal, RBXScriptConnection, RBXScriptSignal}};
+use super::{pvinstance::IPVInstance, DynInstance, IInstance, IInstanceComponent, IModel, InstanceClass, InstanceComponent, ManagedInstance, ModelComponent, PVInstanceComponent};
+use crate::{core::{get_state, get_state_with_rwlock, lua_macros::{lua_getter, lua_invalid_argument}, IWeak, Irc, LuauState, ParallelDispatch::{Desynchronized, Synchronized}, RobloxVM, RwLock, RwLockWriteGuard, Trc}, userdata::{ManagedRBXScriptSignal, RBXScriptConnection, RBXScriptSignal}};
 use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
 
 pub type ManagedActor = Irc<Actor>;
 pub type WeakManagedActor = IWeak<Actor>;
 
-#[derive(Debug)]
+#[derive(Debug, Instance)]
+#[instance(lua_get = Self::lua_get_members, get_actor = Self::get_self_actor, no_new)]
 pub struct Actor {
+    #[component(IInstance)]
     instance: RwLock<InstanceComponent>,
+    #[component(IPVInstance)]
     pvinstance: RwLock<PVInstanceComponent>,
+    #[component(IModel)]
     model: RwLock<ModelComponent>,
+    #[instance(clone = Self::clone_state)]
     state: Trc<LuauState>,
+    #[instance(clone = Self::clone_messages_bound)]
     messages_bound: RwLock<HashMap<String, ManagedRBXScriptSignal>>
 }
 
-impl InheritanceBase for Actor {
-    fn inheritance_table(&self) -> crate::core::InheritanceTable {
-        InheritanceTableBuilder::new()
-            .insert_type::<Actor, dyn IObject>(|x| x, |x| x)
-            .insert_type::<Actor, dyn IInstance>(|x| x, |x| x)
-            .insert_type::<Actor, dyn IPVInstance>(|x| x, |x| x)
-            .insert_type::<Actor, dyn IModel>(|x| x, |x| x)
-            .insert_type::<Actor, Actor>(|x| x, |x| x)
-            .output()
+impl Actor {
+    pub const CLASS: InstanceClass = InstanceClass {
+        name: "Actor",
+        new: |lua| Actor::new(get_state(lua).get_vm_mut())
+    };
+    pub fn new(mut vm: RwLockWriteGuard<'_, RobloxVM>) -> ManagedInstance {
+        let actor: Irc<DynInstance> = Irc::new_cyclic(|x|
+            Actor {
+                instance: RwLock::new_with_flag_auto(InstanceComponent::new(x.cast_to_instance(), "Actor")),
+                pvinstance: RwLock::new_with_flag_auto(PVInstanceComponent::new(x.cast_to_instance(), "Actor")),
+                model: RwLock::new_with_flag_auto(ModelComponent::new(x.cast_to_instance(), "Actor")),
+                state: vm.create_sub_state(),
+                messages_bound: RwLock::new(HashMap::new())
+        }).cast_from_sized().unwrap();
+        actor
     }
-}
-
-impl IObject for Actor {
-    fn lua_get(&self, lua: &Lua, name: String) -> LuaResult<LuaValue> {
-        match name.as_str() {
-            "BindToMessage" => lua_getter!(function, lua, |lua, (this, topic, function): (ManagedInstance, String, LuaFunction)|
+    fn lua_get_members(&self, lua: &Lua, name: &str) -> Option<LuaResult<LuaValue>> {
+        match name {
+            "BindToMessage" => lua_getter!(function_opt, lua, |lua, (this, topic, function): (ManagedInstance, String, LuaFunction)|
                 this.cast_from_unsized::<Actor>()
                     .map_err(|_| lua_invalid_argument!("Actor::BindToMessage", 1, self cast Instance to Actor))
                     .map(|this| this.bind_to_message(lua, topic, function))
             ),
-            "BindToMessageParallel" => lua_getter!(function, lua, |lua, (this, topic, function): (ManagedInstance, String, LuaFunction)|
+            "BindToMessageParallel" => lua_getter!(function_opt, lua, |lua, (this, topic, function): (ManagedInstance, String, LuaFunction)|
                 this.cast_from_unsized::<Actor>()
                     .map_err(|_| lua_invalid_argument!("Actor::BindToMessageParallel", 1, self cast Instance to Actor))
                     .map(|this| this.bind_to_message_parallel(lua, topic, function))
             ),
-            "SendMessage" => lua_getter!(function, lua, |lua, (this, topic, args): (ManagedInstance, String, LuaMultiValue)|
+            "SendMessage" => lua_getter!(function_opt, lua, |lua, (this, topic, args): (ManagedInstance, String, LuaMultiValue)|
                 this.cast_from_unsized::<Actor>()
                     .map_err(|_| lua_invalid_argument!("Actor::SendMessage", 1, self cast Instance to Actor))
                     .map(|this| this.send_message(lua, topic, args))
             ),
-            _ => self.get_model_component().lua_get(self, lua, &name)
-                .or_else(|| self.get_pv_instance_component().lua_get(self, lua, &name))
-                .unwrap_or_else(|| self.get_instance_component().lua_get(lua, &name))
+            _ => None
         }
     }
-
-    fn get_class_name(&self) -> &'static str { "Actor" }
-
-    fn get_property_changed_signal(&self, property: String) -> ManagedRBXScriptSignal {
-        self.get_instance_component().get_property_changed_signal(property).unwrap()
-    }
-
-    fn is_a(&self, class_name: &String) -> bool {
-        match class_name.as_str() {
-            "Object" |
-            "Instance" |
-            "PVInstance" |
-            "Model" |
-            "Actor" => true,
-            _ => false
-        }
-    }
-
-    fn get_changed_signal(&self) -> ManagedRBXScriptSignal {
-        self.get_instance_component().changed.clone()
-    }
-}
-
-impl IInstance for Actor {
-    fn get_instance_component(&self) -> RwLockReadGuard<InstanceComponent> {
-        self.instance.read().unwrap()
-    }
-
-    fn get_instance_component_mut(&self) -> RwLockWriteGuard<InstanceComponent> {
-        self.instance.write().unwrap()
-    }
-
-    fn lua_set(&self, lua: &Lua, name: String, val: LuaValue) -> LuaResult<()> {
-        self.get_model_component_mut().lua_set(self, lua, &name, &val)
-            .or_else(|| self.get_pv_instance_component_mut().lua_set(self, lua, &name, &val))
-            .unwrap_or_else(|| self.get_instance_component_mut().lua_set(lua, &name, val))
-    }
-
-    fn clone_instance(&self, lua: &Lua) -> LuaResult<ManagedInstance> {
-        Ok(Irc::new_cyclic_fallable::<_, LuaError>(|x| {
-            let i = x.cast_to_instance();
-            let state = self.state.read().get_vm_mut().create_sub_state();
-            let a = Actor {
-                instance: RwLock::new_with_flag_auto(self.get_instance_component().clone(lua, &i)?),
-                pvinstance: RwLock::new_with_flag_auto(self.get_pv_instance_component().clone(lua, &i)?),
-                model: RwLock::new_with_flag_auto(self.get_model_component().clone(lua, &i)?),
-                state,
-                messages_bound: RwLock::new(HashMap::new())
-            };
-            Ok(a)
-        })?.cast_from_sized().unwrap())
-    }
-
-    fn get_actor(&self) -> LuaResult<Option<ManagedInstance>> {
+    fn get_self_actor(&self) -> LuaResult<Option<ManagedInstance>> {
         Ok(Some(self.get_instance_component().get_instance_pointer()))
     }
-}
-
-impl IPVInstance for Actor {
-    fn get_pv_instance_component(&self) -> RwLockReadGuard<'_, PVInstanceComponent> {
-        self.pvinstance.read().unwrap()
-    }
-
-    fn get_pv_instance_component_mut(&self) -> RwLockWriteGuard<'_, PVInstanceComponent> {
-        self.pvinstance.write().unwrap()
-    }
-}
-
-impl IModel for Actor {
-    fn get_model_component(&self) -> RwLockReadGuard<'_, ModelComponent> {
-        self.model.read().unwrap()
-    }
-
-    fn get_model_component_mut(&self) -> RwLockWriteGuard<'_, ModelComponent> {
-        self.model.write().unwrap()
+    /// A cloned Actor runs its scripts in a state of its own.
+    fn clone_state(&self, _: &Lua) -> LuaResult<Trc<LuauState>> {
+        Ok(self.state.read().get_vm_mut().create_sub_state())
     }
-}
-
-impl Actor {
-    pub fn new(mut vm: RwLockWriteGuard<'_, RobloxVM>) -> ManagedInstance {
-        let actor: Irc<DynInstance> = Irc::new_cyclic(|x|
-            Actor {
-                instance: RwLock::new_with_flag_auto(InstanceComponent::new(x.cast_to_instance(), "Actor")),
-                pvinstance: RwLock::new_with_flag_auto(PVInstanceComponent::new(x.cast_to_instance(), "Actor")),
-                model: RwLock::new_with_flag_auto(ModelComponent::new(x.cast_to_instance(), "Actor")),
-                state: vm.create_sub_state(),
-                messages_bound: RwLock::new(HashMap::new())
-        }).cast_from_sized().unwrap();
-        actor
+    fn clone_messages_bound(&self, _: &Lua) -> LuaResult<RwLock<HashMap<String, ManagedRBXScriptSignal>>> {
+        Ok(RwLock::new(HashMap::new()))
     }
 
     pub fn bind_to_message(&self, lua: &Lua, topic: String, function: LuaFunction) -> LuaResult<RBXScriptConnection> {
-        if *get_state_with_rwlock(lua) != self.state {
+        if get_state_with_rwlock(lua) != self.state {
             return Err(LuaError::RuntimeError("Cannot bind from outside of the Actor.".into()));
         }
         let mut messages_bound = self.messages_bound.write().unwrap();
@@ -157,7 +85,7 @@ impl Actor {
         }
     }
     pub fn bind_to_message_parallel(&self, lua: &Lua, topic: String, function: LuaFunction) -> LuaResult<RBXScriptConnection> {
-        if *get_state_with_rwlock(lua) != self.state {
+        if get_state_with_rwlock(lua) != self.state {
             return Err(LuaError::RuntimeError("Cannot bind from outside of the Actor.".into()));
         }
         let mut messages_bound = self.messages_bound.write().unwrap();
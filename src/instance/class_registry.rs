@@ -0,0 +1,84 @@
+use r2g_mlua::prelude::*;
+
+use crate::core::{get_task_scheduler_from_lua, ClassDescriptor, CLASS_DATABASE};
+
+use super::{Actor, CollectionService, LocalScript, LogService, ManagedInstance, Model, ModuleScript, RunService, ScriptContext, Script, Stats, Workspace};
+
+/// A class `Instance.new` and `GetService` can create. Whether it is creatable or a service comes from the class database.
+#[derive(Debug)]
+pub struct InstanceClass {
+    pub name: &'static str,
+    pub new: fn(&Lua) -> ManagedInstance
+}
+
+/// Every class with a constructor, sorted by name.
+static INSTANCE_CLASSES: &[InstanceClass] = &[
+    Actor::CLASS,
+    CollectionService::CLASS,
+    LocalScript::CLASS,
+    LogService::CLASS,
+    Model::CLASS,
+    ModuleScript::CLASS,
+    RunService::CLASS,
+    Script::CLASS,
+    ScriptContext::CLASS,
+    Stats::CLASS,
+    Workspace::CLASS
+];
+
+// `find` binary searches the classes.
+const _: () = assert!(is_sorted_by_name(INSTANCE_CLASSES), "INSTANCE_CLASSES must be sorted by name");
+
+const fn is_sorted_by_name(classes: &[InstanceClass]) -> bool {
+    let mut i = 1;
+    while i < classes.len() {
+        if !is_name_before(classes[i - 1].name.as_bytes(), classes[i].name.as_bytes()) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+/// `a < b`, like `str::cmp` compares them.
+const fn is_name_before(a: &[u8], b: &[u8]) -> bool {
+    let mut i = 0;
+    while i < a.len() && i < b.len() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+        i += 1;
+    }
+    a.len() < b.len()
+}
+
+impl InstanceClass {
+    pub fn find(name: &str) -> Option<&'static InstanceClass> {
+        INSTANCE_CLASSES.binary_search_by(|x| x.name.cmp(name)).ok().map(|i| &INSTANCE_CLASSES[i])
+    }
+    /// The class `Instance.new(name)` creates. Roblox gives the same error for unknown, abstract and service classes.
+    pub fn find_creatable(name: &str) -> LuaResult<&'static InstanceClass> {
+        if !CLASS_DATABASE.get_class(name).is_some_and(|x| x.is_creatable()) {
+            return Err(LuaError::RuntimeError(format!("Unable to create an Instance of type \"{}\"", name)));
+        }
+        Self::find(name)
+            .ok_or_else(|| LuaError::RuntimeError(format!("todo!(): Instance.new(\"{}\") is not yet implemented", name)))
+    }
+    /// The class `GetService(name)` creates.
+    pub fn find_service(name: &str) -> LuaResult<&'static InstanceClass> {
+        if !CLASS_DATABASE.get_class(name).is_some_and(|x| x.is_service()) {
+            return Err(LuaError::RuntimeError(format!("'{}' is not a valid Service name", name)));
+        }
+        Self::find(name)
+            .ok_or_else(|| LuaError::RuntimeError(format!("todo!(): service {} is not yet implemented", name)))
+    }
+    pub fn get_descriptor(&self) -> &'static ClassDescriptor {
+        CLASS_DATABASE.get_class(self.name).expect("instance classes are in the class database")
+    }
+    /// Creates an instance of the class. Actors add a state to the VM, which can't change while workers use its states.
+    pub fn create(&self, lua: &Lua) -> LuaResult<ManagedInstance> {
+        if self.name == Actor::CLASS.name && get_task_scheduler_from_lua(lua).is_desynchronized() {
+            return Err(LuaError::RuntimeError("Actors can't be created in parallel".into()));
+        }
+        Ok((self.new)(lua))
+    }
+}
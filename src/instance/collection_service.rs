@@ -0,0 +1,65 @@
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
+
+use crate::core::lua_macros::lua_getter;
+use crate::core::{get_state, RwLock};
+use super::{DynInstance, IInstance, InstanceComponent, ManagedInstance};
+
+#[derive(Debug, Instance)]
+#[instance(lua_get = Self::lua_get_members, uncloneable)]
+pub struct CollectionService {
+    #[component(IInstance)]
+    instance_component: RwLock<InstanceComponent>
+}
+
+impl CollectionService {
+    /// Instances with `tag` which are descendants of the DataModel, the others don't count for CollectionService.
+    pub fn get_tagged(lua: &Lua, tag: &str) -> Vec<ManagedInstance> {
+        let tagged = get_state(lua).get_vm().get_instance_tag_table().get_tagged(tag);
+        tagged.into_iter()
+            .filter(|x| DynInstance::guard_is_in_data_model(&x.get_instance_component()))
+            .collect()
+    }
+    pub fn get_all_tags(lua: &Lua) -> Vec<String> {
+        get_state(lua).get_vm().get_instance_tag_table()
+            .get_tags(|x| DynInstance::guard_is_in_data_model(&x.get_instance_component()))
+    }
+    fn lua_get_members(&self, lua: &Lua, name: &str) -> Option<LuaResult<LuaValue>> {
+        match name {
+            "GetTagged" => lua_getter!(function_opt, lua,
+                |lua, (_, tag): (ManagedInstance, String)|
+                    Ok(CollectionService::get_tagged(lua, &tag))
+            ),
+            "GetAllTags" => lua_getter!(function_opt, lua,
+                |lua, _: ManagedInstance|
+                    Ok(CollectionService::get_all_tags(lua))
+            ),
+            "GetInstanceAddedSignal" => lua_getter!(function_opt, lua,
+                |lua, (_, tag): (ManagedInstance, String)|
+                    Ok(get_state(lua).get_vm().get_instance_tag_table().get_added_signal(tag))
+            ),
+            "GetInstanceRemovedSignal" => lua_getter!(function_opt, lua,
+                |lua, (_, tag): (ManagedInstance, String)|
+                    Ok(get_state(lua).get_vm().get_instance_tag_table().get_removed_signal(tag))
+            ),
+            // Legacy counterparts of the Instance methods.
+            "AddTag" => lua_getter!(function_opt, lua,
+                |lua, (_, instance, tag): (ManagedInstance, ManagedInstance, String)|
+                    instance.add_tag(lua, tag)
+            ),
+            "GetTags" => lua_getter!(function_opt, lua,
+                |_, (_, instance): (ManagedInstance, ManagedInstance)|
+                    instance.get_tags()
+            ),
+            "HasTag" => lua_getter!(function_opt, lua,
+                |_, (_, instance, tag): (ManagedInstance, ManagedInstance, String)|
+                    instance.has_tag(tag)
+            ),
+            "RemoveTag" => lua_getter!(function_opt, lua,
+                |lua, (_, instance, tag): (ManagedInstance, ManagedInstance, String)|
+                    instance.remove_tag(lua, tag)
+            ),
+            _ => None
+        }
+    }
+}
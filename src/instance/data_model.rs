@@ -1,17 +1,17 @@
 use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
 
 use crate::core::FastFlags;
-use crate::core::{get_state, inheritance_cast_to, FastFlag, InheritanceBase, InheritanceTable, InheritanceTableBuilder, Irc, 
+use crate::core::{get_state, inheritance_cast_to, FastFlag, Irc, 
     ParallelDispatch::Synchronized, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use crate::core::lua_macros::{lua_getter, lua_invalid_argument};
 use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal};
 
-use super::{IInstanceComponent, DynInstance, IInstance, IObject, IServiceProvider, InstanceComponent, ManagedInstance, ServiceProviderComponent, WeakManagedInstance};
+use super::{IInstanceComponent, DynInstance, IInstance, IServiceProvider, InstanceClass, InstanceComponent, ManagedInstance, ServiceProviderComponent, WeakManagedInstance};
 
 #[derive(Debug)]
 pub struct DataModelComponent {
     bind_close: ManagedRBXScriptSignal,
-    workspace: (), //todo!
 
     pub graphics_quality_change_request: ManagedRBXScriptSignal,
     pub loaded: ManagedRBXScriptSignal,
@@ -19,10 +19,14 @@ pub struct DataModelComponent {
 
 }
 
-#[derive(Debug)]
+#[derive(Debug, Instance)]
+#[instance(uncloneable, no_new)]
 pub struct DataModel {
+    #[component(IInstance)]
     instance: RwLock<InstanceComponent>,
+    #[component(IServiceProvider, manual)]
     service_provider: RwLock<ServiceProviderComponent>,
+    #[component(IDataModel)]
     data_model: RwLock<DataModelComponent>
 }
 
@@ -31,65 +35,6 @@ pub trait IDataModel {
     fn get_data_model_component_mut(&self) -> RwLockWriteGuard<'_,DataModelComponent>;
 }
 
-impl InheritanceBase for DataModel {
-    fn inheritance_table(&self) -> InheritanceTable {
-        InheritanceTableBuilder::new()
-            .insert_type::<DataModel, dyn IObject>(|x| x, |x| x)
-            .insert_type::<DataModel, DynInstance>(|x| x, |x| x)
-            .insert_type::<DataModel, dyn IServiceProvider>(|x| x, |x| x)
-            .insert_type::<DataModel, dyn IDataModel>(|x| x, |x| x)
-            .output()
-    }
-}
-
-impl IObject for DataModel {
-    fn lua_get(&self, lua: &Lua, name: String) -> LuaResult<LuaValue> {
-        self.data_model.read().unwrap().lua_get(self, lua, &name)
-            .or_else(|| self.service_provider.read().unwrap().lua_get(self, lua, &name))
-            .unwrap_or_else(|| self.instance.read().unwrap().lua_get(lua, &name))
-    }
-
-    fn get_class_name(&self) -> &'static str { "DataModel" }
-
-    fn get_property_changed_signal(&self, property: String) -> ManagedRBXScriptSignal {
-        self.get_instance_component().get_property_changed_signal(property).unwrap()
-    }
-
-    fn is_a(&self, class_name: &String) -> bool {
-        match class_name.as_str() {
-            "DataModel" => true,
-            "ServiceProvider" => true,
-            "Instance" => true,
-            "Object" => true,
-            _ => false
-        }
-    }
-
-    fn get_changed_signal(&self) -> ManagedRBXScriptSignal {
-        self.get_instance_component().changed.clone()
-    }
-}
-
-impl IInstance for DataModel {
-    fn get_instance_component(&self) -> RwLockReadGuard<InstanceComponent> {
-        self.instance.read().unwrap()
-    }
-
-    fn get_instance_component_mut(&self) -> RwLockWriteGuard<InstanceComponent> {
-        self.instance.write().unwrap()
-    }
-
-    fn lua_set(&self, lua: &Lua, name: String, val: LuaValue) -> LuaResult<()> {
-        self.data_model.write().unwrap().lua_set(self, lua, &name, &val)
-            .or_else(|| self.service_provider.write().unwrap().lua_set(self, lua, &name, &val))
-            .unwrap_or_else(|| self.instance.write().unwrap().lua_set(lua, &name, val))
-    }
-
-    fn clone_instance(&self, _: &Lua) -> LuaResult<ManagedInstance> {
-        Err(LuaError::RuntimeError("DataModel cannot be cloned".into()))
-    }
-}
-
 impl IServiceProvider for DataModel {
     fn get_service_provider_component(&self) -> RwLockReadGuard<ServiceProviderComponent> {
         self.service_provider.read().unwrap()
@@ -99,11 +44,16 @@ impl IServiceProvider for DataModel {
         self.service_provider.write().unwrap()
     }
 
-    fn get_service(&self, service_name: String) -> LuaResult<ManagedInstance> {
-        self.find_service(service_name)
-            .and_then(|x|
-                x.ok_or_else(|| LuaError::RuntimeError("Service not found".into()))
-            )
+    fn get_service(&self, lua: &Lua, service_name: String) -> LuaResult<ManagedInstance> {
+        if let Some(service) = self.find_service(service_name.clone())? {
+            return Ok(service);
+        }
+        // Services are created the first time they are asked for.
+        let service = InstanceClass::find_service(&service_name)?.create(lua)?;
+        let game = self.instance.read().unwrap().get_instance_pointer();
+        service.set_parent(lua, Some(game))?;
+        service.lock_parent();
+        Ok(service)
     }
 
     fn find_service(&self, service_name: String) -> LuaResult<Option<ManagedInstance>> {
@@ -136,7 +86,7 @@ impl IInstanceComponent for DataModelComponent {
             "PlaceVersion" => Some(lua_getter!(lua, get_state(lua).flags().get_int(FastFlag::PlaceVersion))),
             "PrivateServerId" => Some(lua_getter!(lua, get_state(lua).flags().get_string(FastFlag::PrivateServerId))),
             "PrivateServerOwnerId" => Some(lua_getter!(lua, get_state(lua).flags().get_int(FastFlag::PrivateServerOwnerId))),
-            "Workspace" => todo!(),
+            "Workspace" => Some(_ptr.find_first_child_of_class("Workspace".into()).and_then(|x| x.into_lua(lua))),
             "BindToClose" => lua_getter!(function_opt, lua, |lua, (this, func): (ManagedInstance, LuaFunction)| {
                 inheritance_cast_to!(&*this, dyn IDataModel)
                     .map_err(|_|
@@ -163,7 +113,7 @@ impl IInstanceComponent for DataModelComponent {
             "PlaceVersion" |
             "PrivateServerId" |
             "PrivateServerOwnerId" |
-            "Workspace "=> Some(Err(LuaError::RuntimeError("Cannot set read only property.".into()))),
+            "Workspace" => Some(Err(LuaError::RuntimeError("Cannot set read only property.".into()))),
             _ => None
         }
     }
@@ -175,7 +125,6 @@ impl IInstanceComponent for DataModelComponent {
     fn new(_ptr: WeakManagedInstance, _class_name: &'static str) -> Self {
         Self {
             bind_close: RBXScriptSignal::new(),
-            workspace: (),
             graphics_quality_change_request: RBXScriptSignal::new(),
             loaded: RBXScriptSignal::new(),
             is_loaded: false
@@ -183,15 +132,6 @@ impl IInstanceComponent for DataModelComponent {
     }
 }
 
-impl IDataModel for DataModel {
-    fn get_data_model_component(&self) -> RwLockReadGuard<'_,DataModelComponent> {
-        self.data_model.read().unwrap()
-    }
-
-    fn get_data_model_component_mut(&self) -> RwLockWriteGuard<'_,DataModelComponent> {
-        self.data_model.write().unwrap()
-    }
-}
 impl dyn IDataModel {
     pub fn bind_to_close(&self, lua: &Lua, func: LuaFunction) -> LuaResult<()> {
         let read = self.get_data_model_component();
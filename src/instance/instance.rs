@@ -10,10 +10,10 @@ use r2g_mlua::prelude::*;
 
 use crate::core::alloc::Allocator;
 use crate::core::lua_macros::lua_getter;
-use crate::core::{get_state, get_task_scheduler_from_lua, IWeak, Irc, IrcHead, ParallelDispatch, RwLockReadGuard, RwLockWriteGuard};
-use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal};
+use crate::core::{get_state, registry_keys, IWeak, MemberDescriptor, CLASS_DATABASE, Irc, IrcHead, RwLockReadGuard, RwLockWriteGuard};
+use crate::userdata::{deserialize_attributes, serialize_attributes, AttributeValue, ManagedRBXScriptSignal, RBXScriptSignal};
 
-use super::IObject;
+use super::{IObject, InstanceClass};
 
 pub type DynInstance = dyn IInstance;
 pub type ManagedInstance = Irc<DynInstance>;
@@ -149,6 +149,8 @@ impl DynInstance {
     fn set_parent_forced(this: &mut WriteInstanceComponent, lua: &Lua, parent: Option<ManagedInstance>) -> LuaResult<()> {
         // Havent tested roblox's internal order
         // This internal order is: DescendantRemoving -> ChildRemoved -> AncestryChanged -> ChildAdded -> DescendantAdded
+        // CollectionService's removed signals fire before DescendantRemoving and its added signals after DescendantAdded.
+        let mut now_in_data_model = false;
         if parent.is_some() {
             let _parent_instance = parent.as_ref().unwrap();
             let p = _parent_instance.get_instance_component();
@@ -157,6 +159,20 @@ impl DynInstance {
             if DynInstance::guard_is_descendant_of(&p, this_ptr)? {
                 return Err(LuaError::RuntimeError("Invalid hierarchy while setting up instance tree.".into()))
             }
+            now_in_data_model = _parent_instance.is_a(&"DataModel".into()) || DynInstance::guard_is_in_data_model(&p);
+        }
+        let was_in_data_model = DynInstance::guard_is_in_data_model(&*this);
+        // The subtree is only walked when something listens to tags, moving a large tree is common.
+        let tags_watched = was_in_data_model != now_in_data_model
+            && get_state(lua).get_vm().get_instance_tag_table().has_signals();
+        let tagged = if tags_watched {
+            DynInstance::guard_get_tagged_subtree(&*this)?
+        } else {
+            Vec::new()
+        };
+        if was_in_data_model && !now_in_data_model {
+            let _guard_release = this.guard_release();
+            DynInstance::fire_tag_signals(lua, &tagged, false)?;
         }
         if this.parent.is_some() {
             // Descendant removing for all ancestors
@@ -177,7 +193,10 @@ impl DynInstance {
             let _ptr_this = this._ptr.as_ref().unwrap().upgrade().unwrap();
             let _guard_release = this.guard_release();
             let old_parent = old_parent.unwrap().upgrade().unwrap();
-            old_parent.get_instance_component_mut().children.retain(|x| *x != _ptr_this);
+            let mut old_parent_write = old_parent.get_instance_component_mut();
+            old_parent_write.children.retain(|x| *x != _ptr_this);
+            old_parent_write.children_cache_dirty = true;
+            drop(old_parent_write);
             old_parent.get_instance_component().child_removed.write().fire_ancestry(lua, (_ptr_this,))?;
         }
 
@@ -195,19 +214,77 @@ impl DynInstance {
             let ancestors = DynInstance::guard_get_ancestors(this);
             let _guard_release = this.guard_release();
             let new_parent = new_parent.unwrap();
-            new_parent.get_instance_component_mut().children.push(_ptr_this.clone());
+            let mut new_parent_write = new_parent.get_instance_component_mut();
+            new_parent_write.children.push(_ptr_this.clone());
+            new_parent_write.children_cache_dirty = true;
+            drop(new_parent_write);
             new_parent.get_instance_component().child_added.write().fire_ancestry(lua, (_ptr_this.clone(),))?;
             for ancestor in ancestors {
                 ancestor.get_instance_component().descendant_added.write().fire_ancestry(lua, (_ptr_this.clone(),))?;
             }
         }
+        if now_in_data_model && !was_in_data_model {
+            let _guard_release = this.guard_release();
+            DynInstance::fire_tag_signals(lua, &tagged, true)?;
+        }
         Ok(())
     }
     pub fn guard_get_name(this: &impl IReadInstanceComponent) -> String {
         this.name.clone()
     }
+    pub fn guard_get_full_name(this: &impl IReadInstanceComponent) -> String {
+        let mut hierarchy = vec![DynInstance::guard_get_name(this)];
+        let mut parent = DynInstance::guard_get_parent(this);
+        while let Some(parent_unwrapped) = parent {
+            let parent_read = parent_unwrapped.get_instance_component();
+            hierarchy.push(DynInstance::guard_get_name(&parent_read));
+            parent = DynInstance::guard_get_parent(&parent_read);
+        }
+        hierarchy.reverse();
+        hierarchy.join(".")
+    }
+    /// Whether the instance is the DataModel or one of its descendants.
+    pub fn guard_is_in_data_model(this: &impl IReadInstanceComponent) -> bool {
+        let root = DynInstance::guard_get_ancestors(this).pop()
+            .or_else(|| this._ptr.as_ref().and_then(|x| x.upgrade()));
+        root.is_some_and(|x| x.is_a(&"DataModel".into()))
+    }
+    /// The instance and its descendants which have tags, with their tags.
+    fn guard_get_tagged_subtree(this: &impl IReadInstanceComponent) -> LuaResult<Vec<(ManagedInstance, Vec<String>)>> {
+        let mut tagged = Vec::new();
+        if !this.tags.is_empty() {
+            tagged.push((this._ptr.as_ref().unwrap().upgrade().unwrap(), this.tags.iter().cloned().collect()));
+        }
+        for i in DynInstance::guard_get_descendants(this)? {
+            let tags: Vec<String> = i.get_instance_component().tags.iter().cloned().collect();
+            if !tags.is_empty() {
+                tagged.push((i, tags));
+            }
+        }
+        Ok(tagged)
+    }
+    /// Fires CollectionService's added or removed signals of the tags.
+    fn fire_tag_signals(lua: &Lua, tagged: &[(ManagedInstance, Vec<String>)], added: bool) -> LuaResult<()> {
+        let signals: Vec<_> = {
+            let vm = get_state(lua).get_vm();
+            let table = vm.get_instance_tag_table();
+            if !table.has_signals() {
+                return Ok(());
+            }
+            tagged.iter()
+                .flat_map(|(instance, tags)| tags.iter().filter_map(move |tag| table.find_signal(tag, added).map(|x| (x, instance.clone()))))
+                .collect()
+        };
+        for (signal, instance) in signals {
+            signal.write().fire(lua, (instance,))?;
+        }
+        Ok(())
+    }
     pub fn guard_set_name(this: &mut WriteInstanceComponent, val: String) -> LuaResult<()> {
         this.name = val;
+        if let Some(parent) = this.parent.as_ref().and_then(|x| x.upgrade()) {
+            parent.get_instance_component_mut().children_cache_dirty = true;
+        }
         Ok(())
     }
     pub fn guard_get_archivable(this: &impl IReadInstanceComponent) -> bool {
@@ -295,6 +372,11 @@ impl DynInstance {
         this.parent_locked = true;
         DynInstance::set_parent_forced(this, lua, None)?;
         DynInstance::guard_clear_all_children(this, lua)?;
+        // Modules have no other point where they stop being used.
+        let vm = get_state(lua).get_vm();
+        if let Some(debugger) = vm.get_debugger() {
+            debugger.release_script(this._ptr.as_ref().unwrap());
+        }
         Ok(())
     }
     #[inline]
@@ -317,51 +399,48 @@ impl DynInstance {
     pub fn get_debug_id(&self, _scope_length: LuaNumber) -> LuaResult<String> {
         Ok(format!("0x{:x}",(&raw const *self.get_instance_component()) as usize))
     }
-    pub fn get_full_name(&self) -> LuaResult<String> {
-        let mut hierarchy = Vec::new();
-        let mut parent = self.get_parent();
-        while parent.is_some() {
-            let parent_unwrapped = unsafe { parent.unwrap_unchecked() };
-            let parent_read = parent_unwrapped.get_instance_component();
-            hierarchy.insert(0, DynInstance::guard_get_name(&parent_read));
-
-            parent = DynInstance::guard_get_parent(&parent_read);
+    /// Instance.fromExisting, a new instance of the same class with the same properties but without the children.
+    pub fn from_existing(&self, lua: &Lua) -> LuaResult<ManagedInstance> {
+        let instance = InstanceClass::find_creatable(self.get_class_name())?.create(lua)?;
+        for class in CLASS_DATABASE.get_inheritance_chain(self.get_class_name()) {
+            for property in class.properties.iter().filter(|x| x.is_scriptable() && !x.is_read_only() && x.name != "Parent") {
+                let result = self.lua_get(lua, property.name.into())
+                    .and_then(|value| instance.lua_set(lua, property.name.into(), value));
+                // Properties which aren't implemented yet are skipped.
+                match result {
+                    Err(err) if !InstanceComponent::is_not_implemented_error(&err) => return Err(err),
+                    _ => {}
+                }
+            }
         }
-        hierarchy.push(self.get_name());
-        Ok(hierarchy.join("."))
+        Ok(instance)
+    }
+    pub fn get_full_name(&self) -> LuaResult<String> {
+        Ok(DynInstance::guard_get_full_name(&self.get_instance_component()))
     }
     pub fn add_tag(&self, lua: &Lua, tag: String) -> LuaResult<()> {
         let mut write = self.get_instance_component_mut();
         let ptr = write._ptr.clone().unwrap();
-        write.tags.insert(tag.clone());
+        let added = write.tags.insert(tag.clone());
+        let in_data_model = DynInstance::guard_is_in_data_model(&write);
         drop(write);
-        get_state(lua).get_vm().get_instance_tag_table().add_tag(tag, ptr);
+        let instance = ptr.upgrade().unwrap();
+        get_state(lua).get_vm().get_instance_tag_table().add_tag(tag.clone(), ptr);
+        if added && in_data_model {
+            DynInstance::fire_tag_signals(lua, &[(instance, vec![tag])], true)?;
+        }
         Ok(())
     }
     
-    pub async fn wait_for_child(&self, lua: &Lua, name: String, timeout: Option<LuaNumber>) -> LuaResult<Option<ManagedInstance>> {
-        let inst = self.find_first_child(name.clone(), Some(false)).unwrap();
-        if inst.is_some() {
-            Ok(Some(unsafe { inst.unwrap_unchecked() }))
-        } else {
-            let thread = lua.current_thread();
-            if timeout.is_some() {
-                get_task_scheduler_from_lua(lua).delay_thread(thread.clone(), ParallelDispatch::Default, timeout.unwrap())?;
-            }
-            let i = self.get_instance_component().child_added.clone();
-            let mut instance: ManagedInstance;
-            loop {
-                let mv = i.read().wait(lua).await?;
-                if mv.is_empty() {
-                    return Ok(None); // timed out
-                }
-                instance = ManagedInstance::from_lua_multi(mv, lua)?;
-                if instance.get_name() == name {
-                    break;
-                }
-            }
-            Ok(Some(instance))
+    /// Returns the child named `name`, or parks the current thread on ChildAdded until it is added, or until `timeout` seconds passed.
+    /// When parked, this returns `None` and the thread must yield.
+    pub fn wait_for_child(&self, lua: &Lua, name: String, timeout: Option<LuaNumber>) -> LuaResult<Option<ManagedInstance>> {
+        let inst = self.find_first_child(name.clone(), Some(false))?;
+        if inst.is_none() {
+            let child_added = self.get_instance_component().child_added.clone();
+            child_added.write().park(lua, Some(name), timeout);
         }
+        Ok(inst)
     }
     pub fn find_first_ancestor(&self, name: String) -> LuaResult<Option<ManagedInstance>> {
         for i in self.get_ancestors() {
@@ -423,10 +502,11 @@ impl DynInstance {
         }
         Ok(None)
     }
-    pub fn get_attribute(&self, attribute: String) -> LuaResult<LuaValue> {
-        Ok(self.get_instance_component().attributes.get(&attribute).unwrap_or(&LuaNil).clone())
+    pub fn get_attribute(&self, attribute: String) -> LuaResult<Option<AttributeValue>> {
+        Ok(self.get_instance_component().attributes.get(&attribute).cloned())
     }
     pub fn get_attribute_changed_signal(&self, attribute: String) -> LuaResult<ManagedRBXScriptSignal> {
+        AttributeValue::check_name(&attribute)?;
         let read = self.get_instance_component();
         if let Some(event) = read.attribute_changed_table.get(&attribute) {
             Ok(event.clone())
@@ -438,8 +518,17 @@ impl DynInstance {
             Ok(event)
         }
     }
-    pub fn get_attributes(&self, lua: &Lua) -> LuaResult<LuaValue> {
-        self.get_instance_component().attributes.clone().into_lua(lua)
+    pub fn get_attributes(&self) -> LuaResult<HashMap<String, AttributeValue>> {
+        Ok(self.get_instance_component().attributes.clone())
+    }
+    /// The attributes in the format of the `AttributesSerialize` property of place files.
+    pub fn get_attributes_serialize(&self) -> Vec<u8> {
+        serialize_attributes(&self.get_instance_component().attributes)
+    }
+    /// Replaces the attributes with ones loaded from a place file, without firing the changed signals.
+    pub fn set_attributes_serialize(&self, data: &[u8]) -> LuaResult<()> {
+        self.get_instance_component_mut().attributes = InstanceComponent::load_attributes(data)?;
+        Ok(())
     }
     pub fn get_tags(&self) -> LuaResult<Vec<String>> {
         Ok(self.get_instance_component().tags.iter().map(|x| x.clone()).collect())
@@ -463,16 +552,29 @@ impl DynInstance {
     pub fn remove_tag(&self, lua: &Lua, tag: String) -> LuaResult<()> {
         let mut write = self.get_instance_component_mut();
         let ptr = write._ptr.clone().unwrap();
-        write.tags.remove(&tag);
+        let removed = write.tags.remove(&tag);
+        let in_data_model = DynInstance::guard_is_in_data_model(&write);
         drop(write);
-        get_state(lua).get_vm().get_instance_tag_table().remove_tag(tag, &ptr);
+        get_state(lua).get_vm().get_instance_tag_table().remove_tag(tag.clone(), &ptr);
+        if removed && in_data_model {
+            DynInstance::fire_tag_signals(lua, &[(ptr.upgrade().unwrap(), vec![tag])], false)?;
+        }
         Ok(())
     }
-    pub fn set_attribute(&self, lua: &Lua, attribute: String, value: LuaValue) -> LuaResult<()> {
+    /// Sets the attribute, or removes it when `value` is nil. The changed signals only fire when the value changes.
+    pub fn set_attribute(&self, lua: &Lua, attribute: String, value: Option<AttributeValue>) -> LuaResult<()> {
+        AttributeValue::check_name(&attribute)?;
         let mut write = self.get_instance_component_mut();
-        write.attributes.insert(attribute.clone(), value.clone());
+        let old_value = match value.clone() {
+            Some(value) => write.attributes.insert(attribute.clone(), value),
+            None => write.attributes.remove(&attribute)
+        };
+        if old_value == value {
+            return Ok(());
+        }
         let attribute_changed = write.attribute_changed.clone();
         let attribute_changed_signal = write.attribute_changed_table.get(&attribute).cloned();
+        drop(write);
         attribute_changed.write().fire(lua, (attribute,))?;
         attribute_changed_signal.map(move |x| x.write().fire(lua, (value,))).unwrap_or(Ok(()))
     }
@@ -509,7 +611,7 @@ pub struct InstanceComponent {
     pub attribute_changed_table: EventsTable,
     pub property_changed_table: EventsTable,
 
-    attributes: HashMap<String, LuaValue>,
+    attributes: HashMap<String, AttributeValue>,
     tags: HashSet<String>
 }
 
@@ -563,9 +665,16 @@ impl IInstanceComponent for InstanceComponent {
         for i in self.children.iter() {
             let inst = i.clone_instance(lua);
             if inst.is_ok() {
-                new_children.push(unsafe { inst.unwrap_unchecked() });
+                let inst = unsafe { inst.unwrap_unchecked() };
+                inst.get_instance_component_mut().parent = Some(ptr.clone());
+                new_children.push(inst);
             }
         }
+        let vm = get_state(lua).get_vm();
+        for tag in self.tags.iter() {
+            vm.get_instance_tag_table().add_tag(tag.clone(), ptr.clone());
+        }
+        drop(vm);
         Ok(InstanceComponent {
             archivable: self.archivable,
             name: self.name.clone(),
@@ -590,7 +699,7 @@ impl IInstanceComponent for InstanceComponent {
             property_changed_table: EventsTable::default(),
 
             attributes: self.attributes.clone(),
-            tags: HashSet::default()
+            tags: self.tags.clone()
         })
     }
 
@@ -605,13 +714,19 @@ impl InstanceComponent {
     pub fn get_weak_instance_pointer(self: &RwLockReadGuard<'_, Self>) -> WeakManagedInstance {
         unsafe { self._ptr.as_ref().unwrap_unchecked().clone() }
     }
+    /// Reads attributes saved in the format of the `AttributesSerialize` property, checking their names.
+    fn load_attributes(data: &[u8]) -> LuaResult<HashMap<String, AttributeValue>> {
+        let attributes = deserialize_attributes(data)?;
+        for (name, _) in attributes.iter() {
+            AttributeValue::check_name(name)?;
+        }
+        Ok(attributes.into_iter().collect())
+    }
     pub fn lua_get(self: &mut RwLockReadGuard<'_, Self>, lua: &Lua, key: &String) -> LuaResult<LuaValue> {
         match key.as_str() {
             "Archivable" => lua_getter!(lua, self.archivable),
-            "ClassName" => IntoLua::into_lua(unsafe { 
-                self._ptr.as_ref().unwrap_unchecked()
-                    .upgrade().unwrap_unchecked().get_class_name()
-                }, lua),
+            "AttributesSerialize" => lua.create_string(serialize_attributes(&self.attributes)).map(LuaValue::String),
+            "ClassName" => IntoLua::into_lua(self.get_class_name(), lua),
             "Name" => lua_getter!(string, lua, self.name),
             "Parent" => lua_getter!(opt_weak_clone, lua, self.parent),
 
@@ -655,7 +770,7 @@ impl InstanceComponent {
             ),
             "FindFirstChildWhichIsA" => lua_getter!(function, lua,
                 |_, (this, class): (ManagedInstance, String)| 
-                    this.find_first_ancestor_which_is_a(class)
+                    this.find_first_child_which_is_a(class)
             ),
             "GetActor" => lua_getter!(function, lua,
                 |_, (this,): (ManagedInstance,)| 
@@ -667,11 +782,11 @@ impl InstanceComponent {
             ),
             "GetAttributeChangedSignal" => lua_getter!(function, lua,
                 |_, (this, attribute): (ManagedInstance, String)| {
-                    Ok(this.get_attribute_changed_signal(attribute))
+                    this.get_attribute_changed_signal(attribute)
             }),
             "GetAttributes" => lua_getter!(function, lua,
-                |lua, (this,): (ManagedInstance,)|
-                    this.get_attributes(lua)
+                |_, (this,): (ManagedInstance,)|
+                    this.get_attributes()
             ),
             "GetChildren" => lua_getter!(function, lua,
                 |_, (this,): (ManagedInstance,)|
@@ -702,6 +817,10 @@ impl InstanceComponent {
                 |_, (this, tag): (ManagedInstance, String)|
                     this.has_tag(tag)
             ),
+            "IsA" => lua_getter!(function, lua,
+                |_, (this, class): (ManagedInstance, String)|
+                    Ok(this.is_a(&class))
+            ),
             "IsAncestorOf" => lua_getter!(function, lua,
                 |_, (this, inst): (ManagedInstance, ManagedInstance)|
                     this.is_ancestor_of(inst)
@@ -715,14 +834,10 @@ impl InstanceComponent {
                     this.remove_tag(lua, tag)
             ),
             "SetAttribute" => lua_getter!(function, lua,
-                |lua, (this, attribute, value): (ManagedInstance, String, LuaValue)|
+                |lua, (this, attribute, value): (ManagedInstance, String, Option<AttributeValue>)|
                     this.set_attribute(lua, attribute, value)
             ),
-            "WaitForChild" => lua_getter!(function_async, lua,
-                async |lua, (this, child, timeout): (ManagedInstance, String, Option<LuaNumber>)| {
-                    this.wait_for_child(&lua, child, timeout).await
-                }
-            ),
+            "WaitForChild" => lua.named_registry_value::<LuaValue>(registry_keys::INSTANCE_WAIT_FOR_CHILD),
             
             "AncestryChanged" => lua_getter!(clone, lua, self.ancestry_changed),
             "AttributeChanged" => lua_getter!(clone, lua, self.attribute_changed),
@@ -732,10 +847,33 @@ impl InstanceComponent {
             "DescendantRemoving" => lua_getter!(clone, lua, self.descendant_removing),
             "Destroying" => lua_getter!(clone, lua, self.destroying),
 
-            _ => lua_getter!(lua, self.find_first_child(key))
+            _ => Self::lua_get_child(self, lua, key)
+        }
+    }
+    /// Indexes the instance with a key which isn't one of its members, giving the child of that name.
+    pub fn lua_get_child(self: &mut RwLockReadGuard<'_, Self>, lua: &Lua, key: &String) -> LuaResult<LuaValue> {
+        match self.find_first_child(key) {
+            Some(child) => lua_getter!(lua, child),
+            None => Err(Self::member_error(self, key))
+        }
+    }
+    fn get_class_name(&self) -> &'static str {
+        unsafe { self._ptr.as_ref().unwrap_unchecked().upgrade().unwrap_unchecked().get_class_name() }
+    }
+    /// The error for a key which is neither a member implemented by the instance nor one of its children.
+    pub fn member_error(this: &impl IReadInstanceComponent, key: &str) -> LuaError {
+        let class_name = this.get_class_name();
+        match CLASS_DATABASE.find_member(class_name, key) {
+            Some(_) => LuaError::RuntimeError(format!("todo!(): {}.{} is not yet implemented", class_name, key)),
+            None => LuaError::RuntimeError(format!("{} is not a valid member of {} \"{}\"", key, class_name, DynInstance::guard_get_full_name(this)))
         }
     }
 
+    /// Whether `err` is the one [`Self::member_error`] gives for a member which isn't implemented yet.
+    pub fn is_not_implemented_error(err: &LuaError) -> bool {
+        matches!(err, LuaError::RuntimeError(message) if message.starts_with("todo!(): ") && message.ends_with(" is not yet implemented"))
+    }
+
     fn remake_cache(self: &mut RwLockReadGuard<'_, Self>) {
         let inst = self._ptr.as_ref().map(|x| x.upgrade()).flatten().unwrap();
         let _release = self.guard_release();
@@ -745,6 +883,7 @@ impl InstanceComponent {
             .map(|x| (x.get_name(), x.downgrade()))
             .collect();
         write.children_cache.clear();
+        write.children_cache_dirty = false;
         for (name, i) in iter {
             if write.children_cache.get(&name).is_none() {
                 write.children_cache.insert(name, i);
@@ -786,6 +925,12 @@ impl InstanceComponent {
                 self.archivable = value.as_boolean().ok_or(LuaError::RuntimeError("bad argument to setting Archivable".into()))?;
                 Self::emit_property_changed(self, lua, "Archivable", &value)
             },
+            // Loads the attributes of a place file, without firing the changed signals.
+            "AttributesSerialize" => {
+                let data = value.as_string().ok_or(LuaError::RuntimeError("bad argument to setting AttributesSerialize".into()))?;
+                self.attributes = Self::load_attributes(&data.as_bytes())?;
+                Ok(())
+            },
             "Name" => {
                 self.name = value.as_string_lossy().ok_or(LuaError::RuntimeError("bad argument to setting Name".into()))?;
                 Self::emit_property_changed(self, lua, "Name", &value)
@@ -793,7 +938,12 @@ impl InstanceComponent {
             "Parent" => {
                 DynInstance::guard_set_parent(self, lua, FromLua::from_lua(value, lua)?)
             },
-            _ => Err(LuaError::RuntimeError(format!("can't set property {} on object of type Instance",key.as_str())))
+            _ => match CLASS_DATABASE.find_member(self.get_class_name(), key) {
+                Some(MemberDescriptor::Property(property)) if !property.is_read_only() => Err(Self::member_error(self, key)),
+                // Methods and events can't be assigned either.
+                Some(_) => Err(LuaError::RuntimeError(format!("Unable to assign property {}. Property is read only", key))),
+                None => Err(Self::member_error(self, key))
+            }
         }
     }
 }
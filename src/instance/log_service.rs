@@ -0,0 +1,38 @@
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
+
+use crate::core::lua_macros::lua_getter;
+use crate::core::{get_state, RwLock};
+use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal};
+use super::{IInstance, InstanceComponent, ManagedInstance};
+
+#[derive(Debug, Instance)]
+#[instance(lua_get = Self::lua_get_members, uncloneable)]
+pub struct LogService {
+    #[component(IInstance)]
+    instance_component: RwLock<InstanceComponent>,
+
+    /// Fired with `(message, messageType)` for every message printed.
+    #[instance(new = RBXScriptSignal::new())]
+    pub message_out: ManagedRBXScriptSignal
+}
+
+impl LogService {
+    fn lua_get_members(&self, lua: &Lua, name: &str) -> Option<LuaResult<LuaValue>> {
+        match name {
+            "MessageOut" => Some(lua_getter!(clone, lua, self.message_out)),
+            "GetLogHistory" => lua_getter!(function_opt, lua, |lua, _: ManagedInstance| {
+                let history = lua.create_table()?;
+                for message in get_state(lua).get_vm().get_log_history() {
+                    let entry = lua.create_table()?;
+                    entry.raw_set("message", message.message)?;
+                    entry.raw_set("messageType", message.message_type)?;
+                    entry.raw_set("timestamp", message.timestamp)?;
+                    history.raw_push(entry)?;
+                }
+                Ok(history)
+            }),
+            _ => None
+        }
+    }
+}
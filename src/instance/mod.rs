@@ -4,10 +4,16 @@ mod actor;
 mod pvinstance;
 mod model;
 mod run_service;
+mod stats;
+mod script_context;
+mod log_service;
+mod collection_service;
 mod data_model;
 mod service_provider;
 mod workspace;
 mod script;
+mod module_script;
+mod class_registry;
 
 pub use object::IObject;
 pub use pvinstance::PVInstanceComponent;
@@ -16,5 +22,12 @@ pub use actor::{Actor, ManagedActor, WeakManagedActor};
 pub use model::{IModel, Model, ModelComponent};
 pub use service_provider::{IServiceProvider, ServiceProviderComponent};
 pub use run_service::RunService;
+pub use stats::Stats;
+pub use script_context::ScriptContext;
+pub use log_service::LogService;
+pub use collection_service::CollectionService;
 pub use data_model::{IDataModel, DataModel};
-pub use script::{IBaseScript, Script, LocalScript};
\ No newline at end of file
+pub use script::{IBaseScript, Script, LocalScript};
+pub use module_script::{ModuleScript, ModuleScriptComponent};
+pub use workspace::{IWorkspace, Workspace, WorkspaceComponent};
+pub use class_registry::InstanceClass;
@@ -0,0 +1,61 @@
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
+
+use crate::core::lua_macros::{lua_getter, lua_setter};
+use crate::core::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{DynInstance, IInstance, IInstanceComponent, InstanceComponent, WeakManagedInstance};
+
+#[derive(Debug)]
+pub struct ModuleScriptComponent {
+    source: String
+}
+
+#[derive(Debug, Instance)]
+pub struct ModuleScript {
+    #[component(IInstance)]
+    instance: RwLock<InstanceComponent>,
+    #[component]
+    module_script: RwLock<ModuleScriptComponent>
+}
+
+impl IInstanceComponent for ModuleScriptComponent {
+    fn lua_get(self: &mut RwLockReadGuard<'_, Self>, _: &DynInstance, lua: &Lua, key: &String) -> Option<LuaResult<LuaValue>> {
+        match key.as_str() {
+            "Source" => Some(lua_getter!(clone, lua, self.source)),
+            _ => None
+        }
+    }
+
+    fn lua_set(self: &mut RwLockWriteGuard<'_, Self>, ptr: &DynInstance, lua: &Lua, key: &String, value: &LuaValue) -> Option<LuaResult<()>> {
+        match key.as_str() {
+            "Source" => {
+                self.source = lua_setter!(opt_clone, lua, value);
+                Some(InstanceComponent::emit_property_changed(&ptr.get_instance_component(), lua, "Source", value))
+            }
+            _ => None
+        }
+    }
+
+    fn clone(self: &RwLockReadGuard<'_, Self>, _: &Lua, _: &WeakManagedInstance) -> LuaResult<Self> {
+        Ok(ModuleScriptComponent {
+            source: self.source.clone()
+        })
+    }
+
+    fn new(_: WeakManagedInstance, _class_name: &'static str) -> Self {
+        ModuleScriptComponent {
+            source: String::new()
+        }
+    }
+}
+
+impl ModuleScript {
+    pub fn get_source(&self) -> String {
+        self.module_script.read().unwrap().source.clone()
+    }
+    /// Sets the source without security checks or change events, for modules loaded by the engine.
+    pub fn set_source(&self, source: String) {
+        self.module_script.write().unwrap().source = source;
+    }
+}
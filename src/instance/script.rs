@@ -1,13 +1,14 @@
 use std::mem::take;
 
 use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
 
 use crate::core::lua_macros::{lua_getter, lua_setter};
 use crate::core::ParallelDispatch::Synchronized;
-use crate::instance::{IObject, IInstance, DynInstance, ManagedInstance, InstanceComponent, IInstanceComponent, WeakManagedInstance};
+use crate::instance::{IInstance, DynInstance, ManagedInstance, InstanceComponent, IInstanceComponent, WeakManagedInstance};
 use crate::userdata::enums::RunContext;
-use crate::userdata::{ManagedRBXScriptSignal, RBXScriptConnection};
-use crate::core::{borrowck_ignore, borrowck_ignore_mut, get_current_identity, get_state, get_task_scheduler_from_lua, inheritance_cast_to, FastFlag, InheritanceBase, InheritanceTableBuilder, Irc, LuauState, RwLock, RwLockReadGuard, RwLockWriteGuard, SecurityContext, Trc};
+use crate::userdata::RBXScriptConnection;
+use crate::core::{borrowck_ignore, borrowck_ignore_mut, get_current_identity, get_state, get_task_scheduler_from_lua, inheritance_cast_to, FastFlag, LuauState, RwLock, RwLockReadGuard, RwLockWriteGuard, ThreadIdentity, Trc};
 
 use super::ManagedActor;
 #[derive(Debug)]
@@ -35,44 +36,32 @@ impl BaseScriptComponent {
             return Ok(());
         }
         let instance = self.self_instance.upgrade().unwrap();
-        self.actor = instance.get_actor()?
+        // Scripts answer get_actor with the state they started in, which is what is being set here.
+        self.actor = DynInstance::guard_find_first_ancestor_of_class(&instance.get_instance_component(), "Actor".into())?
             .map(|x| ActorLuauState::Actor(x.cast_from_unsized().unwrap()))
             .unwrap_or_else(|| ActorLuauState::Main(get_state(lua).get_vm().get_main_state_rc()));
         let state_lock = self.get_state();
         let mut state = unsafe {borrowck_ignore(&state_lock)}.write();
-        let debug = state.flags().get_bool(FastFlag::DebugMode);
-
-        let env = state.create_env_from_global()?;
-        env.set("script", instance.clone())?;
+        let env = state.create_script_env(instance.clone())?;
         let func: LuaFunction;
         {
-            let f: LuaResult<LuaFunction>;
-            if debug {
-                f = state.compile_debug(
-                    format!("<script at {}>", instance.get_full_name()?).as_str(), self.source.as_str(), env
-                );
-            } else if self.source.lines().any(|x| x == "--!native") {
-                f = state.compile_jit(
-                    format!("<script at {}>", instance.get_full_name()?).as_str(), self.source.as_str(), env
-                );
-            } else {
-                f = state.compile_release(
-                    format!("<script at {}>", instance.get_full_name()?).as_str(), self.source.as_str(), env
-                );
-            }
+            let f = state.compile_script(
+                &instance,
+                format!("<script at {}>", instance.get_full_name()?).as_str(), self.source.as_str(), env
+            );
             func = f.inspect_err(|x|
                 match x {
                     LuaError::RuntimeError(err) =>
-                    state.get_vm().log_err(IntoLuaMulti::into_lua_multi(
+                    state.get_vm().log_err(lua, IntoLuaMulti::into_lua_multi(
                         format!("Error occured while setting up script {}: {}", instance.get_full_name().unwrap(), err),
                         lua
                     ).unwrap()),
                     LuaError::SyntaxError { message, .. } =>
-                        state.get_vm().log_err(IntoLuaMulti::into_lua_multi(
+                        state.get_vm().log_err(lua, IntoLuaMulti::into_lua_multi(
                             format!("Error occured while compiling script {}: {}", instance.get_full_name().unwrap(), message),
                             lua
                         ).unwrap()),
-                    x => state.get_vm().log_err(IntoLuaMulti::into_lua_multi(
+                    x => state.get_vm().log_err(lua, IntoLuaMulti::into_lua_multi(
                         format!("Unknown error while compiling script {}: {:?}", instance.get_full_name().unwrap(), x),
                         lua
                     ).unwrap()),
@@ -80,9 +69,17 @@ impl BaseScriptComponent {
             )?;
         }
         self.change_scheduled = Some(unsafe {
+            // The thread is created in the state of the script, which is another Lua VM than `lua` in Actors.
+            let state_lua = state.get_lua().clone();
             let thread = borrowck_ignore_mut(borrowck_ignore_mut(&mut *state).get_task_scheduler_mut())
-                .defer_func(borrowck_ignore(lua), func, (), Synchronized)?;
-            
+                .defer_func(&state_lua, func, (), Synchronized)?;
+            // Attribute the thread to this script, so profiler samples are counted for it.
+            // It keeps the security it was created with, or, when the script runs in another state, the one of the thread starting it.
+            let security_identity = match state.get_thread_identity(thread.clone()) {
+                Some(x) => x.security_identity,
+                None => get_current_identity(lua).map(|x| x.security_identity).unwrap_or_default()
+            };
+            state.set_thread_identity(thread.clone(), ThreadIdentity { security_identity, script: Some(self.self_instance.clone()) });
             thread
         });
         Ok(())
@@ -98,6 +95,10 @@ impl BaseScriptComponent {
             
             borrowck_ignore_mut(&mut *state).get_task_scheduler_mut().cancel_script(lua, &self.self_instance)?;
         }
+        let vm = get_state(lua).get_vm();
+        if let Some(debugger) = vm.get_debugger() {
+            debugger.release_script(&self.self_instance);
+        }
         if let Some(iden) = get_current_identity(lua) {
             if iden.script.as_ref().map(|x| self.self_instance == *x).unwrap_or(false) {
                 get_task_scheduler_from_lua(lua).cancel(lua, &lua.current_thread())?;
@@ -266,13 +267,6 @@ impl IInstanceComponent for BaseScriptComponent {
                 Some(d)
             },
             "Source" => {
-                if let Some(iden) = get_current_identity(lua) {
-                    if !iden.security_identity.get_security_contexts().has(SecurityContext::PLUGIN) {
-                        return Some(Err(LuaError::RuntimeError("Source property is protected (PluginSecurity or higher)".into())));
-                    }
-                } else {
-                    return Some(Err(LuaError::RuntimeError("Source property is protected (PluginSecurity or higher)".into())));
-                }
                 if let Err(err) = self.set_source(lua_setter!(opt_clone, lua, value)) {
                     return Some(Err(err));
                 }
@@ -320,6 +314,12 @@ impl IInstanceComponent for BaseScriptComponent {
 }
 
 impl dyn IBaseScript {
+    fn get_script_actor(&self) -> LuaResult<Option<ManagedInstance>> {
+        match &self.get_base_script_component().actor {
+            ActorLuauState::Actor(x) => Ok(Some(x.clone().cast_from_sized().unwrap())),
+            _ => Ok(None)
+        }
+    }
     fn get_source(&self) -> String {
         self.get_base_script_component().source.clone()
     }
@@ -349,82 +349,15 @@ impl dyn IBaseScript {
 }
 
 //pub struct ScriptComponent {}
-#[derive(Debug)]
+#[derive(Debug, Instance)]
+#[instance(get_actor = <dyn IBaseScript>::get_script_actor)]
 pub struct Script {
+    #[component(IInstance)]
     instance: RwLock<InstanceComponent>,
+    #[component(IBaseScript, manual)]
     base_script: RwLock<BaseScriptComponent>
 }
 
-impl InheritanceBase for Script {
-    fn inheritance_table(&self) -> crate::core::InheritanceTable {
-        InheritanceTableBuilder::new()
-            .insert_type::<Script, dyn IObject>(|x| x, |x| x)
-            .insert_type::<Script, dyn IInstance>(|x| x, |x| x)
-            .insert_type::<Script, dyn IBaseScript>(|x| x, |x| x)
-            .output()
-    }
-}
-impl IObject for Script {
-    fn lua_get(&self, lua: &Lua, name: String) -> LuaResult<LuaValue> {
-        self.base_script.read().unwrap().lua_get(self, lua, &name)
-            .unwrap_or_else(|| self.instance.read().unwrap().lua_get(lua, &name))
-    }
-
-    fn get_class_name(&self) -> &'static str { "Script" }
-
-    fn get_property_changed_signal(&self, property: String) -> ManagedRBXScriptSignal {
-        self.instance.read().unwrap().get_property_changed_signal(property).unwrap()
-    }
-
-    fn is_a(&self, class_name: &String) -> bool {
-        match class_name.as_str() {
-            "Object" |
-            "Instance" |
-            "LuaSourceContainer" |
-            "BaseScript" |
-            "Script" => true,
-            _ => false
-        }
-    }
-
-    fn get_changed_signal(&self) -> ManagedRBXScriptSignal {
-        self.instance.read().unwrap().changed.clone()
-    }
-}
-
-impl IInstance for Script {
-    fn get_instance_component(&self) -> RwLockReadGuard<InstanceComponent> {
-        self.instance.read().unwrap()
-    }
-
-    fn get_instance_component_mut(&self) -> RwLockWriteGuard<InstanceComponent> {
-        self.instance.write().unwrap()
-    }
-
-    fn lua_set(&self, lua: &Lua, name: String, val: LuaValue) -> LuaResult<()> {
-        self.base_script.write().unwrap().lua_set(self, lua, &name, &val)
-            .unwrap_or_else(|| self.instance.write().unwrap().lua_set(lua, &name, val))
-    }
-
-    fn clone_instance(&self, lua: &Lua) -> LuaResult<ManagedInstance> {
-        Ok(Irc::new_cyclic_fallable::<_, LuaError>(|x| {
-            let i = x.cast_to_instance();
-            let script = Script {
-                instance: RwLock::new_with_flag_auto(self.get_instance_component().clone(lua, &i)?),
-                base_script: RwLock::new_with_flag_auto(self.get_base_script_component().clone(lua, &i)?),
-            };
-            Ok(script)
-        })?.cast_from_sized().unwrap())
-    }
-
-    fn get_actor(&self) -> LuaResult<Option<ManagedInstance>> {
-        match &self.get_base_script_component().actor {
-            ActorLuauState::Actor(x) => Ok(Some(x.clone().cast_from_sized().unwrap())),
-            _ => Ok(None)
-        }
-    }
-}
-
 impl IBaseScript for Script {
     fn get_base_script_component(&self) -> RwLockReadGuard<'_, BaseScriptComponent> {
         self.base_script.read().unwrap()
@@ -436,94 +369,15 @@ impl IBaseScript for Script {
 
     fn implicit_run_context(&self) -> RunContext { RunContext::Server }
 }
-
-impl Script {
-    pub fn new() -> ManagedInstance {
-        Irc::new_cyclic(|x|
-            Script {
-                instance: RwLock::new_with_flag_auto(InstanceComponent::new(x.cast_to_instance(), "Script")),
-                base_script: RwLock::new_with_flag_auto(BaseScriptComponent::new(x.cast_to_instance(), "Script"))
-            }
-        ).cast_from_sized().unwrap()
-    }
-}
-#[derive(Debug)]
+#[derive(Debug, Instance)]
+#[instance(get_actor = <dyn IBaseScript>::get_script_actor)]
 pub struct LocalScript {
+    #[component(IInstance)]
     instance: RwLock<InstanceComponent>,
+    #[component(IBaseScript, manual)]
     base_script: RwLock<BaseScriptComponent>
 }
 
-impl InheritanceBase for LocalScript {
-    fn inheritance_table(&self) -> crate::core::InheritanceTable {
-        InheritanceTableBuilder::new()
-            .insert_type::<LocalScript, dyn IObject>(|x| x, |x| x)
-            .insert_type::<LocalScript, dyn IInstance>(|x| x, |x| x)
-            .insert_type::<LocalScript, dyn IBaseScript>(|x| x, |x| x)
-            .output()
-    }
-}
-impl IObject for LocalScript {
-    fn lua_get(&self, lua: &Lua, name: String) -> LuaResult<LuaValue> {
-        self.base_script.read().unwrap().lua_get(self, lua, &name)
-            .unwrap_or_else(|| self.instance.read().unwrap().lua_get(lua, &name))
-    }
-
-    fn get_class_name(&self) -> &'static str { "LocalScript" }
-
-    fn get_property_changed_signal(&self, property: String) -> ManagedRBXScriptSignal {
-        self.instance.read().unwrap().get_property_changed_signal(property).unwrap()
-    }
-
-    fn is_a(&self, class_name: &String) -> bool {
-        match class_name.as_str() {
-            "Object" |
-            "Instance" |
-            "LuaSourceContainer" |
-            "BaseScript" |
-            "Script" |
-            "LocalScript" => true,
-            _ => false
-        }
-    }
-
-    fn get_changed_signal(&self) -> ManagedRBXScriptSignal {
-        self.instance.read().unwrap().changed.clone()
-    }
-}
-
-impl IInstance for LocalScript {
-    fn get_instance_component(&self) -> RwLockReadGuard<InstanceComponent> {
-        self.instance.read().unwrap()
-    }
-
-    fn get_instance_component_mut(&self) -> RwLockWriteGuard<InstanceComponent> {
-        self.instance.write().unwrap()
-    }
-
-    fn lua_set(&self, lua: &Lua, name: String, val: LuaValue) -> LuaResult<()> {
-        self.base_script.write().unwrap().lua_set(self, lua, &name, &val)
-            .unwrap_or_else(|| self.instance.write().unwrap().lua_set(lua, &name, val))
-    }
-
-    fn clone_instance(&self, lua: &Lua) -> LuaResult<ManagedInstance> {
-        Ok(Irc::new_cyclic_fallable::<_, LuaError>(|x| {
-            let i = x.cast_to_instance();
-            let script = LocalScript {
-                instance: RwLock::new_with_flag_auto(self.get_instance_component().clone(lua, &i)?),
-                base_script: RwLock::new_with_flag_auto(self.get_base_script_component().clone(lua, &i)?),
-            };
-            Ok(script)
-        })?.cast_from_sized().unwrap())
-    }
-
-    fn get_actor(&self) -> LuaResult<Option<ManagedInstance>> {
-        match &self.get_base_script_component().actor {
-            ActorLuauState::Actor(x) => Ok(Some(x.clone().cast_from_sized().unwrap())),
-            _ => Ok(None)
-        }
-    }
-}
-
 impl IBaseScript for LocalScript {
     fn get_base_script_component(&self) -> RwLockReadGuard<'_, BaseScriptComponent> {
         self.base_script.read().unwrap()
@@ -535,14 +389,3 @@ impl IBaseScript for LocalScript {
 
     fn implicit_run_context(&self) -> RunContext { RunContext::Client }
 }
-
-impl LocalScript {
-    pub fn new() -> ManagedInstance {
-        Irc::new_cyclic(|x|
-            LocalScript {
-                instance: RwLock::new_with_flag_auto(InstanceComponent::new(x.cast_to_instance(), "LocalScript")),
-                base_script: RwLock::new_with_flag_auto(BaseScriptComponent::new(x.cast_to_instance(), "LocalScript"))
-            }
-        ).cast_from_sized().unwrap()
-    }
-}
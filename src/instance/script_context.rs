@@ -0,0 +1,27 @@
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
+
+use crate::core::lua_macros::lua_getter;
+use crate::core::RwLock;
+use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal};
+use super::{IInstance, InstanceComponent};
+
+#[derive(Debug, Instance)]
+#[instance(lua_get = Self::lua_get_members, uncloneable)]
+pub struct ScriptContext {
+    #[component(IInstance)]
+    instance_component: RwLock<InstanceComponent>,
+
+    /// Fired with `(message, stackTrace, script)` for every error no script caught.
+    #[instance(new = RBXScriptSignal::new())]
+    pub error: ManagedRBXScriptSignal
+}
+
+impl ScriptContext {
+    fn lua_get_members(&self, lua: &Lua, name: &str) -> Option<LuaResult<LuaValue>> {
+        match name {
+            "Error" => Some(lua_getter!(clone, lua, self.error)),
+            _ => None
+        }
+    }
+}
@@ -15,7 +15,7 @@ pub struct ServiceProviderComponent {
 pub trait IServiceProvider: IInstance {
     fn get_service_provider_component(&self) -> RwLockReadGuard<'_, ServiceProviderComponent>;
     fn get_service_provider_component_mut(&self) -> RwLockWriteGuard<'_, ServiceProviderComponent>;
-    fn get_service(&self, service_name: String) -> LuaResult<ManagedInstance>;
+    fn get_service(&self, lua: &Lua, service_name: String) -> LuaResult<ManagedInstance>;
     fn find_service(&self, service_name: String) -> LuaResult<Option<ManagedInstance>>;
 }
 
@@ -25,13 +25,13 @@ impl IInstanceComponent for ServiceProviderComponent {
             "Close" => Some(lua_getter!(clone, lua, self.close)),
             "ServiceAdded" => Some(lua_getter!(clone, lua, self.service_added)),
             "ServiceRemoving" => Some(lua_getter!(clone, lua, self.service_removing)),
-            "GetService" => lua_getter!(function_opt, lua, |_, (this, name): (ManagedInstance, String)| {
+            "GetService" => lua_getter!(function_opt, lua, |lua, (this, name): (ManagedInstance, String)| {
                 let i = inheritance_cast_to!(&*this, dyn IServiceProvider);
                 i.map_err(|_|
                     lua_invalid_argument!("ServiceProvider::GetService",1,self cast Instance to ServiceProvider)
                 )?;
                 unsafe {
-                    i.unwrap_unchecked().get_service(name)
+                    i.unwrap_unchecked().get_service(lua, name)
                 }
             }),
             "FindService" => lua_getter!(function_opt, lua, |_, (this, name): (ManagedInstance, String)| {
@@ -0,0 +1,73 @@
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
+
+use crate::core::lua_macros::lua_getter;
+use crate::core::{get_state, CycleStats, QueueCounts, TaskSchedulerSnapshot, RwLock};
+use super::{IInstance, InstanceComponent, ManagedInstance};
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+#[derive(Debug, Instance)]
+#[instance(lua_get = Self::lua_get_members, uncloneable)]
+pub struct Stats {
+    #[component(IInstance)]
+    instance_component: RwLock<InstanceComponent>
+}
+
+impl Stats {
+    fn lua_get_members(&self, lua: &Lua, name: &str) -> Option<LuaResult<LuaValue>> {
+        match name {
+            "GetTotalMemoryUsageMb" => lua_getter!(function_opt, lua,
+                |lua, _: ManagedInstance|
+                    Ok(get_state(lua).get_vm().get_memory_usage() as f64 / BYTES_PER_MB)
+            ),
+            "GetStateMemoryUsageMb" => lua_getter!(function_opt, lua,
+                |lua, _: ManagedInstance|
+                    Ok(get_state(lua).get_memory_usage() as f64 / BYTES_PER_MB)
+            ),
+            "GetScriptMemoryUsageMb" => lua_getter!(function_opt, lua,
+                |lua, (_, script): (ManagedInstance, ManagedInstance)|
+                    Ok(get_state(lua).get_vm().get_script_memory_usage(&script) as f64 / BYTES_PER_MB)
+            ),
+            "GetTaskSchedulerStats" => lua_getter!(function_opt, lua, |lua, _: ManagedInstance| {
+                let states = lua.create_table()?;
+                let snapshots = get_state(lua).get_vm().get_task_scheduler_snapshots()
+                    .ok_or_else(|| LuaError::RuntimeError("GetTaskSchedulerStats can only be called while synchronized".into()))?;
+                for (name, snapshot) in snapshots {
+                    states.raw_set(name, task_scheduler_snapshot_to_table(lua, snapshot)?)?;
+                }
+                Ok(states)
+            }),
+            _ => None
+        }
+    }
+}
+
+fn queue_counts_to_table(lua: &Lua, counts: QueueCounts, cycle: CycleStats) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.raw_set("Deferred", counts.deferred)?;
+    table.raw_set("Delayed", counts.delayed)?;
+    table.raw_set("Waiting", counts.waiting)?;
+    table.raw_set("DeferResumes", cycle.defer_resumes)?;
+    table.raw_set("DelayResumes", cycle.delay_resumes)?;
+    table.raw_set("DeferTime", cycle.defer_time)?;
+    table.raw_set("DelayTime", cycle.delay_time)?;
+    Ok(table)
+}
+
+fn task_scheduler_snapshot_to_table(lua: &Lua, snapshot: TaskSchedulerSnapshot) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.raw_set("Synchronized", queue_counts_to_table(lua, snapshot.synchronized, snapshot.synchronized_cycle)?)?;
+    table.raw_set("Desynchronized", queue_counts_to_table(lua, snapshot.desynchronized, snapshot.desynchronized_cycle)?)?;
+    let threads = lua.create_table()?;
+    for thread in snapshot.threads {
+        let entry = lua.create_table()?;
+        entry.raw_set("Queue", format!("{:?}", thread.kind))?;
+        entry.raw_set("Dispatch", format!("{:?}", thread.dispatch))?;
+        entry.raw_set("Script", thread.script.and_then(|x| x.upgrade()))?;
+        entry.raw_set("WakeTime", thread.wake_time)?;
+        threads.raw_push(entry)?;
+    }
+    table.raw_set("Threads", threads)?;
+    Ok(table)
+}
@@ -1,10 +1,17 @@
-use crate::{core::RwLock, userdata::{ManagedRBXScriptSignal, Vector3}};
+use r2g_mlua::prelude::*;
+use roblox_to_godot_project_derive::Instance;
 
-use super::InstanceComponent;
+use crate::core::lua_macros::lua_getter;
+use crate::core::{get_state, FastFlag, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::userdata::enums::SignalBehavior;
+use crate::userdata::{ManagedRBXScriptSignal, RBXScriptSignal, Vector3};
 
-pub struct Workspace {
-    instance_component: RwLock<InstanceComponent>,
-    
+use super::instance::IInstanceComponent;
+use super::pvinstance::IPVInstance;
+use super::{DynInstance, IInstance, IModel, InstanceComponent, ModelComponent, PVInstanceComponent, WeakManagedInstance};
+
+#[derive(Debug)]
+pub struct WorkspaceComponent {
     pub persistent_loaded: ManagedRBXScriptSignal,
 
     air_density: f64,
@@ -40,4 +47,103 @@ pub struct Workspace {
     terrain: (), //todo!
     touch_events_use_collision_groups: (), //todo!
     touches_use_collision_groups: bool,
-}
\ No newline at end of file
+}
+#[derive(Debug, Instance)]
+#[instance(uncloneable)]
+pub struct Workspace {
+    #[component(IInstance)]
+    instance: RwLock<InstanceComponent>,
+    #[component(IPVInstance)]
+    pvinstance: RwLock<PVInstanceComponent>,
+    #[component(IModel)]
+    model: RwLock<ModelComponent>,
+    #[component(IWorkspace)]
+    workspace: RwLock<WorkspaceComponent>
+}
+pub trait IWorkspace: IModel {
+    fn get_workspace_component(&self) -> RwLockReadGuard<'_, WorkspaceComponent>;
+    fn get_workspace_component_mut(&self) -> RwLockWriteGuard<'_, WorkspaceComponent>;
+}
+
+impl IInstanceComponent for WorkspaceComponent {
+    fn lua_get(self: &mut RwLockReadGuard<'_, WorkspaceComponent>, _: &DynInstance, lua: &Lua, key: &String) -> Option<LuaResult<LuaValue>> {
+        match key.as_str() {
+            "AirDensity" => Some(lua_getter!(lua, self.air_density)),
+            "AllowThirdPartySales" => Some(lua_getter!(lua, self.allow_third_party_sales)),
+            "DistributedGameTime" => Some(lua_getter!(lua, get_state(lua).get_clock().now())),
+            "FallHeightEnabled" => Some(lua_getter!(lua, self.fall_height_enabled)),
+            "FallenPartsDestroyHeight" => Some(lua_getter!(lua, self.fallen_parts_destroy_height)),
+            "GlobalWind" => Some(lua_getter!(lua, self.global_wind)),
+            "Gravity" => Some(lua_getter!(lua, self.gravity)),
+            "SignalBehavior" => Some(lua_getter!(lua, SignalBehavior::from_value(get_state(lua).flags().get_int(FastFlag::SignalBehavior)))),
+            "StreamingEnabled" => Some(lua_getter!(lua, self.streaming_enabled)),
+            "StreamingMinRadius" => Some(lua_getter!(lua, self.streaming_min_radius)),
+            "StreamingTargetRadius" => Some(lua_getter!(lua, self.streaming_target_radius)),
+            "TouchesUseCollisionGroups" => Some(lua_getter!(lua, self.touches_use_collision_groups)),
+            "PersistentLoaded" => Some(lua_getter!(clone, lua, self.persistent_loaded)),
+            _ => None
+        }
+    }
+
+    fn lua_set(self: &mut RwLockWriteGuard<'_, WorkspaceComponent>, _: &DynInstance, lua: &Lua, key: &String, value: &LuaValue) -> Option<LuaResult<()>> {
+        match key.as_str() {
+            "AirDensity" => Some(f64::from_lua(value.clone(), lua).map(|x| self.air_density = x)),
+            "AllowThirdPartySales" => Some(bool::from_lua(value.clone(), lua).map(|x| self.allow_third_party_sales = x)),
+            "FallHeightEnabled" => Some(bool::from_lua(value.clone(), lua).map(|x| self.fall_height_enabled = x)),
+            "FallenPartsDestroyHeight" => Some(f64::from_lua(value.clone(), lua).map(|x| self.fallen_parts_destroy_height = x)),
+            "GlobalWind" => Some(Vector3::from_lua(value.clone(), lua).map(|x| self.global_wind = x)),
+            "Gravity" => Some(f64::from_lua(value.clone(), lua).map(|x| self.gravity = x)),
+            // SignalBehavior is set by the host through the fast flag of the same name.
+            "DistributedGameTime" |
+            "SignalBehavior" |
+            "StreamingEnabled" |
+            "StreamingMinRadius" |
+            "StreamingTargetRadius" |
+            "TouchesUseCollisionGroups" |
+            "PersistentLoaded" => Some(Err(LuaError::RuntimeError("Cannot set read only property.".into()))),
+            _ => None
+        }
+    }
+
+    fn clone(self: &RwLockReadGuard<'_, WorkspaceComponent>, _: &Lua, _: &WeakManagedInstance) -> LuaResult<Self> {
+        Err(LuaError::RuntimeError("Cannot clone WorkspaceComponent".into()))
+    }
+
+    fn new(_: WeakManagedInstance, _: &'static str) -> Self {
+        WorkspaceComponent {
+            persistent_loaded: RBXScriptSignal::new(),
+            air_density: 0.0012,
+            allow_third_party_sales: false,
+            avatar_unification_mode: (),
+            client_animator_throttling: (),
+            current_camera: (),
+            distributed_game_time: 0.0,
+            fall_height_enabled: true,
+            fallen_parts_destroy_height: -500.0,
+            fluid_forces: (),
+            global_wind: Vector3::ZERO,
+            gravity: 196.2,
+            ik_control_constraint_support: (),
+            insert_point: Vector3::ZERO,
+            mesh_part_heads_and_accessories: (),
+            mover_constraint_root_behavior: (),
+            pathfinding_use_improved_search: (),
+            physics_stepping_method: (),
+            player_character_destroy_behavior: (),
+            primal_physics_solver: (),
+            reject_character_deletions: (),
+            rendering_cache_optimizations: (),
+            replicate_instance_destroy_string: (),
+            retargeting: (),
+            sandboxed_instance_mode: (),
+            stream_out_behavior: (),
+            streaming_enabled: false,
+            streaming_integrity_mode: (),
+            streaming_min_radius: 64.0,
+            streaming_target_radius: 1024.0,
+            terrain: (),
+            touch_events_use_collision_groups: (),
+            touches_use_collision_groups: false
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use r2g_mlua::prelude::*;
+
+use crate::instance::ManagedInstance;
+
+use super::enums::*;
+use super::{
+    BrickColor, CFrame, Color3, ColorSequence, ColorSequenceKeypoint, Font, NumberRange, NumberSequence,
+    NumberSequenceKeypoint, Rect, UDim, UDim2, Vector2, Vector3
+};
+
+const MAX_ATTRIBUTE_NAME_LENGTH: usize = 100;
+
+// Type ids of the attribute format of place files.
+const TYPE_STRING: u8 = 0x02;
+const TYPE_BOOL: u8 = 0x03;
+const TYPE_FLOAT: u8 = 0x05;
+const TYPE_DOUBLE: u8 = 0x06;
+const TYPE_UDIM: u8 = 0x09;
+const TYPE_UDIM2: u8 = 0x0A;
+const TYPE_BRICK_COLOR: u8 = 0x0E;
+const TYPE_COLOR3: u8 = 0x0F;
+const TYPE_VECTOR2: u8 = 0x10;
+const TYPE_VECTOR3: u8 = 0x11;
+const TYPE_CFRAME: u8 = 0x14;
+const TYPE_ENUM_ITEM: u8 = 0x15;
+const TYPE_NUMBER_SEQUENCE: u8 = 0x17;
+const TYPE_COLOR_SEQUENCE: u8 = 0x19;
+const TYPE_NUMBER_RANGE: u8 = 0x1B;
+const TYPE_RECT: u8 = 0x1C;
+const TYPE_FONT: u8 = 0x21;
+
+/// A value an attribute can hold. Unlike a `LuaValue` it doesn't depend on a Lua state, so it can be cloned along
+/// with its instance and saved to place files with [`serialize_attributes`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum AttributeValue {
+    String(String),
+    Bool(bool),
+    Number(f64),
+    UDim(UDim),
+    UDim2(UDim2),
+    BrickColor(BrickColor),
+    Color3(Color3),
+    Vector2(Vector2),
+    Vector3(Vector3),
+    CFrame(CFrame),
+    EnumItem(AttributeEnumItem),
+    NumberSequence(NumberSequence),
+    ColorSequence(ColorSequence),
+    NumberRange(NumberRange),
+    Rect(Rect),
+    Font(Font)
+}
+
+macro_rules! attribute_enum_items {
+    ($($name: ident),*) => {
+        /// An item of one of the enums scripts can name.
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub enum AttributeEnumItem {
+            $($name($name)),*
+        }
+
+        impl AttributeEnumItem {
+            pub fn enum_name(self) -> &'static str {
+                match self {
+                    $(Self::$name(_) => $name::ENUM_NAME),*
+                }
+            }
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$name(x) => x.name()),*
+                }
+            }
+            pub fn value(self) -> i32 {
+                match self {
+                    $(Self::$name(x) => x.value()),*
+                }
+            }
+            fn from_value(enum_name: &str, value: i32) -> Option<Self> {
+                $(if enum_name == $name::ENUM_NAME {
+                    return $name::ITEMS.iter().find(|x| x.value() == value).map(|x| Self::$name(*x));
+                })*
+                None
+            }
+            fn from_userdata(ud: &LuaAnyUserData) -> Option<Self> {
+                $(if let Ok(x) = ud.borrow::<$name>() {
+                    return Some(Self::$name(*x));
+                })*
+                None
+            }
+            fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+                match self {
+                    $(Self::$name(x) => x.into_lua(lua)),*
+                }
+            }
+        }
+    };
+}
+
+with_enums!(attribute_enum_items);
+
+impl AttributeValue {
+    /// Attribute names are up to 100 alphanumeric characters or underscores, and the RBX prefix is reserved.
+    pub fn check_name(name: &str) -> LuaResult<()> {
+        if name.len() > MAX_ATTRIBUTE_NAME_LENGTH {
+            return Err(LuaError::RuntimeError(format!("Attribute name '{}' is longer than {} characters", name, MAX_ATTRIBUTE_NAME_LENGTH)));
+        }
+        if name.is_empty() || !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+            return Err(LuaError::RuntimeError(format!("Attribute name '{}' can only contain alphanumeric characters and underscores", name)));
+        }
+        if name.starts_with("RBX") {
+            return Err(LuaError::RuntimeError(format!("Attribute name '{}' is reserved, names starting with RBX are reserved by Roblox", name)));
+        }
+        Ok(())
+    }
+}
+
+fn unsupported_type_error(type_name: &str) -> LuaError {
+    LuaError::RuntimeError(format!("{} is not a supported attribute type", type_name))
+}
+
+impl FromLua for AttributeValue {
+    fn from_lua(value: LuaValue, _lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::String(x) => Ok(Self::String(x.to_str()?.to_string())),
+            LuaValue::Boolean(x) => Ok(Self::Bool(x)),
+            LuaValue::Integer(x) => Ok(Self::Number(x as f64)),
+            LuaValue::Number(x) => Ok(Self::Number(x)),
+            LuaValue::UserData(ud) => {
+                if let Ok(x) = ud.borrow::<UDim>() {
+                    Ok(Self::UDim(*x))
+                } else if let Ok(x) = ud.borrow::<UDim2>() {
+                    Ok(Self::UDim2(*x))
+                } else if let Ok(x) = ud.borrow::<BrickColor>() {
+                    Ok(Self::BrickColor(*x))
+                } else if let Ok(x) = ud.borrow::<Color3>() {
+                    Ok(Self::Color3(*x))
+                } else if let Ok(x) = ud.borrow::<Vector2>() {
+                    Ok(Self::Vector2(*x))
+                } else if let Ok(x) = ud.borrow::<Vector3>() {
+                    Ok(Self::Vector3(*x))
+                } else if let Ok(x) = ud.borrow::<CFrame>() {
+                    Ok(Self::CFrame(*x))
+                } else if let Some(x) = AttributeEnumItem::from_userdata(&ud) {
+                    Ok(Self::EnumItem(x))
+                } else if let Ok(x) = ud.borrow::<NumberSequence>() {
+                    Ok(Self::NumberSequence(x.clone()))
+                } else if let Ok(x) = ud.borrow::<ColorSequence>() {
+                    Ok(Self::ColorSequence(x.clone()))
+                } else if let Ok(x) = ud.borrow::<NumberRange>() {
+                    Ok(Self::NumberRange(*x))
+                } else if let Ok(x) = ud.borrow::<Rect>() {
+                    Ok(Self::Rect(*x))
+                } else if let Ok(x) = ud.borrow::<Font>() {
+                    Ok(Self::Font(x.clone()))
+                } else if ud.is::<ManagedInstance>() {
+                    Err(unsupported_type_error("Instance"))
+                } else {
+                    Err(unsupported_type_error("userdata"))
+                }
+            },
+            _ => Err(unsupported_type_error(value.type_name()))
+        }
+    }
+}
+
+impl IntoLua for AttributeValue {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self {
+            Self::String(x) => x.into_lua(lua),
+            Self::Bool(x) => x.into_lua(lua),
+            Self::Number(x) => x.into_lua(lua),
+            Self::UDim(x) => x.into_lua(lua),
+            Self::UDim2(x) => x.into_lua(lua),
+            Self::BrickColor(x) => x.into_lua(lua),
+            Self::Color3(x) => x.into_lua(lua),
+            Self::Vector2(x) => x.into_lua(lua),
+            Self::Vector3(x) => x.into_lua(lua),
+            Self::CFrame(x) => x.into_lua(lua),
+            Self::EnumItem(x) => x.into_lua(lua),
+            Self::NumberSequence(x) => x.into_lua(lua),
+            Self::ColorSequence(x) => x.into_lua(lua),
+            Self::NumberRange(x) => x.into_lua(lua),
+            Self::Rect(x) => x.into_lua(lua),
+            Self::Font(x) => x.into_lua(lua)
+        }
+    }
+}
+
+/// Writes attributes in the binary format of the `AttributesSerialize` property of place files, sorted by name.
+/// Numbers other than the Number type itself are stored with 32-bit floats, like Roblox does.
+pub fn serialize_attributes<'a>(attributes: impl IntoIterator<Item = (&'a String, &'a AttributeValue)>) -> Vec<u8> {
+    let mut attributes: Vec<_> = attributes.into_iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    let mut out = Vec::new();
+    out.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+    for (name, value) in attributes {
+        write_string(&mut out, name);
+        value.serialize(&mut out);
+    }
+    out
+}
+
+/// Reads attributes written by [`serialize_attributes`], or by Roblox.
+pub fn deserialize_attributes(data: &[u8]) -> LuaResult<Vec<(String, AttributeValue)>> {
+    let mut reader = AttributeReader { data };
+    let count = reader.u32()?;
+    let mut attributes = Vec::new();
+    for _ in 0..count {
+        let name = reader.string()?;
+        let value = AttributeValue::deserialize(&mut reader)?;
+        attributes.push((name, value));
+    }
+    if !reader.data.is_empty() {
+        return Err(LuaError::RuntimeError("Serialized attributes have trailing bytes".into()));
+    }
+    Ok(attributes)
+}
+
+fn write_string(out: &mut Vec<u8>, x: &str) {
+    out.extend_from_slice(&(x.len() as u32).to_le_bytes());
+    out.extend_from_slice(x.as_bytes());
+}
+
+fn write_udim(out: &mut Vec<u8>, x: &UDim) {
+    write_f32s(out, &[x.scale]);
+    out.extend_from_slice(&x.offset.to_le_bytes());
+}
+
+fn write_f32s(out: &mut Vec<u8>, x: &[f64]) {
+    for x in x {
+        out.extend_from_slice(&(*x as f32).to_le_bytes());
+    }
+}
+
+impl AttributeValue {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::String(x) => {
+                out.push(TYPE_STRING);
+                write_string(out, x);
+            },
+            Self::Bool(x) => out.extend_from_slice(&[TYPE_BOOL, *x as u8]),
+            Self::Number(x) => {
+                out.push(TYPE_DOUBLE);
+                out.extend_from_slice(&x.to_le_bytes());
+            },
+            Self::UDim(x) => {
+                out.push(TYPE_UDIM);
+                write_udim(out, x);
+            },
+            Self::UDim2(x) => {
+                out.push(TYPE_UDIM2);
+                write_udim(out, &x.x);
+                write_udim(out, &x.y);
+            },
+            Self::BrickColor(x) => {
+                out.push(TYPE_BRICK_COLOR);
+                out.extend_from_slice(&x.number().to_le_bytes());
+            },
+            Self::Color3(x) => {
+                out.push(TYPE_COLOR3);
+                write_f32s(out, &[x.r, x.g, x.b]);
+            },
+            Self::Vector2(x) => {
+                out.push(TYPE_VECTOR2);
+                write_f32s(out, &[x.x, x.y]);
+            },
+            Self::Vector3(x) => {
+                out.push(TYPE_VECTOR3);
+                write_f32s(out, &[x.x, x.y, x.z]);
+            },
+            Self::CFrame(x) => {
+                // Rotation id 0, the full rotation matrix follows the position.
+                out.push(TYPE_CFRAME);
+                write_f32s(out, &x.pos);
+                out.push(0);
+                write_f32s(out, x.rot_matrix.as_flattened());
+            },
+            Self::EnumItem(x) => {
+                out.push(TYPE_ENUM_ITEM);
+                write_string(out, x.enum_name());
+                out.extend_from_slice(&(x.value() as u32).to_le_bytes());
+            },
+            Self::NumberSequence(x) => {
+                out.push(TYPE_NUMBER_SEQUENCE);
+                out.extend_from_slice(&(x.keypoints().len() as u32).to_le_bytes());
+                for keypoint in x.keypoints() {
+                    write_f32s(out, &[keypoint.envelope, keypoint.time, keypoint.value]);
+                }
+            },
+            Self::ColorSequence(x) => {
+                // Color keypoints have no envelope, it is always 0.
+                out.push(TYPE_COLOR_SEQUENCE);
+                out.extend_from_slice(&(x.keypoints().len() as u32).to_le_bytes());
+                for keypoint in x.keypoints() {
+                    write_f32s(out, &[0.0, keypoint.time, keypoint.value.r, keypoint.value.g, keypoint.value.b]);
+                }
+            },
+            Self::NumberRange(x) => {
+                out.push(TYPE_NUMBER_RANGE);
+                write_f32s(out, &[x.min, x.max]);
+            },
+            Self::Rect(x) => {
+                out.push(TYPE_RECT);
+                write_f32s(out, &[x.min.x, x.min.y, x.max.x, x.max.y]);
+            },
+            Self::Font(x) => {
+                out.push(TYPE_FONT);
+                out.extend_from_slice(&(x.weight.value() as u16).to_le_bytes());
+                out.push(x.style.value() as u8);
+                write_string(out, &x.family);
+                write_string(out, &x.cached_face_id);
+            }
+        }
+    }
+    fn deserialize(reader: &mut AttributeReader) -> LuaResult<Self> {
+        match reader.u8()? {
+            TYPE_STRING => Ok(Self::String(reader.string()?)),
+            TYPE_BOOL => Ok(Self::Bool(reader.u8()? != 0)),
+            TYPE_FLOAT => Ok(Self::Number(reader.f32()?)),
+            TYPE_DOUBLE => Ok(Self::Number(f64::from_le_bytes(reader.array()?))),
+            TYPE_UDIM => Ok(Self::UDim(reader.udim()?)),
+            TYPE_UDIM2 => Ok(Self::UDim2(UDim2 { x: reader.udim()?, y: reader.udim()? })),
+            TYPE_BRICK_COLOR => {
+                let number = reader.u32()?;
+                BrickColor::from_number(number)
+                    .map(Self::BrickColor)
+                    .ok_or_else(|| LuaError::RuntimeError(format!("Serialized attributes have an invalid BrickColor {}", number)))
+            },
+            TYPE_COLOR3 => Ok(Self::Color3(reader.color3()?)),
+            TYPE_VECTOR2 => Ok(Self::Vector2(Vector2 { x: reader.f32()?, y: reader.f32()? })),
+            TYPE_VECTOR3 => Ok(Self::Vector3(Vector3 { x: reader.f32()?, y: reader.f32()?, z: reader.f32()? })),
+            TYPE_CFRAME => {
+                let pos = [reader.f32()?, reader.f32()?, reader.f32()?];
+                let rot_matrix = match reader.u8()? {
+                    0 => [
+                        [reader.f32()?, reader.f32()?, reader.f32()?],
+                        [reader.f32()?, reader.f32()?, reader.f32()?],
+                        [reader.f32()?, reader.f32()?, reader.f32()?]
+                    ],
+                    id => basic_rotation(id)
+                        .ok_or_else(|| LuaError::RuntimeError(format!("Serialized attributes have an invalid CFrame rotation id {}", id)))?
+                };
+                Ok(Self::CFrame(CFrame { rot_matrix, pos }))
+            },
+            TYPE_ENUM_ITEM => {
+                let enum_name = reader.string()?;
+                let value = reader.u32()? as i32;
+                AttributeEnumItem::from_value(&enum_name, value)
+                    .map(Self::EnumItem)
+                    .ok_or_else(|| LuaError::RuntimeError(format!("Enum.{} has no item with value {}", enum_name, value)))
+            },
+            TYPE_NUMBER_SEQUENCE => {
+                let count = reader.u32()?;
+                let keypoints = (0..count)
+                    .map(|_| {
+                        let (envelope, time, value) = (reader.f32()?, reader.f32()?, reader.f32()?);
+                        Ok(NumberSequenceKeypoint { time, value, envelope })
+                    })
+                    .collect::<LuaResult<_>>()?;
+                Ok(Self::NumberSequence(NumberSequence::new(keypoints)?))
+            },
+            TYPE_COLOR_SEQUENCE => {
+                let count = reader.u32()?;
+                let keypoints = (0..count)
+                    .map(|_| {
+                        let (_envelope, time, value) = (reader.f32()?, reader.f32()?, reader.color3()?);
+                        Ok(ColorSequenceKeypoint { time, value })
+                    })
+                    .collect::<LuaResult<_>>()?;
+                Ok(Self::ColorSequence(ColorSequence::new(keypoints)?))
+            },
+            TYPE_NUMBER_RANGE => Ok(Self::NumberRange(NumberRange::new(reader.f32()?, reader.f32()?)?)),
+            TYPE_RECT => Ok(Self::Rect(Rect::new(
+                Vector2 { x: reader.f32()?, y: reader.f32()? },
+                Vector2 { x: reader.f32()?, y: reader.f32()? }
+            ))),
+            TYPE_FONT => {
+                let weight = u16::from_le_bytes(reader.array()?);
+                let weight = FontWeight::from_value(weight as i32)
+                    .ok_or_else(|| LuaError::RuntimeError(format!("Enum.FontWeight has no item with value {}", weight)))?;
+                let style = reader.u8()?;
+                let style = FontStyle::from_value(style as i32)
+                    .ok_or_else(|| LuaError::RuntimeError(format!("Enum.FontStyle has no item with value {}", style)))?;
+                let family = reader.string()?;
+                let cached_face_id = reader.string()?;
+                Ok(Self::Font(Font { family, weight, style, cached_face_id }))
+            },
+            ty => Err(LuaError::RuntimeError(format!("Serialized attributes have an unsupported type 0x{:02x}", ty)))
+        }
+    }
+}
+
+/// The rotation of an axis-aligned CFrame from its id, which is `6 * x + y + 1` with `x` and `y` the directions of
+/// the first two rows, in the order +X, +Y, +Z, -X, -Y, -Z.
+fn basic_rotation(id: u8) -> Option<[[f64; 3]; 3]> {
+    let id = id - 1;
+    let direction = |n: u8| {
+        let mut row = [0.0; 3];
+        row[(n % 3) as usize] = if n < 3 { 1.0 } else { -1.0 };
+        row
+    };
+    if id / 6 >= 6 || id / 6 % 3 == id % 6 % 3 {
+        return None;
+    }
+    let (x, y) = (direction(id / 6), direction(id % 6));
+    let z = [x[1] * y[2] - x[2] * y[1], x[2] * y[0] - x[0] * y[2], x[0] * y[1] - x[1] * y[0]];
+    Some([x, y, z])
+}
+
+struct AttributeReader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> AttributeReader<'a> {
+    fn bytes(&mut self, len: usize) -> LuaResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(LuaError::RuntimeError("Serialized attributes are truncated".into()));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+    fn array<const N: usize>(&mut self) -> LuaResult<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+    fn u8(&mut self) -> LuaResult<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+    fn u32(&mut self) -> LuaResult<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    fn f32(&mut self) -> LuaResult<f64> {
+        Ok(f32::from_le_bytes(self.array()?) as f64)
+    }
+    fn udim(&mut self) -> LuaResult<UDim> {
+        Ok(UDim { scale: self.f32()?, offset: i32::from_le_bytes(self.array()?) })
+    }
+    fn color3(&mut self) -> LuaResult<Color3> {
+        Ok(Color3 { r: self.f32()?, g: self.f32()?, b: self.f32()? })
+    }
+    fn string(&mut self) -> LuaResult<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| LuaError::RuntimeError("Serialized attributes have a string which isn't UTF-8".into()))
+    }
+}
@@ -4,7 +4,7 @@ use r2g_mlua::prelude::*;
 
 use super::{enums::NormalId, LuaSingleton};
 
-#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 pub struct Axes {
     pub x: bool,
     pub y: bool,
@@ -0,0 +1,309 @@
+use r2g_mlua::prelude::*;
+
+use super::{Color3, LuaSingleton};
+
+/// The [`BrickColor`](https://create.roblox.com/docs/reference/engine/datatypes/BrickColor) data type provides a predefined list of named colors, identified by their number.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BrickColor {
+    entry: &'static BrickColorEntry
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct BrickColorEntry {
+    number: u32,
+    name: &'static str,
+    rgb: [u8; 3]
+}
+
+from_lua_copy_impl!(BrickColor);
+
+impl Default for BrickColor {
+    /// Medium stone grey, which unknown colors also give.
+    fn default() -> Self {
+        BrickColor::from_number(194).unwrap()
+    }
+}
+
+impl BrickColor {
+    pub fn from_number(number: u32) -> Option<BrickColor> {
+        BRICK_COLORS.binary_search_by_key(&number, |x| x.number).ok()
+            .map(|i| BrickColor { entry: &BRICK_COLORS[i] })
+    }
+    /// The first color named `name`, some names are used by two colors.
+    pub fn from_name(name: &str) -> Option<BrickColor> {
+        BRICK_COLORS.iter().find(|x| x.name == name).map(|entry| BrickColor { entry })
+    }
+    /// The color closest to `color`.
+    pub fn from_color3(color: Color3) -> BrickColor {
+        let rgb = color.to_rgb().map(|x| x as i32);
+        let distance = |x: &BrickColorEntry| x.rgb.iter().zip(rgb).map(|(a, b)| (*a as i32 - b).pow(2)).sum::<i32>();
+        BrickColor { entry: BRICK_COLORS.iter().min_by_key(|x| distance(x)).unwrap() }
+    }
+    pub fn number(&self) -> u32 {
+        self.entry.number
+    }
+    pub fn name(&self) -> &'static str {
+        self.entry.name
+    }
+    pub fn color(&self) -> Color3 {
+        let [r, g, b] = self.entry.rgb;
+        Color3::from_rgb(r as f64, g as f64, b as f64)
+    }
+}
+
+impl LuaUserData for BrickColor {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Number", |_, this| Ok(this.number()));
+        fields.add_field_method_get("Name", |_, this| Ok(this.name()));
+        fields.add_field_method_get("Color", |_, this| Ok(this.color()));
+        fields.add_field_method_get("r", |_, this| Ok(this.color().r));
+        fields.add_field_method_get("g", |_, this| Ok(this.color().g));
+        fields.add_field_method_get("b", |_, this| Ok(this.color().b));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| Ok(this.name()));
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for BrickColor {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, args: LuaMultiValue| {
+                // BrickColor.new(number), BrickColor.new(name), BrickColor.new(color) or BrickColor.new(r, g, b).
+                Ok(match (args.front(), args.len()) {
+                    (Some(LuaValue::Integer(x)), 1) => BrickColor::from_number(*x as u32).unwrap_or_default(),
+                    (Some(LuaValue::Number(x)), 1) => BrickColor::from_number(*x as u32).unwrap_or_default(),
+                    (Some(LuaValue::String(x)), _) => BrickColor::from_name(&x.to_str()?).unwrap_or_default(),
+                    (Some(LuaValue::UserData(x)), _) => BrickColor::from_color3(*x.borrow::<Color3>()?),
+                    _ => {
+                        let component = |i: usize| args.get(i).and_then(|x| x.as_number()).unwrap_or_default();
+                        BrickColor::from_color3(Color3::new(component(0), component(1), component(2)))
+                    }
+                })
+            })?
+        )?;
+        for (function, number) in [
+            ("White", 1), ("Gray", 194), ("DarkGray", 199), ("Black", 26),
+            ("Red", 21), ("Yellow", 24), ("Green", 28), ("Blue", 23)
+        ] {
+            let color = BrickColor::from_number(number).unwrap();
+            table.raw_set(function, lua.create_function(move |_, ()| Ok(color))?)?;
+        }
+        lua.globals().raw_set("BrickColor", table)?;
+        Ok(())
+    }
+}
+
+/// Every BrickColor, sorted by number.
+static BRICK_COLORS: &[BrickColorEntry] = &[
+    BrickColorEntry { number: 1, name: "White", rgb: [242, 243, 243] },
+    BrickColorEntry { number: 2, name: "Grey", rgb: [161, 165, 162] },
+    BrickColorEntry { number: 3, name: "Light yellow", rgb: [249, 233, 153] },
+    BrickColorEntry { number: 5, name: "Brick yellow", rgb: [215, 197, 154] },
+    BrickColorEntry { number: 6, name: "Light green (Mint)", rgb: [194, 218, 184] },
+    BrickColorEntry { number: 9, name: "Light reddish violet", rgb: [232, 186, 200] },
+    BrickColorEntry { number: 11, name: "Pastel Blue", rgb: [128, 187, 219] },
+    BrickColorEntry { number: 12, name: "Light orange brown", rgb: [203, 132, 66] },
+    BrickColorEntry { number: 18, name: "Nougat", rgb: [204, 142, 105] },
+    BrickColorEntry { number: 21, name: "Bright red", rgb: [196, 40, 28] },
+    BrickColorEntry { number: 22, name: "Med. reddish violet", rgb: [196, 112, 160] },
+    BrickColorEntry { number: 23, name: "Bright blue", rgb: [13, 105, 172] },
+    BrickColorEntry { number: 24, name: "Bright yellow", rgb: [245, 205, 48] },
+    BrickColorEntry { number: 25, name: "Earth orange", rgb: [98, 71, 50] },
+    BrickColorEntry { number: 26, name: "Black", rgb: [27, 42, 53] },
+    BrickColorEntry { number: 27, name: "Dark grey", rgb: [109, 110, 108] },
+    BrickColorEntry { number: 28, name: "Dark green", rgb: [40, 127, 71] },
+    BrickColorEntry { number: 29, name: "Medium green", rgb: [161, 196, 140] },
+    BrickColorEntry { number: 36, name: "Lig. Yellowich orange", rgb: [243, 207, 155] },
+    BrickColorEntry { number: 37, name: "Bright green", rgb: [75, 151, 75] },
+    BrickColorEntry { number: 38, name: "Dark orange", rgb: [160, 95, 53] },
+    BrickColorEntry { number: 39, name: "Light bluish violet", rgb: [193, 202, 222] },
+    BrickColorEntry { number: 40, name: "Transparent", rgb: [236, 236, 236] },
+    BrickColorEntry { number: 41, name: "Tr. Red", rgb: [205, 84, 75] },
+    BrickColorEntry { number: 42, name: "Tr. Lg blue", rgb: [193, 223, 240] },
+    BrickColorEntry { number: 43, name: "Tr. Blue", rgb: [123, 182, 232] },
+    BrickColorEntry { number: 44, name: "Tr. Yellow", rgb: [247, 241, 141] },
+    BrickColorEntry { number: 45, name: "Light blue", rgb: [180, 210, 228] },
+    BrickColorEntry { number: 47, name: "Tr. Flu. Reddish orange", rgb: [217, 133, 108] },
+    BrickColorEntry { number: 48, name: "Tr. Green", rgb: [132, 182, 141] },
+    BrickColorEntry { number: 49, name: "Tr. Flu. Green", rgb: [248, 241, 132] },
+    BrickColorEntry { number: 50, name: "Phosph. White", rgb: [236, 232, 222] },
+    BrickColorEntry { number: 100, name: "Light red", rgb: [238, 196, 182] },
+    BrickColorEntry { number: 101, name: "Medium red", rgb: [218, 134, 122] },
+    BrickColorEntry { number: 102, name: "Medium blue", rgb: [110, 153, 202] },
+    BrickColorEntry { number: 103, name: "Light grey", rgb: [199, 193, 183] },
+    BrickColorEntry { number: 104, name: "Bright violet", rgb: [107, 50, 124] },
+    BrickColorEntry { number: 105, name: "Br. yellowish orange", rgb: [226, 155, 64] },
+    BrickColorEntry { number: 106, name: "Bright orange", rgb: [218, 133, 65] },
+    BrickColorEntry { number: 107, name: "Bright bluish green", rgb: [0, 143, 156] },
+    BrickColorEntry { number: 108, name: "Earth yellow", rgb: [104, 92, 67] },
+    BrickColorEntry { number: 110, name: "Bright bluish violet", rgb: [67, 84, 147] },
+    BrickColorEntry { number: 111, name: "Tr. Brown", rgb: [191, 183, 177] },
+    BrickColorEntry { number: 112, name: "Medium bluish violet", rgb: [104, 116, 172] },
+    BrickColorEntry { number: 113, name: "Tr. Medi. reddish violet", rgb: [229, 173, 200] },
+    BrickColorEntry { number: 115, name: "Med. yellowish green", rgb: [199, 210, 60] },
+    BrickColorEntry { number: 116, name: "Med. bluish green", rgb: [85, 165, 175] },
+    BrickColorEntry { number: 118, name: "Light bluish green", rgb: [183, 215, 213] },
+    BrickColorEntry { number: 119, name: "Br. yellowish green", rgb: [164, 189, 71] },
+    BrickColorEntry { number: 120, name: "Lig. yellowish green", rgb: [217, 228, 167] },
+    BrickColorEntry { number: 121, name: "Med. yellowish orange", rgb: [231, 172, 88] },
+    BrickColorEntry { number: 123, name: "Br. reddish orange", rgb: [211, 111, 76] },
+    BrickColorEntry { number: 124, name: "Bright reddish violet", rgb: [146, 57, 120] },
+    BrickColorEntry { number: 125, name: "Light orange", rgb: [234, 184, 146] },
+    BrickColorEntry { number: 126, name: "Tr. Bright bluish violet", rgb: [165, 165, 203] },
+    BrickColorEntry { number: 127, name: "Gold", rgb: [220, 188, 129] },
+    BrickColorEntry { number: 128, name: "Dark nougat", rgb: [174, 122, 89] },
+    BrickColorEntry { number: 131, name: "Silver", rgb: [156, 163, 168] },
+    BrickColorEntry { number: 133, name: "Neon orange", rgb: [213, 115, 61] },
+    BrickColorEntry { number: 134, name: "Neon green", rgb: [216, 221, 86] },
+    BrickColorEntry { number: 135, name: "Sand blue", rgb: [116, 134, 157] },
+    BrickColorEntry { number: 136, name: "Sand violet", rgb: [135, 124, 144] },
+    BrickColorEntry { number: 137, name: "Medium orange", rgb: [224, 152, 100] },
+    BrickColorEntry { number: 138, name: "Sand yellow", rgb: [149, 138, 115] },
+    BrickColorEntry { number: 140, name: "Earth blue", rgb: [32, 58, 86] },
+    BrickColorEntry { number: 141, name: "Earth green", rgb: [39, 70, 45] },
+    BrickColorEntry { number: 143, name: "Tr. Flu. Blue", rgb: [207, 226, 247] },
+    BrickColorEntry { number: 145, name: "Sand blue metallic", rgb: [121, 136, 161] },
+    BrickColorEntry { number: 146, name: "Sand violet metallic", rgb: [149, 142, 163] },
+    BrickColorEntry { number: 147, name: "Sand yellow metallic", rgb: [147, 135, 103] },
+    BrickColorEntry { number: 148, name: "Dark grey metallic", rgb: [87, 88, 87] },
+    BrickColorEntry { number: 149, name: "Black metallic", rgb: [22, 29, 50] },
+    BrickColorEntry { number: 150, name: "Light grey metallic", rgb: [171, 173, 172] },
+    BrickColorEntry { number: 151, name: "Sand green", rgb: [120, 144, 130] },
+    BrickColorEntry { number: 153, name: "Sand red", rgb: [149, 121, 119] },
+    BrickColorEntry { number: 154, name: "Dark red", rgb: [123, 46, 47] },
+    BrickColorEntry { number: 157, name: "Tr. Flu. Yellow", rgb: [255, 246, 123] },
+    BrickColorEntry { number: 158, name: "Tr. Flu. Red", rgb: [225, 164, 194] },
+    BrickColorEntry { number: 168, name: "Gun metallic", rgb: [117, 108, 98] },
+    BrickColorEntry { number: 176, name: "Red flip/flop", rgb: [151, 105, 91] },
+    BrickColorEntry { number: 178, name: "Yellow flip/flop", rgb: [180, 132, 85] },
+    BrickColorEntry { number: 179, name: "Silver flip/flop", rgb: [137, 135, 136] },
+    BrickColorEntry { number: 180, name: "Curry", rgb: [215, 169, 75] },
+    BrickColorEntry { number: 190, name: "Fire Yellow", rgb: [249, 214, 46] },
+    BrickColorEntry { number: 191, name: "Flame yellowish orange", rgb: [232, 171, 45] },
+    BrickColorEntry { number: 192, name: "Reddish brown", rgb: [105, 64, 40] },
+    BrickColorEntry { number: 193, name: "Flame reddish orange", rgb: [207, 96, 36] },
+    BrickColorEntry { number: 194, name: "Medium stone grey", rgb: [163, 162, 165] },
+    BrickColorEntry { number: 195, name: "Royal blue", rgb: [70, 103, 164] },
+    BrickColorEntry { number: 196, name: "Dark Royal blue", rgb: [35, 71, 139] },
+    BrickColorEntry { number: 198, name: "Bright reddish lilac", rgb: [142, 66, 133] },
+    BrickColorEntry { number: 199, name: "Dark stone grey", rgb: [99, 95, 98] },
+    BrickColorEntry { number: 200, name: "Lemon metalic", rgb: [130, 138, 93] },
+    BrickColorEntry { number: 208, name: "Light stone grey", rgb: [229, 228, 223] },
+    BrickColorEntry { number: 209, name: "Dark Curry", rgb: [176, 142, 68] },
+    BrickColorEntry { number: 210, name: "Faded green", rgb: [112, 149, 120] },
+    BrickColorEntry { number: 211, name: "Turquoise", rgb: [121, 181, 181] },
+    BrickColorEntry { number: 212, name: "Light Royal blue", rgb: [159, 195, 233] },
+    BrickColorEntry { number: 213, name: "Medium Royal blue", rgb: [108, 129, 183] },
+    BrickColorEntry { number: 216, name: "Rust", rgb: [144, 76, 42] },
+    BrickColorEntry { number: 217, name: "Brown", rgb: [124, 92, 70] },
+    BrickColorEntry { number: 218, name: "Reddish lilac", rgb: [150, 112, 159] },
+    BrickColorEntry { number: 219, name: "Lilac", rgb: [107, 98, 155] },
+    BrickColorEntry { number: 220, name: "Light lilac", rgb: [167, 169, 206] },
+    BrickColorEntry { number: 221, name: "Bright purple", rgb: [205, 98, 152] },
+    BrickColorEntry { number: 222, name: "Light purple", rgb: [228, 173, 200] },
+    BrickColorEntry { number: 223, name: "Light pink", rgb: [220, 144, 149] },
+    BrickColorEntry { number: 224, name: "Light brick yellow", rgb: [240, 213, 160] },
+    BrickColorEntry { number: 225, name: "Warm yellowish orange", rgb: [235, 184, 127] },
+    BrickColorEntry { number: 226, name: "Cool yellow", rgb: [253, 234, 141] },
+    BrickColorEntry { number: 232, name: "Dove blue", rgb: [125, 187, 221] },
+    BrickColorEntry { number: 268, name: "Medium lilac", rgb: [52, 43, 117] },
+    BrickColorEntry { number: 301, name: "Slime green", rgb: [80, 109, 84] },
+    BrickColorEntry { number: 302, name: "Smoky grey", rgb: [91, 93, 105] },
+    BrickColorEntry { number: 303, name: "Dark blue", rgb: [0, 16, 176] },
+    BrickColorEntry { number: 304, name: "Parsley green", rgb: [44, 101, 29] },
+    BrickColorEntry { number: 305, name: "Steel blue", rgb: [82, 124, 174] },
+    BrickColorEntry { number: 306, name: "Storm blue", rgb: [51, 88, 130] },
+    BrickColorEntry { number: 307, name: "Lapis", rgb: [16, 42, 220] },
+    BrickColorEntry { number: 308, name: "Dark indigo", rgb: [61, 21, 133] },
+    BrickColorEntry { number: 309, name: "Sea green", rgb: [52, 142, 64] },
+    BrickColorEntry { number: 310, name: "Shamrock", rgb: [91, 154, 76] },
+    BrickColorEntry { number: 311, name: "Fossil", rgb: [159, 161, 172] },
+    BrickColorEntry { number: 312, name: "Mulberry", rgb: [89, 34, 89] },
+    BrickColorEntry { number: 313, name: "Forest green", rgb: [31, 128, 29] },
+    BrickColorEntry { number: 314, name: "Cadet blue", rgb: [159, 173, 192] },
+    BrickColorEntry { number: 315, name: "Electric blue", rgb: [9, 137, 207] },
+    BrickColorEntry { number: 316, name: "Eggplant", rgb: [123, 0, 123] },
+    BrickColorEntry { number: 317, name: "Moss", rgb: [124, 156, 107] },
+    BrickColorEntry { number: 318, name: "Artichoke", rgb: [138, 171, 133] },
+    BrickColorEntry { number: 319, name: "Sage green", rgb: [185, 196, 177] },
+    BrickColorEntry { number: 320, name: "Ghost grey", rgb: [202, 203, 209] },
+    BrickColorEntry { number: 321, name: "Lilac", rgb: [167, 94, 155] },
+    BrickColorEntry { number: 322, name: "Plum", rgb: [123, 47, 123] },
+    BrickColorEntry { number: 323, name: "Olivine", rgb: [148, 190, 129] },
+    BrickColorEntry { number: 324, name: "Laurel green", rgb: [168, 189, 153] },
+    BrickColorEntry { number: 325, name: "Quill grey", rgb: [223, 223, 222] },
+    BrickColorEntry { number: 327, name: "Crimson", rgb: [151, 0, 0] },
+    BrickColorEntry { number: 328, name: "Mint", rgb: [177, 229, 166] },
+    BrickColorEntry { number: 329, name: "Baby blue", rgb: [152, 194, 219] },
+    BrickColorEntry { number: 330, name: "Carnation pink", rgb: [255, 152, 220] },
+    BrickColorEntry { number: 331, name: "Persimmon", rgb: [255, 89, 89] },
+    BrickColorEntry { number: 332, name: "Maroon", rgb: [117, 0, 0] },
+    BrickColorEntry { number: 333, name: "Gold", rgb: [239, 184, 56] },
+    BrickColorEntry { number: 334, name: "Daisy orange", rgb: [248, 217, 109] },
+    BrickColorEntry { number: 335, name: "Pearl", rgb: [231, 231, 236] },
+    BrickColorEntry { number: 336, name: "Fog", rgb: [199, 212, 228] },
+    BrickColorEntry { number: 337, name: "Salmon", rgb: [255, 148, 148] },
+    BrickColorEntry { number: 338, name: "Terra Cotta", rgb: [190, 104, 98] },
+    BrickColorEntry { number: 339, name: "Cocoa", rgb: [86, 36, 36] },
+    BrickColorEntry { number: 340, name: "Wheat", rgb: [241, 231, 199] },
+    BrickColorEntry { number: 341, name: "Buttermilk", rgb: [254, 243, 187] },
+    BrickColorEntry { number: 342, name: "Mauve", rgb: [224, 178, 208] },
+    BrickColorEntry { number: 343, name: "Sunrise", rgb: [212, 144, 189] },
+    BrickColorEntry { number: 344, name: "Tawny", rgb: [150, 85, 85] },
+    BrickColorEntry { number: 345, name: "Rust", rgb: [143, 76, 42] },
+    BrickColorEntry { number: 346, name: "Cashmere", rgb: [211, 190, 150] },
+    BrickColorEntry { number: 347, name: "Khaki", rgb: [226, 220, 188] },
+    BrickColorEntry { number: 348, name: "Lily white", rgb: [237, 234, 234] },
+    BrickColorEntry { number: 349, name: "Seashell", rgb: [233, 218, 218] },
+    BrickColorEntry { number: 350, name: "Burgundy", rgb: [136, 62, 62] },
+    BrickColorEntry { number: 351, name: "Cork", rgb: [188, 155, 93] },
+    BrickColorEntry { number: 352, name: "Burlap", rgb: [199, 172, 120] },
+    BrickColorEntry { number: 353, name: "Beige", rgb: [202, 191, 163] },
+    BrickColorEntry { number: 354, name: "Oyster", rgb: [187, 179, 178] },
+    BrickColorEntry { number: 355, name: "Pine Cone", rgb: [108, 88, 75] },
+    BrickColorEntry { number: 356, name: "Fawn brown", rgb: [160, 132, 79] },
+    BrickColorEntry { number: 357, name: "Hurricane grey", rgb: [149, 137, 136] },
+    BrickColorEntry { number: 358, name: "Cloudy grey", rgb: [171, 168, 158] },
+    BrickColorEntry { number: 359, name: "Linen", rgb: [175, 148, 131] },
+    BrickColorEntry { number: 360, name: "Copper", rgb: [150, 103, 102] },
+    BrickColorEntry { number: 361, name: "Dirt brown", rgb: [86, 66, 54] },
+    BrickColorEntry { number: 362, name: "Bronze", rgb: [126, 104, 63] },
+    BrickColorEntry { number: 363, name: "Flint", rgb: [105, 102, 92] },
+    BrickColorEntry { number: 364, name: "Dark taupe", rgb: [90, 76, 66] },
+    BrickColorEntry { number: 365, name: "Burnt Sienna", rgb: [106, 57, 9] },
+    BrickColorEntry { number: 1001, name: "Institutional white", rgb: [248, 248, 248] },
+    BrickColorEntry { number: 1002, name: "Mid gray", rgb: [205, 205, 205] },
+    BrickColorEntry { number: 1003, name: "Really black", rgb: [17, 17, 17] },
+    BrickColorEntry { number: 1004, name: "Really red", rgb: [255, 0, 0] },
+    BrickColorEntry { number: 1005, name: "Deep orange", rgb: [255, 176, 0] },
+    BrickColorEntry { number: 1006, name: "Alder", rgb: [180, 128, 255] },
+    BrickColorEntry { number: 1007, name: "Dusty Rose", rgb: [163, 75, 75] },
+    BrickColorEntry { number: 1008, name: "Olive", rgb: [193, 190, 66] },
+    BrickColorEntry { number: 1009, name: "New Yeller", rgb: [255, 255, 0] },
+    BrickColorEntry { number: 1010, name: "Really blue", rgb: [0, 0, 255] },
+    BrickColorEntry { number: 1011, name: "Navy blue", rgb: [0, 32, 96] },
+    BrickColorEntry { number: 1012, name: "Deep blue", rgb: [33, 84, 185] },
+    BrickColorEntry { number: 1013, name: "Cyan", rgb: [4, 175, 236] },
+    BrickColorEntry { number: 1014, name: "CGA brown", rgb: [170, 85, 0] },
+    BrickColorEntry { number: 1015, name: "Magenta", rgb: [170, 0, 170] },
+    BrickColorEntry { number: 1016, name: "Pink", rgb: [255, 102, 204] },
+    BrickColorEntry { number: 1017, name: "Deep orange", rgb: [255, 175, 0] },
+    BrickColorEntry { number: 1018, name: "Teal", rgb: [18, 238, 212] },
+    BrickColorEntry { number: 1019, name: "Toothpaste", rgb: [0, 255, 255] },
+    BrickColorEntry { number: 1020, name: "Lime green", rgb: [0, 255, 0] },
+    BrickColorEntry { number: 1021, name: "Camo", rgb: [58, 125, 21] },
+    BrickColorEntry { number: 1022, name: "Grime", rgb: [127, 142, 100] },
+    BrickColorEntry { number: 1023, name: "Lavender", rgb: [140, 91, 159] },
+    BrickColorEntry { number: 1024, name: "Pastel light blue", rgb: [175, 221, 255] },
+    BrickColorEntry { number: 1025, name: "Pastel orange", rgb: [255, 201, 201] },
+    BrickColorEntry { number: 1026, name: "Pastel violet", rgb: [177, 167, 255] },
+    BrickColorEntry { number: 1027, name: "Pastel blue-green", rgb: [159, 243, 233] },
+    BrickColorEntry { number: 1028, name: "Pastel green", rgb: [204, 255, 204] },
+    BrickColorEntry { number: 1029, name: "Pastel yellow", rgb: [255, 255, 204] },
+    BrickColorEntry { number: 1030, name: "Pastel brown", rgb: [255, 204, 153] },
+    BrickColorEntry { number: 1031, name: "Royal purple", rgb: [98, 37, 209] },
+    BrickColorEntry { number: 1032, name: "Hot pink", rgb: [255, 0, 191] }
+];
@@ -0,0 +1,130 @@
+use r2g_mlua::prelude::*;
+
+use super::LuaSingleton;
+
+/// The [`Color3`](https://create.roblox.com/docs/reference/engine/datatypes/Color3) data type describes a color using red, green, and blue components in the range of 0 to 1.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Color3 {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64
+}
+
+from_lua_copy_impl!(Color3);
+
+impl Color3 {
+    pub const fn new(r: f64, g: f64, b: f64) -> Color3 {
+        Color3 { r, g, b }
+    }
+    /// Creates a `Color3` from components in the range of 0 to 255.
+    pub fn from_rgb(r: f64, g: f64, b: f64) -> Color3 {
+        Color3::new(r / 255.0, g / 255.0, b / 255.0)
+    }
+    /// Creates a `Color3` from its hue, saturation and value, in the range of 0 to 1.
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Color3 {
+        let h = (h.rem_euclid(1.0)) * 6.0;
+        let i = h.floor();
+        let f = h - i;
+        let (p, q, t) = (v * (1.0 - s), v * (1.0 - s * f), v * (1.0 - s * (1.0 - f)));
+        match i as u8 {
+            0 => Color3::new(v, t, p),
+            1 => Color3::new(q, v, p),
+            2 => Color3::new(p, v, t),
+            3 => Color3::new(p, q, v),
+            4 => Color3::new(t, p, v),
+            _ => Color3::new(v, p, q)
+        }
+    }
+    /// Creates a `Color3` from a hex code of 3 or 6 digits, optionally prefixed with `#`.
+    pub fn from_hex(hex: &str) -> Option<Color3> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        let digits: Vec<u32> = hex.chars().map(|x| x.to_digit(16)).collect::<Option<_>>()?;
+        match digits[..] {
+            [r, g, b] => Some(Color3::from_rgb((r * 17) as f64, (g * 17) as f64, (b * 17) as f64)),
+            [r1, r2, g1, g2, b1, b2] => Some(Color3::from_rgb((r1 * 16 + r2) as f64, (g1 * 16 + g2) as f64, (b1 * 16 + b2) as f64)),
+            _ => None
+        }
+    }
+    /// Returns the hue, saturation and value of the color.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / delta).rem_euclid(6.0) / 6.0
+        } else if max == self.g {
+            ((self.b - self.r) / delta + 2.0) / 6.0
+        } else {
+            ((self.r - self.g) / delta + 4.0) / 6.0
+        };
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+        (h, s, max)
+    }
+    /// Returns the color as 6 hex digits, RRGGBB.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b] = self.to_rgb();
+        format!("{:02X}{:02X}{:02X}", r, g, b)
+    }
+    /// Returns the components in the range of 0 to 255.
+    pub fn to_rgb(&self) -> [u8; 3] {
+        [self.r, self.g, self.b].map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+    /// Returns a color linearly interpolated between the color and another color.
+    pub fn lerp(&self, other: Color3, alpha: f64) -> Color3 {
+        Color3::new(
+            self.r + (other.r - self.r) * alpha,
+            self.g + (other.g - self.g) * alpha,
+            self.b + (other.b - self.b) * alpha
+        )
+    }
+}
+
+impl LuaUserData for Color3 {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("R", |_, this| Ok(this.r));
+        fields.add_field_method_get("G", |_, this| Ok(this.g));
+        fields.add_field_method_get("B", |_, this| Ok(this.b));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Lerp", |_, this, (other, alpha)| Ok(this.lerp(other, alpha)));
+        methods.add_method("ToHSV", |_, this, ()| Ok(this.to_hsv()));
+        methods.add_method("ToHex", |_, this, ()| Ok(this.to_hex()));
+        methods.add_meta_method("__tostring", |_, this, ()| Ok(format!("({}, {}, {})", this.r, this.g, this.b)));
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for Color3 {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (r, g, b): (Option<f64>, Option<f64>, Option<f64>)| {
+                Ok(Color3::new(r.unwrap_or_default(), g.unwrap_or_default(), b.unwrap_or_default()))
+            })?
+        )?;
+        table.raw_set(
+            "fromRGB",
+            lua.create_function(|_, (r, g, b): (Option<f64>, Option<f64>, Option<f64>)| {
+                Ok(Color3::from_rgb(r.unwrap_or_default(), g.unwrap_or_default(), b.unwrap_or_default()))
+            })?
+        )?;
+        table.raw_set(
+            "fromHSV",
+            lua.create_function(|_, (h, s, v): (f64, f64, f64)| Ok(Color3::from_hsv(h, s, v)))?
+        )?;
+        table.raw_set(
+            "fromHex",
+            lua.create_function(|_, hex: String| {
+                Color3::from_hex(&hex).ok_or_else(|| LuaError::RuntimeError(format!("Unable to convert characters to hex value: {}", hex)))
+            })?
+        )?;
+        table.raw_set(
+            "toHSV",
+            lua.create_function(|_, color: Color3| Ok(color.to_hsv()))?
+        )?;
+        lua.globals().raw_set("Color3", table)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+use r2g_mlua::prelude::*;
+
+use super::EnumItem;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic
+}
+
+from_lua_copy_impl!(FontStyle);
+enum_item!(FontStyle, [Normal, Italic]);
+
+impl FontStyle {
+    pub fn from_value(value: i32) -> Option<FontStyle> {
+        Self::ITEMS.get(usize::try_from(value).ok()?).copied()
+    }
+}
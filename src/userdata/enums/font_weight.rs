@@ -0,0 +1,26 @@
+use r2g_mlua::prelude::*;
+
+use super::EnumItem;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum FontWeight {
+    Thin = 100,
+    ExtraLight = 200,
+    Light = 300,
+    #[default]
+    Regular = 400,
+    Medium = 500,
+    SemiBold = 600,
+    Bold = 700,
+    ExtraBold = 800,
+    Heavy = 900
+}
+
+from_lua_copy_impl!(FontWeight);
+enum_item!(FontWeight, [Thin, ExtraLight, Light, Regular, Medium, SemiBold, Bold, ExtraBold, Heavy]);
+
+impl FontWeight {
+    pub fn from_value(value: i32) -> Option<FontWeight> {
+        Self::ITEMS.iter().copied().find(|x| x.value() == value)
+    }
+}
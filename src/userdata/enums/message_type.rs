@@ -0,0 +1,27 @@
+use r2g_mlua::prelude::*;
+
+use crate::core::HostMessageKind;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum MessageType {
+    MessageOutput,
+    MessageInfo,
+    MessageWarning,
+    MessageError
+}
+
+from_lua_copy_impl!(MessageType);
+enum_item!(MessageType, [MessageOutput, MessageInfo, MessageWarning, MessageError]);
+
+impl MessageType {
+    /// Messages which are not produced by scripts have no message type and are not logged.
+    pub fn from_host_message_kind(kind: HostMessageKind) -> Option<MessageType> {
+        match kind {
+            HostMessageKind::Output => Some(Self::MessageOutput),
+            HostMessageKind::Info => Some(Self::MessageInfo),
+            HostMessageKind::Warning => Some(Self::MessageWarning),
+            HostMessageKind::Error => Some(Self::MessageError),
+            HostMessageKind::Debug => None
+        }
+    }
+}
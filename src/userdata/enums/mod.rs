@@ -1,13 +1,107 @@
+/// Implements [`EnumItem`] and the EnumItem members for an enum, from its items in the order of their values.
+macro_rules! enum_item {
+    ($name: ident, [$($item: ident),*]) => {
+        impl crate::userdata::enums::EnumItem for $name {
+            const ENUM_NAME: &'static str = stringify!($name);
+            const ITEMS: &'static [Self] = &[$(Self::$item),*];
+            fn name(self) -> &'static str {
+                match self {
+                    $(Self::$item => stringify!($item)),*
+                }
+            }
+            fn value(self) -> i32 {
+                self as i32
+            }
+        }
+        impl LuaUserData for $name {
+            fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+                crate::userdata::enums::add_enum_item_methods(methods);
+            }
+            fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+                crate::userdata::enums::add_enum_item_fields(fields);
+            }
+        }
+    };
+}
+
+/// Invokes `$callback!` with the given leading arguments followed by every enum scripts can name,
+/// so the registry, attributes and marshalling can't disagree about which enums exist.
+macro_rules! with_enums {
+    ($callback: ident $(, $arg: ident)*) => {
+        $callback!($($arg,)* Axis, FontStyle, FontWeight, MessageType, ModelLevelOfDetail, ModelStreamingMode, NormalId, RotationOrder, RunContext, SignalBehavior);
+    };
+}
+pub(crate) use with_enums;
+
 mod normalid;
 mod axis;
 mod rotation_order;
 mod model_level_of_detail;
 mod model_streaming_mode;
 mod run_context;
+mod message_type;
+mod signal_behavior;
+mod font_weight;
+mod font_style;
 
 pub use normalid::NormalId;
 pub use axis::Axis;
 pub use rotation_order::RotationOrder;
 pub use model_level_of_detail::ModelLevelOfDetail;
 pub use model_streaming_mode::ModelStreamingMode;
-pub use run_context::RunContext;
\ No newline at end of file
+pub use run_context::RunContext;
+pub use message_type::MessageType;
+pub use signal_behavior::SignalBehavior;
+pub use font_weight::FontWeight;
+pub use font_style::FontStyle;
+
+use r2g_mlua::prelude::*;
+
+use crate::core::registry_keys;
+
+/// An item of an enum scripts can name, `Enum.<ENUM_NAME>.<name>` in Lua.
+pub trait EnumItem: Copy + PartialEq + LuaUserData + 'static {
+    const ENUM_NAME: &'static str;
+    /// Every item, in the order of their values.
+    const ITEMS: &'static [Self];
+    fn name(self) -> &'static str;
+    fn value(self) -> i32;
+}
+
+fn add_enum_item_methods<T: EnumItem, M: LuaUserDataMethods<T>>(methods: &mut M) {
+    methods.add_meta_method("__tostring", |_, this, ()| Ok(format!("Enum.{}.{}", T::ENUM_NAME, this.name())));
+    methods.add_meta_method("__eq", |_, this, other: LuaAnyUserData| Ok(other.borrow::<T>().is_ok_and(|x| *this == *x)));
+}
+
+fn add_enum_item_fields<T: EnumItem, F: LuaUserDataFields<T>>(fields: &mut F) {
+    fields.add_meta_field("__subtype", "EnumItem");
+    fields.add_field_method_get("Name", |_, this| Ok(this.name()));
+    fields.add_field_method_get("Value", |_, this| Ok(this.value()));
+    fields.add_field_method_get("EnumType", |lua, _| {
+        let enums: LuaTable = lua.named_registry_value(registry_keys::ENUMS)?;
+        enums.raw_get::<LuaTable>(T::ENUM_NAME)
+    });
+}
+
+fn add_enum<T: EnumItem>(lua: &Lua, enums: &LuaTable) -> LuaResult<()> {
+    let table = lua.create_table()?;
+    for item in T::ITEMS {
+        table.raw_set(item.name(), *item)?;
+    }
+    table.set_readonly(true);
+    enums.raw_set(T::ENUM_NAME, table)
+}
+
+/// Registers the `Enum` global, holding the items of every enum scripts can name.
+pub(super) fn register_enums(lua: &Lua) -> LuaResult<()> {
+    let enums = lua.create_table()?;
+    macro_rules! add_enums {
+        ($lua: ident, $enums: ident, $($name: ident),*) => {
+            $(add_enum::<$name>($lua, &$enums)?;)*
+        };
+    }
+    with_enums!(add_enums, lua, enums);
+    enums.set_readonly(true);
+    lua.set_named_registry_value(registry_keys::ENUMS, &enums)?;
+    lua.globals().raw_set("Enum", enums)
+}
@@ -0,0 +1,31 @@
+use r2g_mlua::prelude::*;
+
+use super::EnumItem;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum SignalBehavior {
+    #[default]
+    Default,
+    Immediate,
+    Deferred,
+    AncestryDeferred
+}
+
+from_lua_copy_impl!(SignalBehavior);
+enum_item!(SignalBehavior, [Default, Immediate, Deferred, AncestryDeferred]);
+
+impl SignalBehavior {
+    /// Reads the value of the SignalBehavior fast flag, unknown values are treated as Default.
+    pub fn from_value(value: i64) -> SignalBehavior {
+        Self::ITEMS.get(value as usize).copied().unwrap_or_default()
+    }
+    /// Whether handlers are deferred to the next invocation point instead of running inside the fire.
+    /// Default behaves as Immediate. AncestryDeferred only defers the signals fired while reparenting.
+    pub fn is_deferred(self, ancestry: bool) -> bool {
+        match self {
+            Self::Default | Self::Immediate => false,
+            Self::Deferred => true,
+            Self::AncestryDeferred => ancestry
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use std::{cell::RefCell, collections::HashMap, future::Future, mem::take, pin::Pin, rc::Rc, task::{Context, Poll}};
+use std::{ffi::c_int, mem::take};
 
-use r2g_mlua::prelude::*;
-use super::from_lua_clone_impl;
-use crate::core::{get_state, get_state_with_rwlock, get_task_scheduler_from_lua, FastFlag, LuauState, ParallelDispatch, Trc, TrcReadLock, TrcWriteLock, Weak};
+use r2g_mlua::{ffi::{self, lua_State}, prelude::*};
+use super::{from_lua_clone_impl, LuaSingleton, MarshalledValues};
+use super::enums::SignalBehavior;
+use crate::core::{get_state, get_state_with_rwlock, get_task_scheduler_from_lua, registry_keys, FastFlag, HostMessageKind, LuauState, ParallelDispatch, Trc, TrcWriteLock, Weak};
+use crate::instance::ManagedInstance;
 pub type ManagedRBXScriptSignal = Trc<RBXScriptSignal>;
 
 #[derive(Debug, Clone)]
@@ -17,36 +19,88 @@ struct SignalCallback {
     once: bool,
     parallel: ParallelDispatch
 }
+/// A thread parked on a signal by `Wait`, see [`RBXScriptSignal::park`].
+#[derive(Debug)]
+struct SignalWaiter {
+    thread: LuaThread,
+    state: Trc<LuauState>,
+    token: u64,
+    parallel: ParallelDispatch,
+    // Only resumed by a ChildAdded fire of the child with this name, for WaitForChild.
+    child_name: Option<String>
+}
 #[derive(Debug)]
 pub struct RBXScriptSignal {
-    callbacks: HashMap<usize, SignalCallback>,
+    // Sorted by id, which is the order the callbacks were connected in.
+    callbacks: Vec<(usize, SignalCallback)>,
+    waiters: Vec<SignalWaiter>,
     this_ptr: Option<Weak<RBXScriptSignal>>,
     id: usize
 }
-struct InnerRBXScriptSignalFuture {
-    event: ManagedRBXScriptSignal,
-    lua: Lua,
-    resolved: bool,
-    waiting: bool,
-    values: LuaMultiValue
+
+impl SignalCallback {
+    fn is_foreign(&self, state: *mut LuauState) -> bool {
+        unsafe { self.state.access() != state }
+    }
+    /// Functions can only run in the Lua VM they were created in, so callbacks connected in another state
+    /// are always deferred by that state, with copies of the arguments.
+    fn defer_foreign(&self, args: &LuaMultiValue) -> LuaResult<()> {
+        let args = MarshalledValues::new(args)?;
+        let state = unsafe { self.state.access().as_ref().unwrap_unchecked() };
+        state.get_task_scheduler().defer_foreign_func(self.func.clone(), args, self.parallel);
+        Ok(())
+    }
 }
-pub struct RBXScriptSignalFuture {
-    future: Rc<RefCell<InnerRBXScriptSignalFuture>>
+impl SignalWaiter {
+    fn is_foreign(&self, state: *mut LuauState) -> bool {
+        unsafe { self.state.access() != state }
+    }
+    fn matches(&self, args: &LuaMultiValue) -> bool {
+        let Some(name) = &self.child_name else {
+            return true;
+        };
+        args.front()
+            .and_then(|x| x.as_userdata())
+            .and_then(|x| x.borrow::<ManagedInstance>().ok())
+            .is_some_and(|x| x.get_name() == *name)
+    }
+    /// Resumes the thread with `args`, which is deferred when the thread didn't wait in the current context.
+    fn resume(self, lua: &Lua, args: &LuaMultiValue, deferred: bool, signal: usize) -> LuaResult<()> {
+        let task = get_task_scheduler_from_lua(lua);
+        if !task.unpark(&self.thread, self.token) {
+            // Timed out, or cancelled.
+            return Ok(());
+        }
+        let desynchronized = self.parallel == ParallelDispatch::Desynchronized;
+        if desynchronized != task.is_desynchronized() {
+            task.defer_thread_with(lua, self.thread, args.clone(), self.parallel).map(|_| ())
+        } else if deferred {
+            task.defer_event(lua, self.thread, args.clone(), self.parallel, signal).map(|_| ())
+        } else {
+            let _ = task.resume_with(lua, self.thread, args.clone());
+            Ok(())
+        }
+    }
+    fn resume_foreign(self, args: &LuaMultiValue) -> LuaResult<()> {
+        let args = MarshalledValues::new(args)?;
+        let state = unsafe { self.state.access().as_ref().unwrap_unchecked() };
+        state.get_task_scheduler().defer_foreign_resume(self.thread, self.token, args, self.parallel);
+        Ok(())
+    }
 }
-
 impl RBXScriptSignal {
     pub fn new() -> Trc<RBXScriptSignal> {
-        Trc::new_cyclic(|x| RBXScriptSignal { callbacks: HashMap::default(), this_ptr: Some(x.clone()), id: 0 })
+        Trc::new_cyclic(|x| RBXScriptSignal { callbacks: Vec::new(), waiters: Vec::new(), this_ptr: Some(x.clone()), id: 0 })
     }
     pub fn connect(&mut self, lua: &Lua, func: LuaFunction, parallel: ParallelDispatch) -> LuaResult<RBXScriptConnection> {
         let id = self.id;
         self.id += 1;
-        self.callbacks.insert(id, SignalCallback {
+        self.callbacks.push((id, SignalCallback {
             func,
-            state: get_state_with_rwlock(lua).clone(),
+            state: get_state_with_rwlock(lua),
             once: false,
             parallel: parallel
-        });
+        }));
         Ok(RBXScriptConnection {
             id,
             signal: self.this_ptr.as_ref().unwrap().upgrade().unwrap()
@@ -59,119 +113,124 @@ impl RBXScriptSignal {
     pub fn once(&mut self, lua: &Lua, func: LuaFunction, parallel: ParallelDispatch) -> LuaResult<RBXScriptConnection> {
         let id = self.id;
         self.id += 1;
-        self.callbacks.insert(id, SignalCallback {
+        self.callbacks.push((id, SignalCallback {
             func,
-            state: get_state_with_rwlock(lua).clone(),
+            state: get_state_with_rwlock(lua),
             once: true,
             parallel: parallel
-        });
+        }));
         Ok(RBXScriptConnection {
             id,
             signal: self.this_ptr.as_ref().unwrap().upgrade().unwrap()
         })
     }
-    pub fn fire(mut self: TrcWriteLock<'_, RBXScriptSignal>, lua: &Lua, args: impl IntoLuaMulti) -> LuaResult<()> {
+    pub fn fire(self: TrcWriteLock<'_, RBXScriptSignal>, lua: &Lua, args: impl IntoLuaMulti) -> LuaResult<()> {
         let args = args.into_lua_multi(lua)?;
-        let mut to_remove = Vec::new();
-        let task = get_task_scheduler_from_lua(unsafe {(lua as *const Lua).as_ref().unwrap_unchecked()});
-        let callbacks_clone = self.callbacks.clone();
-        let release = self.guard_release();
-        let fire_behavior = get_state(lua).flags().get_int(FastFlag::SignalBehavior);
-        for (id, callback) in callbacks_clone {
-            let _ = match fire_behavior {
-                0 | 1 | 3 => task.spawn_func(lua, callback.func, args.clone()),
-                2 => task.defer_func(lua, callback.func, args.clone(), callback.parallel),
-                _ => unreachable!()
-            };
-            if callback.once {
-                to_remove.push(id);
-            }
-        }
-        drop(release);
-        if !to_remove.is_empty() {
-            for i in to_remove {
-                self.callbacks.remove(&i);
-            }
-        }
-        Ok(())
+        self.dispatch(lua, args, false)
     }
-    pub fn fire_ancestry(mut self: TrcWriteLock<'_, RBXScriptSignal>, lua: &Lua, args: impl IntoLuaMulti) -> LuaResult<()> {
+    /// Fires a signal reporting a change of parent, which is also deferred in AncestryDeferred mode.
+    pub fn fire_ancestry(self: TrcWriteLock<'_, RBXScriptSignal>, lua: &Lua, args: impl IntoLuaMulti) -> LuaResult<()> {
         let args = args.into_lua_multi(lua)?;
-        let mut to_remove = Vec::new();
+        self.dispatch(lua, args, true)
+    }
+    fn dispatch(mut self: TrcWriteLock<'_, RBXScriptSignal>, lua: &Lua, args: LuaMultiValue, ancestry: bool) -> LuaResult<()> {
         let task = get_task_scheduler_from_lua(unsafe {(lua as *const Lua).as_ref().unwrap_unchecked()});
-        let callbacks_clone = self.callbacks.clone();
+        let deferred = SignalBehavior::from_value(get_state(lua).flags().get_int(FastFlag::SignalBehavior)).is_deferred(ancestry);
+        let signal = &raw const *self as usize;
+        let this = self.this_ptr.as_ref().unwrap().upgrade().unwrap();
+        let mut callbacks_clone = self.callbacks.clone();
+        // Immediate handlers run in reverse connection order, like Roblox.
+        if !deferred {
+            callbacks_clone.reverse();
+        }
+        let (waiters, waiting) = take(&mut self.waiters).into_iter().partition::<Vec<_>, _>(|x| x.matches(&args));
+        self.waiters = waiting;
         let release = self.guard_release();
-        let fire_behavior = get_state(lua).flags().get_int(FastFlag::SignalBehavior);
+        let state = &raw mut *get_state(lua);
+        let mut result = Ok(());
         for (id, callback) in callbacks_clone {
-            let _ = match fire_behavior {
-                0 | 1 => task.spawn_func(lua, callback.func, args.clone()),
-                2 | 3 => task.defer_func(lua, callback.func, args.clone(), callback.parallel),
-                _ => unreachable!()
-            };
-            if callback.once {
-                to_remove.push(id);
+            {
+                let mut signal = this.write();
+                // A handler can disconnect the ones which didn't run yet.
+                let Ok(i) = signal.callbacks.binary_search_by_key(&id, |(id, _)| *id) else {
+                    continue;
+                };
+                // Once connections are disconnected before their handler runs, a handler firing the signal again can't call them twice.
+                if callback.once {
+                    signal.callbacks.remove(i);
+                }
+            }
+            if callback.is_foreign(state) {
+                // Arguments which can't be passed to another Actor only skip its handler, the other ones still run.
+                if let Err(err) = callback.defer_foreign(&args) {
+                    get_state(lua).get_vm().log(lua, HostMessageKind::Error, format!("Could not pass the arguments to a handler in another Actor: {err}"));
+                }
+            } else if deferred {
+                result = result.and(lua.create_thread(callback.func)
+                    .and_then(|thread| task.defer_event(lua, thread, args.clone(), callback.parallel, signal))
+                    .map(|_| ()));
+            } else {
+                let _ = task.spawn_func(lua, callback.func, args.clone());
             }
         }
-        drop(release);
-        if !to_remove.is_empty() {
-            for i in to_remove {
-                self.callbacks.remove(&i);
+        for waiter in waiters {
+            if waiter.is_foreign(state) {
+                result = result.and(waiter.resume_foreign(&args));
+            } else {
+                result = result.and(waiter.resume(lua, &args, deferred, signal));
             }
         }
+        drop(release);
+        result
+    }
+    /// Whether a handler is connected, or a thread waits for the signal.
+    pub fn has_listeners(&self) -> bool {
+        !self.callbacks.is_empty() || !self.waiters.is_empty()
+    }
+    fn is_connected(&self, id: usize) -> bool {
+        self.callbacks.binary_search_by_key(&id, |(id, _)| *id).is_ok()
+    }
+    /// Parks the current thread until the signal fires, or until `timeout` seconds passed.
+    /// The thread must yield right after, it is resumed with the fired values, or with nil once it timed out.
+    /// With `child_name`, only a fire whose first value is an instance of that name resumes it.
+    pub fn park(&mut self, lua: &Lua, child_name: Option<String>, timeout: Option<f64>) {
+        let task = get_task_scheduler_from_lua(lua);
+        let state = &raw mut *get_state(lua);
+        // Waiters which timed out are only dropped here and by fire.
+        self.waiters.retain(|x| x.is_foreign(state) || task.is_parked(&x.thread, x.token));
+        let token = task.park(lua, timeout);
+        self.waiters.push(SignalWaiter {
+            thread: lua.current_thread(),
+            state: get_state_with_rwlock(lua),
+            token,
+            parallel: if task.is_desynchronized() { ParallelDispatch::Desynchronized } else { ParallelDispatch::Synchronized },
+            child_name
+        });
+    }
+    fn push_wait(lua: &Lua, this: ManagedRBXScriptSignal) -> LuaResult<()> {
+        this.write().park(lua, None, None);
         Ok(())
     }
-    pub fn wait<'a>(self: TrcReadLock<'_, Self>, lua: &'a Lua) -> RBXScriptSignalFuture {
-        RBXScriptSignalFuture {
-            future: Rc::new(RefCell::new(InnerRBXScriptSignalFuture {
-                event: self.this_ptr.as_ref().unwrap().upgrade().unwrap(),
-                lua: lua.clone(),
-                resolved: false,
-                waiting: false,
-                values: LuaMultiValue::new()
-            }))
-        }
+    unsafe extern "C-unwind" fn wait(state: *mut lua_State) -> c_int {
+        ffi::lua_settop(state, 1);
+        registry_keys::push_value(state, registry_keys::SIGNAL_PUSH_WAIT);
+        ffi::lua_insert(state, 1);
+        ffi::lua_call(state, 1, 0);
+        ffi::lua_yield(state, 0)
     }
 }
 impl RBXScriptConnection {
     pub fn is_connected(&self) -> bool {
-        self.signal.read().callbacks.contains_key(&self.id)
+        self.signal.read().is_connected(self.id)
     }
     pub fn disconnect(&self) {
         let mut lock = self.signal.write();
-        lock.callbacks.remove(&self.id);
-    }
-}
-
-impl Future for RBXScriptSignalFuture {
-    type Output = LuaResult<LuaMultiValue>;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let immut_borrow = self.future.borrow();
-        if immut_borrow.resolved {
-            drop(immut_borrow);
-            let mut borrow = self.future.borrow_mut();
-            borrow.resolved = false;
-            borrow.waiting = false;
-            let values = take(&mut borrow.values);
-            Poll::Ready(Ok(values))
-        } else {
-            if !immut_borrow.waiting {
-                let waker = cx.waker().clone();
-                let clone = self.future.clone();
-                let func = immut_borrow.lua.create_function_mut(move |_, mv: LuaMultiValue| {
-                    let mut borrow = clone.borrow_mut();
-                    borrow.resolved = true;
-                    borrow.waiting = false;
-                    borrow.values = mv;
-                    waker.clone().wake();
-                    Ok(())
-                })?;
-                immut_borrow.event.write().once(&immut_borrow.lua, func, ParallelDispatch::Default)?;
-            }
-            Poll::Pending
+        if let Ok(i) = lock.callbacks.binary_search_by_key(&self.id, |(id, _)| *id) {
+            lock.callbacks.remove(i);
         }
     }
 }
+
 impl LuaUserData for ManagedRBXScriptSignal {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         methods.add_method_mut("Connect", |lua, this, func: LuaFunction| {
@@ -183,11 +242,19 @@ impl LuaUserData for ManagedRBXScriptSignal {
         methods.add_method_mut("Once", |lua, this, func: LuaFunction| {
             this.write().once(lua, func, ParallelDispatch::Synchronized)
         });
-        methods.add_async_method_mut("Wait", async |lua, this, ()| {
-            this.read().wait(&lua).await
+    }
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_function_get("Wait", |lua, _| {
+            lua.named_registry_value::<LuaFunction>(registry_keys::SIGNAL_WAIT)
         });
     }
 }
+impl LuaSingleton for ManagedRBXScriptSignal {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        lua.set_named_registry_value(registry_keys::SIGNAL_WAIT, unsafe { lua.create_c_function(RBXScriptSignal::wait)? })?;
+        lua.set_named_registry_value(registry_keys::SIGNAL_PUSH_WAIT, lua.create_function(RBXScriptSignal::push_wait)?)
+    }
+}
 impl LuaUserData for RBXScriptConnection {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         methods.add_method_mut("Disconnect", 
@@ -0,0 +1,73 @@
+use r2g_mlua::prelude::*;
+
+use super::LuaSingleton;
+use super::enums::{FontStyle, FontWeight};
+
+/// The [`Font`](https://create.roblox.com/docs/reference/engine/datatypes/Font) data type describes a font face, a font family along with its weight and style.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Font {
+    pub family: String,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+    /// The content id of the face the family resolved to when it was last loaded, kept as is through serialization.
+    pub cached_face_id: String
+}
+
+from_lua_clone_impl!(Font);
+
+impl Font {
+    pub fn new(family: String, weight: FontWeight, style: FontStyle) -> Font {
+        Font { family, weight, style, cached_face_id: String::new() }
+    }
+    /// Creates a `Font` from the name of a font family bundled with the engine.
+    pub fn from_name(name: &str, weight: FontWeight, style: FontStyle) -> Font {
+        Font::new(format!("rbxasset://fonts/families/{}.json", name), weight, style)
+    }
+    /// Creates a `Font` from the asset id of a font family.
+    pub fn from_id(id: u64, weight: FontWeight, style: FontStyle) -> Font {
+        Font::new(format!("rbxassetid://{}", id), weight, style)
+    }
+    pub fn bold(&self) -> bool {
+        self.weight >= FontWeight::SemiBold
+    }
+}
+
+impl LuaUserData for Font {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Family", |_, this| Ok(this.family.clone()));
+        fields.add_field_method_get("Weight", |_, this| Ok(this.weight));
+        fields.add_field_method_get("Style", |_, this| Ok(this.style));
+        fields.add_field_method_get("Bold", |_, this| Ok(this.bold()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()|
+            Ok(format!("Font {{ Family = {}, Weight = {:?}, Style = {:?} }}", this.family, this.weight, this.style))
+        );
+        methods.add_meta_method("__eq", |_, this, other: Font| Ok(*this == other));
+    }
+}
+impl LuaSingleton for Font {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (family, weight, style): (String, Option<FontWeight>, Option<FontStyle>)| {
+                Ok(Font::new(family, weight.unwrap_or_default(), style.unwrap_or_default()))
+            })?
+        )?;
+        table.raw_set(
+            "fromName",
+            lua.create_function(|_, (name, weight, style): (String, Option<FontWeight>, Option<FontStyle>)| {
+                Ok(Font::from_name(&name, weight.unwrap_or_default(), style.unwrap_or_default()))
+            })?
+        )?;
+        table.raw_set(
+            "fromId",
+            lua.create_function(|_, (id, weight, style): (u64, Option<FontWeight>, Option<FontStyle>)| {
+                Ok(Font::from_id(id, weight.unwrap_or_default(), style.unwrap_or_default()))
+            })?
+        )?;
+        lua.globals().raw_set("Font", table)?;
+        Ok(())
+    }
+}
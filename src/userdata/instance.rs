@@ -1,44 +1,231 @@
-use r2g_mlua::prelude::*;
+use std::ffi::c_int;
+use std::ptr::null_mut;
 
-use crate::{core::{get_state, lua_macros::lua_getter}, instance::{Actor, DynInstance, LocalScript, ManagedInstance, Model, Script}};
+use r2g_mlua::{ffi::{self, lua_State}, prelude::*};
+
+use crate::{core::{get_current_identity, get_task_scheduler_from_lua, registry_keys, MemberDescriptor, SecurityContext, SecurityLevel, ThreadSafety, CLASS_DATABASE}, instance::{DynInstance, InstanceClass, InstanceComponent, ManagedInstance}};
 
 use super::LuaSingleton;
 
-impl LuaUserData for ManagedInstance {
-    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
-        methods.add_meta_method("__index", |lua, this, field: String| {
-            this.lua_get(lua, field)
-        });
-        methods.add_meta_method("__newindex", |lua, this, (field, val): (String, LuaValue)| {
-            this.lua_set(lua, field, val)
-        });
-        methods.add_meta_method("__tostring", |_, this: &ManagedInstance, ()| {
-            let instance_read = this.get_instance_component();
-            Ok(format!("{} {}: replication 0x{:x}", 
-                this.get_class_name(), 
-                DynInstance::guard_get_name(&instance_read), 
-                DynInstance::guard_get_uniqueid(&instance_read)))
-        });
+fn add_methods<M: LuaUserDataMethods<ManagedInstance>>(methods: &mut M) {
+    methods.add_meta_method("__index", |lua, this, field: String| {
+        get_member(lua, this, field)
+    });
+    methods.add_meta_method("__newindex", |lua, this, (field, val): (String, LuaValue)| {
+        if get_task_scheduler_from_lua(lua).is_desynchronized() {
+            return Err(LuaError::RuntimeError(format!("Property {}.{} is not safe to write in parallel", this.get_class_name(), field)));
+        }
+        if check_member_security(lua, this, &field, true)?.is_none() {
+            return Err(InstanceComponent::member_error(&this.get_instance_component(), &field));
+        }
+        this.lua_set(lua, field, val)
+    });
+    methods.add_meta_method("__tostring", |_, this: &ManagedInstance, ()| {
+        let instance_read = this.get_instance_component();
+        Ok(format!("{} {}: replication 0x{:x}", 
+            this.get_class_name(), 
+            DynInstance::guard_get_name(&instance_read), 
+            DynInstance::guard_get_uniqueid(&instance_read)))
+    });
+    // Fallback, pushing an instance to a state always gives the same userdata.
+    methods.add_meta_function("__eq", |_, (a, b): (ManagedInstance, ManagedInstance)| {
+        Ok(a == b)
+    });
+}
+
+/// The methods of `class_name` created so far in this state.
+fn get_class_methods(lua: &Lua, class_name: &'static str) -> LuaResult<LuaTable> {
+    let classes: LuaTable = lua.named_registry_value(registry_keys::INSTANCE_METHODS)?;
+    match classes.raw_get::<Option<LuaTable>>(class_name)? {
+        Some(methods) => Ok(methods),
+        None => {
+            let methods = lua.create_table()?;
+            classes.raw_set(class_name, &methods)?;
+            Ok(methods)
+        }
+    }
+}
+/// Checks that the current thread can read, or write, the member `name` of `this`, with the security the class database
+/// declares for it. Every access to an instance member from Lua goes through here. Returns the security of the member,
+/// or `None` when `name` isn't a member the database declares, which can then only index a child.
+fn check_member_security(lua: &Lua, this: &ManagedInstance, name: &str, write: bool) -> LuaResult<Option<SecurityLevel>> {
+    let Some(security) = CLASS_DATABASE.get_member_security(this.get_class_name(), name, write) else {
+        return Ok(None);
+    };
+    if security == SecurityLevel::None {
+        return Ok(Some(security));
+    }
+    let contexts = get_current_identity(lua)
+        .map(|x| x.security_identity.get_security_contexts())
+        .unwrap_or(SecurityContext::NONE);
+    if !security.is_accessible_with(contexts) {
+        return Err(LuaError::RuntimeError(format!("The current thread cannot access '{}' (lacking capability {})", name, security.get_capability_name())));
+    }
+    Ok(Some(security))
+}
+/// Indexes `this`, reusing the methods of its class. Every function `lua_get` returns is a method,
+/// properties never hold functions and methods only depend on their arguments, so they are created once per state.
+/// Methods needing a security are not reused, __namecall calls the ones which were without checking it.
+/// Methods which aren't Safe are wrapped to error when called while desynchronized, unsafe properties can't be read then.
+fn get_member(lua: &Lua, this: &ManagedInstance, name: String) -> LuaResult<LuaValue> {
+    let Some(security) = check_member_security(lua, this, &name, false)? else {
+        return InstanceComponent::lua_get_child(&mut this.get_instance_component(), lua, &name);
+    };
+    let methods = get_class_methods(lua, this.get_class_name())?;
+    if let Some(method) = methods.raw_get::<Option<LuaFunction>>(name.as_str())? {
+        return Ok(LuaValue::Function(method));
+    }
+    let member = CLASS_DATABASE.find_member(this.get_class_name(), &name);
+    if let Some(MemberDescriptor::Property(property)) = member {
+        if property.thread_safety == ThreadSafety::Unsafe && get_task_scheduler_from_lua(lua).is_desynchronized() {
+            return Err(LuaError::RuntimeError(format!("Property {}.{} is not safe to read in parallel", this.get_class_name(), name)));
+        }
+    }
+    let mut value = this.lua_get(lua, name.clone())?;
+    if let LuaValue::Function(method) = &value {
+        if !matches!(member, Some(MemberDescriptor::Function(x)) if x.thread_safety == ThreadSafety::Safe) {
+            let guard: LuaFunction = lua.named_registry_value(registry_keys::INSTANCE_PARALLEL_GUARD)?;
+            let message = format!("Function {}.{} is not safe to call in parallel", this.get_class_name(), name);
+            value = guard.call((method, message))?;
+        }
+    }
+    if let LuaValue::Function(method) = &value {
+        if security == SecurityLevel::None {
+            methods.raw_set(name, method)?;
+        }
+    }
+    Ok(value)
+}
+
+/// Returns a method which errors when called while desynchronized. It is a Lua function, so yielding methods still can.
+const PARALLEL_GUARD: &str = r#"
+local is_desynchronized = ...
+return function(method, message)
+    return function(...)
+        if is_desynchronized() then
+            error(message, 0)
+        end
+        return method(...)
+    end
+end
+"#;
+
+/// __namecall, `instance:Method(...)`. Calling the method from Lua instead of with `lua_call` lets methods yield.
+/// The method is looked up before anything else can start another namecall and replace its name.
+const NAMECALL: &str = r#"
+local find_method = ...
+return function(self, ...)
+    return find_method(self)(self, ...)
+end
+"#;
+
+/// Finds the method the current __namecall calls on the instance, without indexing the instance.
+unsafe extern "C-unwind" fn find_namecall_method(state: *mut lua_State) -> c_int {
+    let name = ffi::lua_namecallatom(state, null_mut());
+    if name.is_null() {
+        ffi::luaL_error(state, c"__namecall called without a method name".as_ptr());
+    }
+    ffi::lua_settop(state, 1);
+    // Methods already created are found without calling into Rust.
+    registry_keys::push_value(state, registry_keys::INSTANCE_CLASS_METHODS);
+    ffi::lua_pushvalue(state, 1);
+    ffi::lua_rawget(state, -2);
+    if ffi::lua_istable(state, -1) != 0 {
+        ffi::lua_pushstring(state, name);
+        ffi::lua_rawget(state, -2);
+        if ffi::lua_isfunction(state, -1) != 0 {
+            return 1;
+        }
+    }
+    ffi::lua_settop(state, 1);
+    registry_keys::push_value(state, registry_keys::INSTANCE_GET_MEMBER);
+    ffi::lua_insert(state, 1);
+    ffi::lua_pushstring(state, name);
+    ffi::lua_call(state, 2, 1);
+    1
+}
+
+impl IntoLua for ManagedInstance {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        // Instances are keyed by address, which can't be reused while their userdata is alive.
+        let cache: LuaTable = lua.named_registry_value(registry_keys::INSTANCE_USERDATA_CACHE)?;
+        let key = LuaLightUserData((&raw const *self).cast_mut().cast());
+        if let LuaValue::UserData(ud) = cache.raw_get(key)? {
+            return Ok(LuaValue::UserData(ud));
+        }
+        let class_methods = get_class_methods(lua, self.get_class_name())?;
+        let ud = lua.create_any_userdata(self)?;
+        cache.raw_set(key, &ud)?;
+        lua.named_registry_value::<LuaTable>(registry_keys::INSTANCE_CLASS_METHODS)?.raw_set(&ud, class_methods)?;
+        Ok(LuaValue::UserData(ud))
     }
 }
 
 impl LuaSingleton for ManagedInstance {
     fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        lua.set_named_registry_value(registry_keys::INSTANCE_FIND_NAMECALL_METHOD, unsafe { lua.create_c_function(find_namecall_method)? })?;
+        lua.register_userdata_type::<ManagedInstance>(|registry| {
+            add_methods(registry);
+            registry.add_meta_field_with("__namecall", |lua| {
+                lua.named_registry_value::<LuaFunction>(registry_keys::INSTANCE_FIND_NAMECALL_METHOD)
+                    .and_then(|find_method| lua.load(NAMECALL).set_name("=namecall").call::<LuaFunction>(find_method))
+            });
+        })?;
+        lua.set_named_registry_value(registry_keys::INSTANCE_METHODS, lua.create_table()?)?;
+        // The methods of the class of each instance userdata, for __namecall.
+        let class_methods = lua.create_table()?;
+        class_methods.set_metatable(Some(lua.create_table_from([("__mode", "k")])?));
+        lua.set_named_registry_value(registry_keys::INSTANCE_CLASS_METHODS, class_methods)?;
+        let is_desynchronized = lua.create_function(|lua, ()| Ok(get_task_scheduler_from_lua(lua).is_desynchronized()))?;
+        let guard: LuaFunction = lua.load(PARALLEL_GUARD).set_name("=parallel_guard").call(is_desynchronized)?;
+        lua.set_named_registry_value(registry_keys::INSTANCE_PARALLEL_GUARD, guard)?;
+        lua.set_named_registry_value(registry_keys::INSTANCE_GET_MEMBER, lua.create_function(
+            |lua, (this, name): (ManagedInstance, String)| get_member(lua, &this, name)
+        )?)?;
+        // Weak cache of the userdata of each instance, so the same instance is always rawequal to itself and works as a table key.
+        let cache = lua.create_table()?;
+        cache.set_metatable(Some(lua.create_table_from([("__mode", "v")])?));
+        lua.set_named_registry_value(registry_keys::INSTANCE_USERDATA_CACHE, cache)?;
         let table = lua.create_table()?;
-        table.raw_set("new", lua.create_function(|lua, (class_name,): (String,)| {
-            match class_name.as_str() {
-                "Model" => lua_getter!(lua, Model::new()),
-                "Actor" => lua_getter!(lua, Actor::new(get_state(lua).get_vm_mut())),
-                "Script" => lua_getter!(lua, Script::new()),
-                "LocalScript" => lua_getter!(lua, LocalScript::new()),
-                _ => Err::<LuaValue, LuaError>(LuaError::RuntimeError(format!("invalid class name \"{}\"", class_name)))
+        table.raw_set("new", lua.create_function(|lua, (class_name, parent): (String, Option<ManagedInstance>)| {
+            let instance = InstanceClass::find_creatable(&class_name)?.create(lua)?;
+            if parent.is_some() {
+                instance.set_parent(lua, parent)?;
             }
+            Ok(instance)
+        })?)?;
+        table.raw_set("fromExisting", lua.create_function(|lua, (existing,): (ManagedInstance,)| {
+            existing.from_existing(lua)
         })?)?;
         lua.globals().raw_set("Instance", table)?;
+        lua.set_named_registry_value(registry_keys::INSTANCE_WAIT_FOR_CHILD, unsafe { lua.create_c_function(wait_for_child)? })?;
+        lua.set_named_registry_value(registry_keys::INSTANCE_PUSH_WAIT_FOR_CHILD, lua.create_function(
+            |lua, (this, name, timeout): (ManagedInstance, String, Option<LuaNumber>)| {
+                if get_task_scheduler_from_lua(lua).is_desynchronized() {
+                    return Err(LuaError::RuntimeError(format!("Function {}.WaitForChild is not safe to call in parallel", this.get_class_name())));
+                }
+                this.wait_for_child(lua, name, timeout)
+            }
+        )?)?;
         Ok(())
     }
 }
 
+/// Instance:WaitForChild, which returns the child right away if it exists and otherwise yields until
+/// ChildAdded resumes the thread with it, or until the timeout resumes it with nil.
+unsafe extern "C-unwind" fn wait_for_child(state: *mut lua_State) -> c_int {
+    ffi::lua_settop(state, 3);
+    registry_keys::push_value(state, registry_keys::INSTANCE_PUSH_WAIT_FOR_CHILD);
+    ffi::lua_insert(state, 1);
+    ffi::lua_call(state, 3, 1);
+    if ffi::lua_isnil(state, -1) == 0 {
+        1
+    } else {
+        ffi::lua_settop(state, 0);
+        ffi::lua_yield(state, 0)
+    }
+}
+
 impl FromLua for ManagedInstance {
     fn from_lua(value:LuaValue,_lua: &Lua) -> LuaResult<Self>{
         let ud = value.as_userdata();
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+
+use r2g_mlua::prelude::*;
+
+use crate::instance::ManagedInstance;
+use super::enums::*;
+use super::{
+    Axes, BrickColor, CFrame, Color3, ColorSequence, ColorSequenceKeypoint, Font, ManagedRBXScriptSignal, NumberRange,
+    NumberSequence, NumberSequenceKeypoint, RBXScriptConnection, Rect, UDim, UDim2, Vector2, Vector2int16, Vector3,
+    Vector3int16
+};
+
+macro_rules! marshalled_userdata {
+    ($($name: ident),*) => {
+        /// Datatypes which are copied, and objects which are shared, between Lua VMs.
+        #[derive(Clone, Debug)]
+        enum MarshalledUserData {
+            $($name($name)),*
+        }
+        impl MarshalledUserData {
+            fn from_userdata(ud: &LuaAnyUserData) -> Option<MarshalledUserData> {
+                $(
+                    if let Ok(x) = ud.borrow::<$name>() {
+                        return Some(MarshalledUserData::$name(x.clone()));
+                    }
+                )*
+                None
+            }
+            fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+                match self {
+                    $(MarshalledUserData::$name(x) => x.into_lua(lua)),*
+                }
+            }
+        }
+    };
+}
+
+with_enums!(
+    marshalled_userdata,
+    ManagedInstance, ManagedRBXScriptSignal, RBXScriptConnection,
+    Axes, CFrame, Vector2, Vector2int16, Vector3, Vector3int16,
+    UDim, UDim2, Rect, Color3, BrickColor, NumberRange, NumberSequence, NumberSequenceKeypoint,
+    ColorSequence, ColorSequenceKeypoint, Font
+);
+
+#[derive(Clone, Debug)]
+enum MarshalledValue {
+    Nil,
+    Boolean(bool),
+    Integer(LuaInteger),
+    Number(LuaNumber),
+    Vector(LuaVector),
+    String(Vec<u8>),
+    Buffer(Vec<u8>),
+    /// Index into [`MarshalledValues::tables`].
+    Table(usize),
+    UserData(MarshalledUserData)
+}
+
+/// Values copied out of one Lua VM, to be recreated in another one, e.g. the arguments of a signal fired in a different Actor.
+/// Tables are deep-copied without their metatable, keeping the tables they share and their cycles.
+/// Functions, threads and userdata which isn't a datatype can't leave their VM.
+#[derive(Clone, Debug, Default)]
+pub struct MarshalledValues {
+    values: Vec<MarshalledValue>,
+    tables: Vec<Vec<(MarshalledValue, MarshalledValue)>>
+}
+
+impl MarshalledValues {
+    pub fn new(values: &LuaMultiValue) -> LuaResult<MarshalledValues> {
+        let mut marshalled = MarshalledValues::default();
+        let mut visited = HashMap::new();
+        for value in values.iter() {
+            let value = marshalled.marshal(value, &mut visited)?;
+            marshalled.values.push(value);
+        }
+        Ok(marshalled)
+    }
+    fn marshal(&mut self, value: &LuaValue, visited: &mut HashMap<*const c_void, usize>) -> LuaResult<MarshalledValue> {
+        Ok(match value {
+            LuaValue::Nil => MarshalledValue::Nil,
+            LuaValue::Boolean(x) => MarshalledValue::Boolean(*x),
+            LuaValue::Integer(x) => MarshalledValue::Integer(*x),
+            LuaValue::Number(x) => MarshalledValue::Number(*x),
+            LuaValue::Vector(x) => MarshalledValue::Vector(*x),
+            LuaValue::String(x) => MarshalledValue::String(x.as_bytes().to_vec()),
+            LuaValue::Buffer(x) => MarshalledValue::Buffer(x.to_vec()),
+            LuaValue::Table(table) => {
+                if let Some(index) = visited.get(&table.to_pointer()) {
+                    return Ok(MarshalledValue::Table(*index));
+                }
+                let index = self.tables.len();
+                visited.insert(table.to_pointer(), index);
+                self.tables.push(Vec::new());
+                let mut entries = Vec::new();
+                for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+                    let (k, v) = pair?;
+                    entries.push((self.marshal(&k, visited)?, self.marshal(&v, visited)?));
+                }
+                self.tables[index] = entries;
+                MarshalledValue::Table(index)
+            },
+            LuaValue::UserData(ud) => MarshalledValue::UserData(
+                MarshalledUserData::from_userdata(ud)
+                    .ok_or_else(|| LuaError::RuntimeError("userdata cannot be passed between Actors".into()))?
+            ),
+            _ => return Err(LuaError::RuntimeError(format!("{} cannot be passed between Actors", value.type_name())))
+        })
+    }
+    /// Recreates the values in `lua`.
+    pub fn into_lua_multi(self, lua: &Lua) -> LuaResult<LuaMultiValue> {
+        // Tables are created first, since any of them can reference any other.
+        let tables = self.tables.iter()
+            .map(|_| lua.create_table())
+            .collect::<LuaResult<Vec<LuaTable>>>()?;
+        let unmarshal = |value: MarshalledValue| -> LuaResult<LuaValue> {
+            Ok(match value {
+                MarshalledValue::Nil => LuaValue::Nil,
+                MarshalledValue::Boolean(x) => LuaValue::Boolean(x),
+                MarshalledValue::Integer(x) => LuaValue::Integer(x),
+                MarshalledValue::Number(x) => LuaValue::Number(x),
+                MarshalledValue::Vector(x) => LuaValue::Vector(x),
+                MarshalledValue::String(x) => LuaValue::String(lua.create_string(x)?),
+                MarshalledValue::Buffer(x) => LuaValue::Buffer(lua.create_buffer(x)?),
+                MarshalledValue::Table(x) => LuaValue::Table(tables[x].clone()),
+                MarshalledValue::UserData(x) => x.into_lua(lua)?
+            })
+        };
+        for (table, entries) in tables.iter().zip(self.tables) {
+            for (k, v) in entries {
+                table.raw_set(unmarshal(k)?, unmarshal(v)?)?;
+            }
+        }
+        self.values.into_iter().map(unmarshal).collect()
+    }
+}
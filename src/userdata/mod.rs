@@ -51,6 +51,15 @@ mod vectors;
 pub mod enums;
 mod cframe;
 mod instance;
+mod marshal;
+mod attribute;
+mod udim;
+mod color3;
+mod brick_color;
+mod number_range;
+mod rect;
+mod sequences;
+mod font;
 
 pub use axes::Axes;
 pub use vectors::{Vector2int16, Vector3int16};
@@ -58,6 +67,15 @@ pub type Vector2 = vectors::Vector2<f64>;
 pub type Vector3 = vectors::Vector3<f64>;
 pub use events::{ManagedRBXScriptSignal, RBXScriptConnection, RBXScriptSignal};
 pub use cframe::CFrame;
+pub use udim::{UDim, UDim2};
+pub use color3::Color3;
+pub use brick_color::BrickColor;
+pub use number_range::NumberRange;
+pub use rect::Rect;
+pub use sequences::{NumberSequence, NumberSequenceKeypoint, ColorSequence, ColorSequenceKeypoint};
+pub use font::Font;
+pub use marshal::MarshalledValues;
+pub use attribute::{AttributeValue, AttributeEnumItem, serialize_attributes, deserialize_attributes};
 
 use crate::instance::ManagedInstance;
 
@@ -70,7 +88,23 @@ pub fn register_userdata_singletons(lua: &mut Lua) -> LuaResult<()> {
     Vector3::register_singleton(lua)?;
     Vector3int16::register_singleton(lua)?;
 
+    UDim::register_singleton(lua)?;
+    UDim2::register_singleton(lua)?;
+    Rect::register_singleton(lua)?;
+
+    Color3::register_singleton(lua)?;
+    BrickColor::register_singleton(lua)?;
+    NumberRange::register_singleton(lua)?;
+    NumberSequence::register_singleton(lua)?;
+    NumberSequenceKeypoint::register_singleton(lua)?;
+    ColorSequence::register_singleton(lua)?;
+    ColorSequenceKeypoint::register_singleton(lua)?;
+    Font::register_singleton(lua)?;
+
     ManagedInstance::register_singleton(lua)?;
+    ManagedRBXScriptSignal::register_singleton(lua)?;
+
+    enums::register_enums(lua)?;
 
     Ok(())
 }
\ No newline at end of file
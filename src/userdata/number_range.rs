@@ -0,0 +1,43 @@
+use r2g_mlua::prelude::*;
+
+use super::LuaSingleton;
+
+/// The [`NumberRange`](https://create.roblox.com/docs/reference/engine/datatypes/NumberRange) data type represents a range of numbers.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct NumberRange {
+    pub min: f64,
+    pub max: f64
+}
+
+from_lua_copy_impl!(NumberRange);
+
+impl NumberRange {
+    pub fn new(min: f64, max: f64) -> LuaResult<NumberRange> {
+        if max < min {
+            return Err(LuaError::RuntimeError("NumberRange: invalid range".into()));
+        }
+        Ok(NumberRange { min, max })
+    }
+}
+
+impl LuaUserData for NumberRange {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Min", |_, this| Ok(this.min));
+        fields.add_field_method_get("Max", |_, this| Ok(this.max));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| Ok(format!("({}, {})", this.min, this.max)));
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for NumberRange {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (min, max): (f64, Option<f64>)| NumberRange::new(min, max.unwrap_or(min)))?
+        )?;
+        lua.globals().raw_set("NumberRange", table)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use r2g_mlua::prelude::*;
+
+use super::{LuaSingleton, Vector2};
+
+/// The [`Rect`](https://create.roblox.com/docs/reference/engine/datatypes/Rect) data type describes a rectangle from its top-left and bottom-right corners.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Rect {
+    pub min: Vector2,
+    pub max: Vector2
+}
+
+from_lua_copy_impl!(Rect);
+
+impl Rect {
+    pub const fn new(min: Vector2, max: Vector2) -> Rect {
+        Rect { min, max }
+    }
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+}
+
+impl LuaUserData for Rect {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Min", |_, this| Ok(this.min));
+        fields.add_field_method_get("Max", |_, this| Ok(this.max));
+        fields.add_field_method_get("Width", |_, this| Ok(this.width()));
+        fields.add_field_method_get("Height", |_, this| Ok(this.height()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()|
+            Ok(format!("({}, {}, {}, {})", this.min.x, this.min.y, this.max.x, this.max.y))
+        );
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for Rect {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|lua, args: LuaMultiValue| {
+                // Rect.new(min, max) with two Vector2s, or Rect.new(minX, minY, maxX, maxY).
+                if let Ok((min, max)) = <(Vector2, Vector2)>::from_lua_multi(args.clone(), lua) {
+                    return Ok(Rect::new(min, max));
+                }
+                let (min_x, min_y, max_x, max_y) = <(Option<f64>, Option<f64>, Option<f64>, Option<f64>)>::from_lua_multi(args, lua)?;
+                Ok(Rect::new(
+                    Vector2::new(min_x.unwrap_or_default(), min_y.unwrap_or_default()),
+                    Vector2::new(max_x.unwrap_or_default(), max_y.unwrap_or_default())
+                ))
+            })?
+        )?;
+        lua.globals().raw_set("Rect", table)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use r2g_mlua::prelude::*;
+
+use super::{Color3, LuaSingleton};
+
+const MAX_KEYPOINTS: usize = 20;
+
+/// The [`NumberSequenceKeypoint`](https://create.roblox.com/docs/reference/engine/datatypes/NumberSequenceKeypoint) data type represents a keypoint of a [`NumberSequence`], a value at a time along with the envelope of its random variance.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct NumberSequenceKeypoint {
+    pub time: f64,
+    pub value: f64,
+    pub envelope: f64
+}
+/// The [`NumberSequence`](https://create.roblox.com/docs/reference/engine/datatypes/NumberSequence) data type represents a series of number values from 0 to 1, with keypoints at each end.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NumberSequence {
+    keypoints: Vec<NumberSequenceKeypoint>
+}
+/// The [`ColorSequenceKeypoint`](https://create.roblox.com/docs/reference/engine/datatypes/ColorSequenceKeypoint) data type represents a keypoint of a [`ColorSequence`], a color at a time.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ColorSequenceKeypoint {
+    pub time: f64,
+    pub value: Color3
+}
+/// The [`ColorSequence`](https://create.roblox.com/docs/reference/engine/datatypes/ColorSequence) data type represents a gradient of color values from 0 to 1, with keypoints at each end.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColorSequence {
+    keypoints: Vec<ColorSequenceKeypoint>
+}
+
+from_lua_copy_impl!(NumberSequenceKeypoint);
+from_lua_clone_impl!(NumberSequence);
+from_lua_copy_impl!(ColorSequenceKeypoint);
+from_lua_clone_impl!(ColorSequence);
+
+/// Sequences have between 2 and 20 keypoints in the order of their times, which go from 0 to 1.
+fn check_keypoint_times(type_name: &str, times: &[f64]) -> LuaResult<()> {
+    if times.len() < 2 {
+        return Err(LuaError::RuntimeError(format!("{}: requires at least 2 keypoints", type_name)));
+    }
+    if times.len() > MAX_KEYPOINTS {
+        return Err(LuaError::RuntimeError(format!("{}: table is too long.", type_name)));
+    }
+    if times.windows(2).any(|x| x[1] < x[0]) {
+        return Err(LuaError::RuntimeError(format!("{}: all keypoints must be ordered by time", type_name)));
+    }
+    if times[0] != 0.0 || times[times.len() - 1] != 1.0 {
+        return Err(LuaError::RuntimeError(format!("{} must start at time 0 and end at time 1", type_name)));
+    }
+    Ok(())
+}
+
+impl NumberSequence {
+    pub fn new(keypoints: Vec<NumberSequenceKeypoint>) -> LuaResult<NumberSequence> {
+        check_keypoint_times("NumberSequence", &keypoints.iter().map(|x| x.time).collect::<Vec<_>>())?;
+        Ok(NumberSequence { keypoints })
+    }
+    pub fn keypoints(&self) -> &[NumberSequenceKeypoint] {
+        &self.keypoints
+    }
+}
+impl ColorSequence {
+    pub fn new(keypoints: Vec<ColorSequenceKeypoint>) -> LuaResult<ColorSequence> {
+        check_keypoint_times("ColorSequence", &keypoints.iter().map(|x| x.time).collect::<Vec<_>>())?;
+        Ok(ColorSequence { keypoints })
+    }
+    pub fn keypoints(&self) -> &[ColorSequenceKeypoint] {
+        &self.keypoints
+    }
+}
+
+impl LuaUserData for NumberSequenceKeypoint {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Time", |_, this| Ok(this.time));
+        fields.add_field_method_get("Value", |_, this| Ok(this.value));
+        fields.add_field_method_get("Envelope", |_, this| Ok(this.envelope));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| Ok(format!("({}, {}, {})", this.time, this.value, this.envelope)));
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for NumberSequenceKeypoint {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (time, value, envelope): (f64, f64, Option<f64>)| {
+                Ok(NumberSequenceKeypoint { time, value, envelope: envelope.unwrap_or_default() })
+            })?
+        )?;
+        lua.globals().raw_set("NumberSequenceKeypoint", table)?;
+        Ok(())
+    }
+}
+
+impl LuaUserData for NumberSequence {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Keypoints", |_, this| Ok(this.keypoints.clone()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| {
+            Ok(this.keypoints.iter().map(|x| format!("({}, {}, {})", x.time, x.value, x.envelope)).collect::<Vec<_>>().join(" "))
+        });
+        methods.add_meta_method("__eq", |_, this, other: NumberSequence| Ok(*this == other));
+    }
+}
+impl LuaSingleton for NumberSequence {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|lua, args: LuaMultiValue| {
+                // NumberSequence.new(value), NumberSequence.new(start, end) or NumberSequence.new(keypoints).
+                if let Ok(keypoints) = Vec::<NumberSequenceKeypoint>::from_lua_multi(args.clone(), lua) {
+                    return NumberSequence::new(keypoints);
+                }
+                let (start, end) = <(f64, Option<f64>)>::from_lua_multi(args, lua)?;
+                NumberSequence::new(vec![
+                    NumberSequenceKeypoint { time: 0.0, value: start, envelope: 0.0 },
+                    NumberSequenceKeypoint { time: 1.0, value: end.unwrap_or(start), envelope: 0.0 }
+                ])
+            })?
+        )?;
+        lua.globals().raw_set("NumberSequence", table)?;
+        Ok(())
+    }
+}
+
+impl LuaUserData for ColorSequenceKeypoint {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Time", |_, this| Ok(this.time));
+        fields.add_field_method_get("Value", |_, this| Ok(this.value));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| {
+            Ok(format!("({}, ({}, {}, {}))", this.time, this.value.r, this.value.g, this.value.b))
+        });
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+    }
+}
+impl LuaSingleton for ColorSequenceKeypoint {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (time, value): (f64, Color3)| Ok(ColorSequenceKeypoint { time, value }))?
+        )?;
+        lua.globals().raw_set("ColorSequenceKeypoint", table)?;
+        Ok(())
+    }
+}
+
+impl LuaUserData for ColorSequence {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Keypoints", |_, this| Ok(this.keypoints.clone()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", |_, this, ()| {
+            Ok(this.keypoints.iter()
+                .map(|x| format!("({}, ({}, {}, {}))", x.time, x.value.r, x.value.g, x.value.b))
+                .collect::<Vec<_>>()
+                .join(" "))
+        });
+        methods.add_meta_method("__eq", |_, this, other: ColorSequence| Ok(*this == other));
+    }
+}
+impl LuaSingleton for ColorSequence {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|lua, args: LuaMultiValue| {
+                // ColorSequence.new(color), ColorSequence.new(start, end) or ColorSequence.new(keypoints).
+                if let Ok(keypoints) = Vec::<ColorSequenceKeypoint>::from_lua_multi(args.clone(), lua) {
+                    return ColorSequence::new(keypoints);
+                }
+                let (start, end) = <(Color3, Option<Color3>)>::from_lua_multi(args, lua)?;
+                ColorSequence::new(vec![
+                    ColorSequenceKeypoint { time: 0.0, value: start },
+                    ColorSequenceKeypoint { time: 1.0, value: end.unwrap_or(start) }
+                ])
+            })?
+        )?;
+        lua.globals().raw_set("ColorSequence", table)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::{Add, Neg, Sub};
+
+use r2g_mlua::prelude::*;
+
+use super::LuaSingleton;
+
+/// The [`UDim`](https://create.roblox.com/docs/reference/engine/datatypes/UDim) data type represents a one-dimensional value with two components, a relative scale and an absolute offset.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct UDim {
+    pub scale: f64,
+    pub offset: i32
+}
+/// The [`UDim2`](https://create.roblox.com/docs/reference/engine/datatypes/UDim2) data type represents a two-dimensional value where each dimension is composed of a relative scale and an absolute offset. It is a combination of two [`UDim`]s representing the X and Y dimensions.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct UDim2 {
+    pub x: UDim,
+    pub y: UDim
+}
+
+from_lua_copy_impl!(UDim);
+from_lua_copy_impl!(UDim2);
+
+impl UDim {
+    pub const fn new(scale: f64, offset: i32) -> UDim {
+        UDim { scale, offset }
+    }
+    /// Returns a `UDim` linearly interpolated between the `UDim` and another one, the offset is rounded down.
+    pub fn lerp(&self, other: UDim, alpha: f64) -> UDim {
+        UDim {
+            scale: self.scale + (other.scale - self.scale) * alpha,
+            offset: (self.offset as f64 + (other.offset - self.offset) as f64 * alpha) as i32
+        }
+    }
+}
+impl UDim2 {
+    pub const fn new(x_scale: f64, x_offset: i32, y_scale: f64, y_offset: i32) -> UDim2 {
+        UDim2 {
+            x: UDim::new(x_scale, x_offset),
+            y: UDim::new(y_scale, y_offset)
+        }
+    }
+    /// Returns a `UDim2` linearly interpolated between the `UDim2` and another one.
+    pub fn lerp(&self, other: UDim2, alpha: f64) -> UDim2 {
+        UDim2 {
+            x: self.x.lerp(other.x, alpha),
+            y: self.y.lerp(other.y, alpha)
+        }
+    }
+}
+
+impl Add for UDim {
+    type Output = UDim;
+    fn add(self, rhs: Self) -> Self::Output {
+        UDim::new(self.scale + rhs.scale, self.offset.wrapping_add(rhs.offset))
+    }
+}
+impl Sub for UDim {
+    type Output = UDim;
+    fn sub(self, rhs: Self) -> Self::Output {
+        UDim::new(self.scale - rhs.scale, self.offset.wrapping_sub(rhs.offset))
+    }
+}
+impl Neg for UDim {
+    type Output = UDim;
+    fn neg(self) -> Self::Output {
+        UDim::new(-self.scale, self.offset.wrapping_neg())
+    }
+}
+impl Add for UDim2 {
+    type Output = UDim2;
+    fn add(self, rhs: Self) -> Self::Output {
+        UDim2 { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+impl Sub for UDim2 {
+    type Output = UDim2;
+    fn sub(self, rhs: Self) -> Self::Output {
+        UDim2 { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+impl Neg for UDim2 {
+    type Output = UDim2;
+    fn neg(self) -> Self::Output {
+        UDim2 { x: -self.x, y: -self.y }
+    }
+}
+
+impl LuaUserData for UDim {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Scale", |_, this| Ok(this.scale));
+        fields.add_field_method_get("Offset", |_, this| Ok(this.offset));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__add", |_, this, other| Ok(*this+other));
+        methods.add_meta_method("__sub", |_, this, other| Ok(*this-other));
+        methods.add_meta_method("__tostring", |_, this, ()| Ok(format!("{{{}, {}}}", this.scale, this.offset)));
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+        methods.add_meta_method("__unm", |_, this, ()| Ok(-*this));
+    }
+}
+impl LuaSingleton for UDim {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|_, (scale, offset): (Option<f64>, Option<i32>)| {
+                Ok(UDim::new(scale.unwrap_or_default(), offset.unwrap_or_default()))
+            })?
+        )?;
+        lua.globals().raw_set("UDim", table)?;
+        Ok(())
+    }
+}
+
+impl LuaUserData for UDim2 {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("X", |_, this| Ok(this.x));
+        fields.add_field_method_get("Y", |_, this| Ok(this.y));
+        fields.add_field_method_get("Width", |_, this| Ok(this.x));
+        fields.add_field_method_get("Height", |_, this| Ok(this.y));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Lerp", |_, this, (other, alpha)| Ok(this.lerp(other, alpha)));
+        methods.add_meta_method("__add", |_, this, other| Ok(*this+other));
+        methods.add_meta_method("__sub", |_, this, other| Ok(*this-other));
+        methods.add_meta_method("__tostring", |_, this, ()|
+            Ok(format!("{{{}, {}}}, {{{}, {}}}", this.x.scale, this.x.offset, this.y.scale, this.y.offset))
+        );
+        methods.add_meta_method("__eq", |_, this, other| Ok(*this == other));
+        methods.add_meta_method("__unm", |_, this, ()| Ok(-*this));
+    }
+}
+impl LuaSingleton for UDim2 {
+    fn register_singleton(lua: &Lua) -> LuaResult<()> {
+        let table = lua.create_table()?;
+        table.raw_set(
+            "new",
+            lua.create_function(|lua, args: LuaMultiValue| {
+                // UDim2.new(x, y) with two UDims, or UDim2.new(xScale, xOffset, yScale, yOffset).
+                if let Ok((x, y)) = <(UDim, UDim)>::from_lua_multi(args.clone(), lua) {
+                    return Ok(UDim2 { x, y });
+                }
+                let (x_scale, x_offset, y_scale, y_offset) = <(Option<f64>, Option<i32>, Option<f64>, Option<i32>)>::from_lua_multi(args, lua)?;
+                Ok(UDim2::new(x_scale.unwrap_or_default(), x_offset.unwrap_or_default(), y_scale.unwrap_or_default(), y_offset.unwrap_or_default()))
+            })?
+        )?;
+        table.raw_set(
+            "fromScale",
+            lua.create_function(|_, (x, y): (Option<f64>, Option<f64>)| {
+                Ok(UDim2::new(x.unwrap_or_default(), 0, y.unwrap_or_default(), 0))
+            })?
+        )?;
+        table.raw_set(
+            "fromOffset",
+            lua.create_function(|_, (x, y): (Option<i32>, Option<i32>)| {
+                Ok(UDim2::new(0.0, x.unwrap_or_default(), 0.0, y.unwrap_or_default()))
+            })?
+        )?;
+        lua.globals().raw_set("UDim2", table)?;
+        Ok(())
+    }
+}
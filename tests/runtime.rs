@@ -0,0 +1,959 @@
+//! Runs the runtime headless through `MemoryVMHost` and `RobloxVM::step`, without a Godot engine instance.
+
+use roblox_to_godot_project::core::*;
+
+const FRAME: f64 = 1.0 / 60.0;
+
+struct TestVM {
+    vm: Box<RwLock<RobloxVM>>,
+    host: MemoryVMHost
+}
+
+impl TestVM {
+    /// A VM on a virtual clock, so waits only depend on the number of frames stepped.
+    fn new() -> TestVM {
+        Self::with_flags(None)
+    }
+    fn with_flags(flags: Option<Vec<(FastFlag, FastFlagValue)>>) -> TestVM {
+        let host = MemoryVMHost::new();
+        let vm = RobloxVM::new_with_host(flags, Box::new(host.clone()));
+        vm.write().unwrap().set_clock(Clock::new_virtual(0.0));
+        TestVM { vm, host }
+    }
+    fn push_code(&self, source: &str) {
+        self.vm.write().unwrap().push_code("test", source).unwrap();
+    }
+    /// Pushes `source` running with the capabilities of `identity` instead of the ones of the command bar.
+    fn push_code_as(&self, source: &str, identity: ThreadIdentityType) {
+        let mut vm = self.vm.write().unwrap();
+        let thread = vm.push_code("test", source).unwrap();
+        vm.get_main_state().set_thread_identity(thread, ThreadIdentity { security_identity: identity, script: None });
+    }
+    fn step(&self, frames: usize) {
+        for _ in 0..frames {
+            RobloxVM::step(&self.vm, FRAME).unwrap();
+            self.vm.read().unwrap().get_clock().advance(FRAME);
+        }
+    }
+    /// Runs `source` for `frames` frames and returns what it printed.
+    fn run(&self, source: &str, frames: usize) -> Vec<String> {
+        self.push_code(source);
+        self.step(frames);
+        self.output()
+    }
+    fn output(&self) -> Vec<String> {
+        self.host.messages_of_kind(HostMessageKind::Output)
+    }
+    fn errors(&self) -> Vec<String> {
+        self.vm.read().unwrap().take_script_errors().into_iter().map(|x| x.message).collect()
+    }
+}
+
+fn run(source: &str, frames: usize) -> Vec<String> {
+    TestVM::new().run(source, frames)
+}
+
+#[test]
+fn prints_through_host() {
+    assert_eq!(run(r#"print("hello", 1, true)"#, 1), ["hello\t1\ttrue"]);
+}
+
+#[test]
+fn waits_follow_virtual_clock() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        task.delay(0.5, function() print("delay") end)
+        task.defer(function() print("defer") end)
+        print("start")
+        task.wait(0.25)
+        print("wait")
+    "#);
+    vm.step(1);
+    assert_eq!(vm.output(), ["start", "defer"]);
+    vm.step(60);
+    assert_eq!(vm.output(), ["start", "defer", "wait", "delay"]);
+}
+
+#[test]
+fn uncaught_errors_are_reported() {
+    let vm = TestVM::new();
+    vm.run(r#"error("boom")"#, 1);
+    let errors = vm.errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].ends_with("boom"), "{:?}", errors);
+}
+
+#[test]
+fn require_cycle_through_two_threads_errors() {
+    let vm = TestVM::new();
+    let output = vm.run(r#"
+        local folder = Instance.new("Model", workspace)
+        local a = Instance.new("ModuleScript", folder)
+        a.Name = "A"
+        a.Source = "task.wait(0.1) require(script.Parent.B) return 'A'"
+        local b = Instance.new("ModuleScript", folder)
+        b.Name = "B"
+        b.Source = "task.wait(0.1) require(script.Parent.A) return 'B'"
+        task.spawn(function() print("A", pcall(require, a)) end)
+        task.spawn(function() print("B", pcall(require, b)) end)
+    "#, 30);
+    assert_eq!(output.len(), 2, "{:?}", output);
+    assert!(output.contains(&"A\tfalse\tRequested module experienced an error while loading".to_string()), "{:?}", output);
+    assert!(output.contains(&"B\tfalse\tRequested module experienced an error while loading".to_string()), "{:?}", output);
+    let errors = vm.host.messages_of_kind(HostMessageKind::Error);
+    assert!(errors.iter().any(|x| x.contains("Requested module was required recursively")), "{:?}", errors);
+}
+
+#[test]
+fn require_waiters_wake_when_loader_is_cancelled() {
+    let output = run(r#"
+        local module = Instance.new("ModuleScript")
+        module.Source = "task.wait(1) return 1"
+        local loader = task.spawn(require, module)
+        task.spawn(function() print(pcall(require, module)) end)
+        task.wait(0.1)
+        task.cancel(loader)
+    "#, 30);
+    assert_eq!(output, ["false\tRequested module experienced an error while loading"]);
+}
+
+#[test]
+fn string_requires_stay_inside_require_root() {
+    let dir = std::env::temp_dir().join(format!("r2g-require-root-{}", std::process::id()));
+    let root = dir.join("root");
+    std::fs::create_dir_all(root.join("lib")).unwrap();
+    std::fs::write(root.join("lib").join("inside.luau"), "return 'inside'").unwrap();
+    std::fs::write(dir.join("outside.luau"), "return 'outside'").unwrap();
+
+    let vm = TestVM::new();
+    vm.vm.write().unwrap().get_require_config_mut().set_root(root).unwrap();
+    let output = vm.run(r#"
+        print(pcall(require, "./lib/inside"))
+        print(pcall(require, "./lib/../../outside"))
+        print(pcall(require, "../outside"))
+    "#, 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output[0], "true\tinside");
+    assert!(output[1].starts_with("false\t") && output[1].contains("is outside of the require root"), "{:?}", output);
+    assert!(output[2].starts_with("false\t") && output[2].contains("is outside of the require root"), "{:?}", output);
+}
+
+#[test]
+fn string_requires_load_from_host_alias_directories() {
+    let dir = std::env::temp_dir().join(format!("r2g-require-alias-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("root")).unwrap();
+    std::fs::create_dir_all(dir.join("shared")).unwrap();
+    std::fs::write(dir.join("shared").join("util.luau"), "return 'util'").unwrap();
+
+    let vm = TestVM::new();
+    {
+        let mut vm = vm.vm.write().unwrap();
+        let config = vm.get_require_config_mut();
+        config.set_root(dir.join("root")).unwrap();
+        config.add_alias("Shared", RequireAlias::Directory(dir.join("root").join("..").join("shared")));
+    }
+    let output = vm.run(r#"print(pcall(require, "@shared/util"))"#, 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output, ["true\tutil"]);
+}
+
+#[test]
+fn bytecode_cache_skips_corrupted_entries() {
+    let path = std::env::temp_dir().join(format!("r2g-bytecode-cache-{}", std::process::id()));
+    let cache = BytecodeCache::new();
+    cache.get_or_compile(CompileMode::Release, "return 1", || Ok(vec![6, 1, 2, 3])).unwrap();
+    cache.save(&path).unwrap();
+    assert_eq!(BytecodeCache::new().load(&path).unwrap(), 1);
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+    let loaded = BytecodeCache::new().load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), 0);
+}
+
+#[test]
+fn bytecode_cache_evicts_least_recently_used_entries() {
+    let cache = BytecodeCache::new();
+    cache.set_capacity(8);
+    let compiled = |byte: u8| move || Ok(vec![byte; 4]);
+    cache.get_or_compile(CompileMode::Release, "return 'a'", compiled(1)).unwrap();
+    cache.get_or_compile(CompileMode::Release, "return 'b'", compiled(2)).unwrap();
+    cache.get_or_compile(CompileMode::Release, "return 'a'", compiled(0)).unwrap();
+    cache.get_or_compile(CompileMode::Release, "return 'c'", compiled(3)).unwrap();
+    assert_eq!((cache.len(), cache.size()), (2, 8));
+    // Sources of the same length only share entries if they're equal.
+    assert_eq!(&*cache.get_or_compile(CompileMode::Release, "return 'a'", compiled(0)).unwrap(), [1; 4]);
+    assert_eq!(&*cache.get_or_compile(CompileMode::Release, "return 'b'", compiled(4)).unwrap(), [4; 4]);
+    assert_eq!(cache.stats(), (2, 4));
+    // Bytecode larger than the capacity isn't cached.
+    cache.get_or_compile(CompileMode::Release, "return 'd'", || Ok(vec![5; 16])).unwrap();
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn bytecode_cache_saves_through_a_temporary_file() {
+    let dir = std::env::temp_dir().join(format!("r2g-bytecode-cache-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cache = BytecodeCache::new();
+    cache.get_or_compile(CompileMode::Debug, "return 1", || Ok(vec![6, 1, 2, 3])).unwrap();
+    cache.save(&dir.join("cache.bin")).unwrap();
+    assert!(cache.save(&dir.join("missing").join("cache.bin")).is_err());
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|x| x.unwrap().file_name()).collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(files, ["cache.bin"]);
+}
+
+#[test]
+fn json_round_trips_and_caps_nesting() {
+    let value = JsonValue::parse(r#" { "a": [1, 2.5, -3e2, true, null], "b": "x\"\\\né" } "#).unwrap();
+    assert_eq!(value.get("a").as_array().len(), 5);
+    assert_eq!(value.get("a").as_array()[2].as_i64(), Some(-300));
+    assert_eq!(value.get("b").as_str(), Some("x\"\\\né"));
+    assert_eq!(JsonValue::parse(&value.to_string()).unwrap(), value);
+    assert!(JsonValue::parse("[1] 2").is_err());
+    assert!(JsonValue::parse("{\"a\" 1}").is_err());
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(JsonValue::parse(&nested(64)).is_ok());
+    assert!(JsonValue::parse(&nested(65)).unwrap_err().contains("nesting deeper than 64"));
+}
+
+/// A Debug Adapter Protocol client connected to the debugger of a TestVM.
+struct DebugClient {
+    stream: std::net::TcpStream,
+    messages: std::sync::mpsc::Receiver<JsonValue>,
+    /// Messages received while waiting for another one.
+    pending: Vec<JsonValue>,
+    seq: i64
+}
+
+impl DebugClient {
+    fn connect(vm: &TestVM, token: &str) -> DebugClient {
+        use std::io::{BufRead, Read};
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        vm.vm.write().unwrap().attach_debugger(DebugTransport::Tcp { port, token: token.into() }).unwrap();
+        let stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+        let (sender, messages) = std::sync::mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                match line.trim_end().split_once(": ") {
+                    Some(("Content-Length", value)) => length = value.parse().unwrap(),
+                    _ => break
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            if sender.send(JsonValue::parse(std::str::from_utf8(&body).unwrap()).unwrap()).is_err() {
+                return;
+            }
+        });
+        DebugClient { stream, messages, pending: Vec::new(), seq: 0 }
+    }
+    fn send(&mut self, command: &str, arguments: JsonValue) -> i64 {
+        use std::io::Write;
+        self.seq += 1;
+        let body = JsonValue::object([
+            ("seq", self.seq.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments)
+        ]).to_string();
+        write!(self.stream, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.seq
+    }
+    /// Waits for the first message matching `matches`, stepping `vm` so the debugger polls, if there is one.
+    fn wait(&mut self, vm: Option<&TestVM>, matches: impl Fn(&JsonValue) -> bool) -> JsonValue {
+        if let Some(index) = self.pending.iter().position(&matches) {
+            return self.pending.remove(index);
+        }
+        for _ in 0..500 {
+            if let Some(vm) = vm {
+                vm.step(1);
+            }
+            while let Ok(message) = self.messages.recv_timeout(std::time::Duration::from_millis(10)) {
+                if matches(&message) {
+                    return message;
+                }
+                self.pending.push(message);
+            }
+        }
+        panic!("no matching message, received {:?}", self.pending);
+    }
+    fn request(&mut self, vm: Option<&TestVM>, command: &str, arguments: JsonValue) -> JsonValue {
+        let seq = self.send(command, arguments);
+        self.wait(vm, |x| x.get("type").as_str() == Some("response") && x.get("request_seq").as_i64() == Some(seq))
+    }
+    fn event(&mut self, vm: Option<&TestVM>, event: &str) -> JsonValue {
+        self.wait(vm, |x| x.get("type").as_str() == Some("event") && x.get("event").as_str() == Some(event))
+    }
+}
+
+fn succeeded(response: &JsonValue) -> bool {
+    response.get("success") == &JsonValue::Bool(true)
+}
+
+#[test]
+fn debugger_refuses_requests_until_attached_with_the_token() {
+    let vm = TestVM::new();
+    assert!(vm.vm.write().unwrap().attach_debugger(DebugTransport::Tcp { port: 0, token: String::new() }).is_err());
+    let mut client = DebugClient::connect(&vm, "secret");
+    assert!(succeeded(&client.request(Some(&vm), "initialize", JsonValue::object([]))));
+    let refused = client.request(Some(&vm), "threads", JsonValue::object([]));
+    assert_eq!(refused.get("message").as_str(), Some("attach or launch with the debugger token first"));
+    let wrong = client.request(Some(&vm), "attach", JsonValue::object([("token", "guess".into())]));
+    assert_eq!(wrong.get("message").as_str(), Some("invalid debugger token"));
+    assert!(succeeded(&client.request(Some(&vm), "attach", JsonValue::object([("token", "secret".into())]))));
+    client.event(Some(&vm), "initialized");
+    let threads = client.request(Some(&vm), "threads", JsonValue::object([]));
+    assert_eq!(threads.get("body").get("threads").as_array()[0].get("name").as_str(), Some("Main"));
+    // Evaluating between frames would run code in the middle of the VM's own state.
+    let evaluated = client.request(Some(&vm), "evaluate", JsonValue::object([("expression", "1 + 1".into())]));
+    assert_eq!(evaluated.get("message").as_str(), Some("no thread is paused"));
+}
+
+#[test]
+fn debugger_pauses_at_breakpoints_and_evaluates_in_the_paused_frame() {
+    let vm = TestVM::new();
+    let mut client = DebugClient::connect(&vm, "secret");
+    client.request(Some(&vm), "attach", JsonValue::object([("token", "secret".into())]));
+    let breakpoints = client.request(Some(&vm), "setBreakpoints", JsonValue::object([
+        ("source", JsonValue::object([("path", "test".into())])),
+        ("breakpoints", vec![JsonValue::object([("line", 3.into())])].into())
+    ]));
+    assert!(succeeded(&breakpoints));
+    // Stepping blocks while the VM is paused, the client answers from another thread.
+    let session = std::thread::spawn(move || {
+        let stopped = client.event(None, "stopped");
+        let frames = client.request(None, "stackTrace", JsonValue::object([("threadId", 1.into())]));
+        let evaluated = client.request(None, "evaluate", JsonValue::object([("expression", "x + 1".into()), ("frameId", 1.into())]));
+        client.request(None, "continue", JsonValue::object([("threadId", 1.into())]));
+        (stopped, frames, evaluated)
+    });
+    // Code pushed by the host has no local names, the expression reads the global through the frame environment.
+    vm.run("x = 41\nx += 0\nprint(x)\n", 1);
+    let (stopped, frames, evaluated) = session.join().unwrap();
+    assert_eq!(stopped.get("body").get("reason").as_str(), Some("breakpoint"));
+    let top = &frames.get("body").get("stackFrames").as_array()[0];
+    assert_eq!((top.get("source").get("path").as_str(), top.get("line").as_i64()), (Some("test"), Some(3)));
+    assert_eq!(evaluated.get("body").get("result").as_str(), Some("42"));
+    assert_eq!(vm.output(), ["41"]);
+}
+
+const PROFILED: &str = r#"
+local function spin()
+    local total = 0
+    for i = 1, 2000 do
+        total += i
+    end
+    return total
+end
+for _ = 1, 20 do
+    spin()
+end
+"#;
+
+#[test]
+fn profiler_exports_collapsed_stacks_and_chrome_trace() {
+    let vm = TestVM::new();
+    vm.vm.write().unwrap().start_profiler(0.0);
+    vm.run(PROFILED, 1);
+    vm.vm.write().unwrap().stop_profiler();
+    let read = vm.vm.read().unwrap();
+    let profiler = read.get_profiler();
+    let collapsed = profiler.to_collapsed_stacks();
+    let spin_line = collapsed.lines()
+        .find(|x| x.starts_with("<unknown>;<anonymous> (test:1);spin (test:2) "))
+        .unwrap_or_else(|| panic!("{}", collapsed));
+    let counted: usize = collapsed.lines().map(|x| x.rsplit_once(' ').unwrap().1.parse::<usize>().unwrap()).sum();
+    assert_eq!(counted, profiler.samples().len());
+    assert!(spin_line.rsplit_once(' ').unwrap().1.parse::<usize>().unwrap() > 0);
+
+    let trace = JsonValue::parse(&profiler.to_chrome_trace()).unwrap();
+    let events = trace.get("traceEvents").as_array();
+    assert!(events.iter().any(|x| x.get("ph").as_str() == Some("M") && x.get("args").get("name").as_str() == Some("Main")));
+    let spins: Vec<_> = events.iter().filter(|x| x.get("name").as_str() == Some("spin (test:2)")).collect();
+    assert!(!spins.is_empty());
+    assert!(spins.iter().all(|x| x.get("ph").as_str() == Some("X") && x.get("dur").as_i64().is_some_and(|x| x >= 0)));
+}
+
+#[test]
+fn profiler_keeps_only_the_latest_samples() {
+    let vm = TestVM::new();
+    vm.vm.read().unwrap().get_profiler().set_capacity(10);
+    vm.vm.write().unwrap().start_profiler(0.0);
+    vm.run(PROFILED, 1);
+    vm.vm.write().unwrap().stop_profiler();
+    let read = vm.vm.read().unwrap();
+    let samples = read.get_profiler().samples();
+    assert_eq!(samples.len(), 10);
+    assert!(samples.windows(2).all(|x| x[0].time <= x[1].time));
+    read.get_profiler().set_capacity(4);
+    assert_eq!(read.get_profiler().samples().iter().map(|x| x.time).collect::<Vec<_>>(),
+        samples[6..].iter().map(|x| x.time).collect::<Vec<_>>());
+}
+
+#[test]
+fn engine_stats_are_reachable_by_scripts() {
+    let vm = TestVM::new();
+    vm.push_code_as(r#"
+        local stats = game:GetService("Stats")
+        print(type(stats:GetTotalMemoryUsageMb()))
+        print(type(stats:GetStateMemoryUsageMb()))
+        print(type(stats:GetScriptMemoryUsageMb(Instance.new("Script"))))
+        print(type(stats:GetTaskSchedulerStats().Main.Synchronized.Deferred))
+    "#, ThreadIdentityType::Script);
+    vm.step(1);
+    assert_eq!(vm.output(), ["number", "number", "number", "number"]);
+}
+
+#[test]
+fn errors_with_failing_tostring_are_reported() {
+    let vm = TestVM::new();
+    vm.run(r#"
+        task.spawn(function()
+            error(setmetatable({}, { __tostring = function() error("tostring failed") end }))
+        end)
+        task.spawn(function()
+            error(setmetatable({}, { __tostring = function() return {} end }))
+        end)
+        print("alive")
+    "#, 1);
+    assert_eq!(vm.output(), ["alive"]);
+    assert_eq!(vm.errors(), ["table", "table"]);
+}
+
+#[test]
+fn enum_items_know_their_enum() {
+    let output = run(r#"
+        local item = Enum.NormalId.Top
+        print(item, item.Name, item.Value, item.EnumType == Enum.NormalId)
+        print(Enum.RunContext.Client == Enum.RunContext.Client, Enum.Axis.X == Enum.RotationOrder.XYZ)
+        print(Enum.ModelStreamingMode.Atomic.Value, Enum.ModelLevelOfDetail.Disabled, Enum.MessageType.MessageError.EnumType == Enum.MessageType)
+        workspace:SetAttribute("Face", Enum.NormalId.Front)
+        print(workspace:GetAttribute("Face"))
+    "#, 1);
+    assert_eq!(output, [
+        "Enum.NormalId.Top\tTop\t1\ttrue",
+        "true\tfalse",
+        "1\tEnum.ModelLevelOfDetail.Disabled\ttrue",
+        "Enum.NormalId.Front"
+    ]);
+}
+
+#[test]
+fn watchdogs_use_wall_time_under_virtual_clock() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        local resumes = 0
+        local function again()
+            resumes += 1
+            task.defer(again)
+        end
+        task.defer(again)
+        task.delay(0, function() print(resumes > 0) end)
+    "#);
+    // Each frame resumes the deferred thread until it runs out of wall time, a virtual clock never runs out.
+    let started = std::time::Instant::now();
+    vm.step(2);
+    assert!(started.elapsed().as_secs_f64() < 5.0, "{:?}", started.elapsed());
+    assert_eq!(vm.output(), ["true"]);
+}
+
+#[test]
+fn delaying_a_thread_twice_resumes_it_twice() {
+    let output = run(r#"
+        local thread = coroutine.create(function()
+            local resumes = 0
+            while true do
+                resumes += 1
+                print("resumed", resumes)
+                coroutine.yield()
+            end
+        end)
+        task.delay(0, thread)
+        task.delay(0.1, thread)
+    "#, 20);
+    assert_eq!(output, ["resumed\t1", "resumed\t2"]);
+}
+
+#[test]
+fn states_are_named_alike_everywhere() {
+    let vm = TestVM::new();
+    vm.run(r#"
+        local actor = Instance.new("Actor", workspace)
+        local script = Instance.new("Script")
+        script.Source = "task.wait(0)"
+        script.Parent = actor
+    "#, 2);
+    let vm = vm.vm.read().unwrap();
+    let names: Vec<String> = vm.state_names().into_iter().map(|x| x.0).collect();
+    assert_eq!(names, ["Actor 1", "Main"]);
+    let snapshots = vm.get_task_scheduler_snapshots().unwrap();
+    assert_eq!(snapshots.iter().map(|x| x.0.clone()).collect::<Vec<_>>(), names);
+    assert_eq!(vm.get_memory_stats().into_iter().map(|x| x.0).collect::<Vec<_>>(), names);
+}
+
+#[test]
+fn task_scheduler_cycles_are_timed_with_wall_time() {
+    let vm = TestVM::new();
+    // The virtual clock doesn't move while the frame runs, the cycle still took time.
+    vm.run(r#"
+        local spins = 0
+        while spins < 200000 do
+            spins += 1
+        end
+    "#, 1);
+    let snapshots = vm.vm.read().unwrap().get_task_scheduler_snapshots().unwrap();
+    let (_, main) = snapshots.iter().find(|x| x.0 == "Main").unwrap();
+    assert_eq!(main.synchronized_cycle.defer_resumes, 1);
+    assert!(main.synchronized_cycle.defer_time > 0.0);
+}
+
+#[test]
+fn unsafe_members_error_in_parallel() {
+    let vm = TestVM::new();
+    vm.run(r#"
+        local seen = {}
+        game:GetService("LogService").MessageOut:Connect(function(message)
+            table.insert(seen, message)
+        end)
+        game:GetService("ScriptContext").Error:Connect(function(message)
+            print("error fired", #seen, message:find("parallel boom") ~= nil)
+        end)
+        local actor = Instance.new("Actor", workspace)
+        local script = Instance.new("LocalScript")
+        script.Source = [[
+            task.desynchronize()
+            print(pcall(workspace.ClearAllChildren, workspace))
+            print(pcall(function() script:SetAttribute("A", 1) end))
+            print(pcall(function() script.Parent:Destroy() end))
+            print(pcall(function() return script.Parent:FindFirstChild("LocalScript") == script end))
+            print(pcall(function() return script:GetAttributeChangedSignal("A") end))
+            print(tostring(select(2, pcall(Instance.new, "Actor"))):match("Actors can't be created in parallel"))
+            print(pcall(Instance.new, "Model") == true)
+            error("parallel boom")
+        ]]
+        script.Parent = actor
+        script.Enabled = true
+    "#, 2);
+    assert_eq!(vm.output(), [
+        "false\tFunction Workspace.ClearAllChildren is not safe to call in parallel",
+        "false\tFunction LocalScript.SetAttribute is not safe to call in parallel",
+        "false\tFunction Actor.Destroy is not safe to call in parallel",
+        "true\ttrue",
+        "false\tFunction LocalScript.GetAttributeChangedSignal is not safe to call in parallel",
+        "Actors can't be created in parallel",
+        "true",
+        "error fired\t9\ttrue"
+    ]);
+}
+
+#[test]
+fn enums_are_passed_to_handlers_in_actors() {
+    let vm = TestVM::new();
+    vm.run(r#"
+        local actor = Instance.new("Actor", workspace)
+        local script = Instance.new("LocalScript")
+        script.Source = [[
+            script:GetAttributeChangedSignal("Face"):Connect(function(value)
+                print("changed", value)
+            end)
+        ]]
+        script.Parent = actor
+        script.Enabled = true
+        task.wait(0.1)
+        script:SetAttribute("Face", Enum.NormalId.Top)
+        print("set")
+    "#, 10);
+    assert_eq!(vm.output(), ["set", "changed\tEnum.NormalId.Top"]);
+}
+
+#[test]
+fn deferred_handlers_run_at_the_next_invocation_point() {
+    let vm = TestVM::with_flags(Some(vec![(FastFlag::SignalBehavior, FastFlagValue::Int(2))]));
+    vm.run(r#"
+        print(workspace.SignalBehavior)
+        local connection
+        connection = workspace:GetAttributeChangedSignal("Count"):Once(function()
+            print("once", connection.Connected)
+        end)
+        workspace:GetAttributeChangedSignal("Count"):Connect(function()
+            print("changed", workspace:GetAttribute("Count"))
+        end)
+        workspace:SetAttribute("Count", 1)
+        workspace:SetAttribute("Count", 2)
+        print("set", connection.Connected)
+    "#, 1);
+    assert_eq!(vm.output(), ["Enum.SignalBehavior.Deferred", "set\tfalse", "once\tfalse", "changed\t2", "changed\t2"]);
+}
+
+#[test]
+fn deferred_handlers_refiring_their_signal_stop_at_the_reentrancy_limit() {
+    let vm = TestVM::with_flags(Some(vec![(FastFlag::SignalBehavior, FastFlagValue::Int(2))]));
+    vm.run(r#"
+        local runs = 0
+        workspace:GetAttributeChangedSignal("Count"):Connect(function()
+            runs += 1
+            workspace:SetAttribute("Count", runs)
+        end)
+        workspace:SetAttribute("Count", 0)
+        task.wait(0.1)
+        print(runs)
+    "#, 10);
+    assert_eq!(vm.output(), ["10"]);
+    let errors = vm.errors();
+    assert!(errors.len() == 1 && errors[0].contains("Maximum event re-entrancy depth exceeded"), "{:?}", errors);
+}
+
+#[test]
+fn handlers_run_in_connection_order_and_can_disconnect_each_other() {
+    let output = run(r#"
+        local signal = workspace.AttributeChanged
+        local order = {}
+        local first, second
+        first = signal:Connect(function() table.insert(order, "first") end)
+        second = signal:Once(function() table.insert(order, "second") end)
+        signal:Connect(function()
+            table.insert(order, "third")
+            second:Disconnect()
+            first:Disconnect()
+            signal:Connect(function() table.insert(order, "new") end)
+        end)
+        workspace:SetAttribute("Value", 1)
+        print(table.concat(order, " "), first.Connected, second.Connected)
+        table.clear(order)
+        workspace:SetAttribute("Value", 2)
+        print(table.concat(order, " "))
+    "#, 1);
+    assert_eq!(output, ["third\tfalse\tfalse", "new third"]);
+
+    let vm = TestVM::with_flags(Some(vec![(FastFlag::SignalBehavior, FastFlagValue::Int(2))]));
+    vm.run(r#"
+        for i = 1, 3 do
+            workspace.AttributeChanged:Connect(function() print(i) end)
+        end
+        workspace:SetAttribute("Value", 1)
+    "#, 1);
+    assert_eq!(vm.output(), ["1", "2", "3"]);
+}
+
+#[test]
+fn waits_on_signals_resume_with_the_fired_values_or_time_out() {
+    let vm = TestVM::new();
+    vm.run(r#"
+        task.spawn(function()
+            print("attribute", workspace.AttributeChanged:Wait())
+        end)
+        task.spawn(function()
+            print("child", workspace:WaitForChild("Later").Name)
+        end)
+        task.spawn(function()
+            print("timed out", workspace:WaitForChild("Never", 0.5))
+        end)
+        workspace:SetAttribute("Flag", true)
+        local later = Instance.new("Model")
+        later.Name = "Later"
+        later.Parent = workspace
+    "#, 1);
+    vm.step(20);
+    assert_eq!(vm.output(), ["attribute\tFlag", "child\tLater"]);
+    vm.step(20);
+    vm.run(r#"
+        local never = Instance.new("Model")
+        never.Name = "Never"
+        never.Parent = workspace
+    "#, 1);
+    assert_eq!(vm.output(), ["attribute\tFlag", "child\tLater", "timed out\tnil"]);
+}
+
+#[test]
+fn instances_keep_their_identity_in_lua() {
+    let output = run(r#"
+        local model = Instance.new("Model", workspace)
+        model.Name = "Part"
+        print(workspace.Part == model, rawequal(workspace.Part, model), rawequal(game.Workspace, workspace))
+        local seen = {}
+        seen[model] = true
+        collectgarbage()
+        print(seen[workspace.Part], seen[workspace:FindFirstChild("Part")], seen[workspace:GetChildren()[1]])
+    "#, 1);
+    assert_eq!(output, ["true\ttrue\ttrue", "true\ttrue\ttrue"]);
+}
+
+#[test]
+fn methods_are_created_once_and_keep_their_security() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        local run_service = game:GetService("RunService")
+        print(workspace.FindFirstChild == workspace.FindFirstChild)
+        run_service:Pause()
+        run_service:Run()
+    "#);
+    vm.push_code_as(r#"
+        local run_service = game:GetService("RunService")
+        print(run_service:IsRunning())
+        print(pcall(function() run_service:Pause() end))
+        print(pcall(function() return run_service.Pause end))
+    "#, ThreadIdentityType::Script);
+    vm.step(1);
+    let output = vm.output();
+    assert_eq!(output.len(), 4);
+    assert_eq!(output[..2], ["true", "true"]);
+    assert!(output[2..].iter().all(|x| x.starts_with("false\t") && x.contains("lacking capability Plugin")), "{:?}", output);
+}
+
+#[test]
+fn derived_classes_inherit_members_and_clone_their_components() {
+    let output = run(r#"
+        local model = Instance.new("Model")
+        model.Name = "Original"
+        model:SetAttribute("Flag", true)
+        local child = Instance.new("Model", model)
+        print(model.ClassName, model:IsA("Model"), model:IsA("PVInstance"), model:IsA("Instance"), model:IsA("Actor"))
+        local copy = model:Clone()
+        print(copy.ClassName, copy.Name, copy:GetAttribute("Flag"), #copy:GetChildren(), copy:GetChildren()[1] ~= child)
+        print(model:FindFirstChildWhichIsA("PVInstance") == child, model:FindFirstChildWhichIsA("Actor"))
+        local actor = Instance.new("Actor")
+        print(actor:IsA("Model"), actor:Clone().ClassName)
+        print(game:GetService("RunService"):IsRunning(), tostring(select(2, pcall(game.Clone, game:GetService("RunService")))):match("Cannot clone %a+%."))
+    "#, 1);
+    assert_eq!(output, [
+        "Model\ttrue\ttrue\ttrue\tfalse",
+        "Model\tOriginal\ttrue\t1\ttrue",
+        "true\tnil",
+        "true\tActor",
+        "true\tCannot clone RunService."
+    ]);
+}
+
+#[test]
+fn class_database_looks_up_inherited_members_and_engine_extensions() {
+    let db = &CLASS_DATABASE;
+    assert!(db.classes().windows(2).all(|x| x[0].name < x[1].name));
+    assert!(db.enums().windows(2).all(|x| x[0].name < x[1].name));
+    let chain: Vec<_> = db.get_inheritance_chain("LocalScript").map(|x| x.name).collect();
+    assert_eq!(chain, ["LocalScript", "Script", "BaseScript", "LuaSourceContainer", "Instance"]);
+    assert!(db.is_a("Workspace", "Model") && db.is_a("Workspace", "Object") && !db.is_a("Model", "Workspace"));
+    assert!(db.get_class("Part").is_none() && db.find_member("Workspace", "NotAMember").is_none());
+    assert!(!db.get_class("Instance").unwrap().is_creatable() && db.get_class("RunService").unwrap().is_service());
+    let archivable = db.find_property("Workspace", "Archivable").unwrap();
+    assert_eq!((archivable.read_security, archivable.thread_safety), (SecurityLevel::None, ThreadSafety::ReadSafe));
+    assert_eq!(db.get_member_security("Script", "Source", true), Some(SecurityLevel::PluginSecurity));
+    assert_eq!(db.get_enum("NormalId").unwrap().items.iter().find(|x| x.name == "Front").unwrap().value, 5);
+    // Members the engine adds to the dump come from api/Engine-Extensions.json.
+    assert!(db.find_function("Stats", "GetTaskSchedulerStats").is_some());
+    assert!(db.find_function("Stats", "GetTotalMemoryUsageMb").is_some());
+}
+
+#[test]
+fn keys_which_are_not_declared_members_only_index_children() {
+    let output = run(r#"
+        local model = Instance.new("Model", workspace)
+        model.Name = "Undeclared"
+        print(workspace.Undeclared == model)
+        print(pcall(function() workspace.Undeclared = 1 end))
+    "#, 1);
+    assert_eq!(output[0], "true");
+    assert!(output[1].starts_with("false\t") && output[1].contains("Undeclared is not a valid member of Workspace"), "{:?}", output);
+}
+
+#[test]
+fn instance_new_creates_only_creatable_classes() {
+    let output = run(r#"
+        local model = Instance.new("Model", workspace)
+        model.Name = "Source"
+        Instance.new("Model", model)
+        print(model.Parent == workspace)
+        local copy = Instance.fromExisting(model)
+        print(copy.ClassName, copy.Name, copy.Parent, #copy:GetChildren())
+        for _, class_name in {"PVInstance", "Workspace", "Nonexistent"} do
+            print(tostring(select(2, pcall(Instance.new, class_name))):match('Unable to create an Instance of type "%a+"'))
+        end
+        print(tostring(select(2, pcall(game.GetService, game, "Model"))):match("'%a+' is not a valid Service name"))
+    "#, 1);
+    assert_eq!(output, [
+        "true",
+        "Model\tSource\tnil\t0",
+        "Unable to create an Instance of type \"PVInstance\"",
+        "Unable to create an Instance of type \"Workspace\"",
+        "Unable to create an Instance of type \"Nonexistent\"",
+        "'Model' is not a valid Service name"
+    ]);
+}
+
+#[test]
+fn run_service_run_pause_and_stop_need_plugin_security() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        local run_service = game:GetService("RunService")
+        run_service:Pause()
+        run_service:Stop()
+        run_service:Run()
+        print(run_service:IsRunning())
+    "#);
+    vm.push_code_as(r#"print(pcall(function() game:GetService("RunService"):Pause() end))"#, ThreadIdentityType::Script);
+    vm.step(1);
+    let output = vm.output();
+    assert_eq!(output[0], "true");
+    assert!(output[1].starts_with("false\t") && output[1].contains("lacking capability Plugin"), "{:?}", output);
+}
+
+#[test]
+fn tag_signals_fire_when_tagged_trees_move() {
+    let output = run(r#"
+        local collection_service = game:GetService("CollectionService")
+        local model = Instance.new("Model")
+        local child = Instance.new("Model", model)
+        child:AddTag("Marked")
+        model.Parent = workspace
+        collection_service:GetInstanceAddedSignal("Marked"):Connect(function(x) print("added", x == child) end)
+        collection_service:GetInstanceRemovedSignal("Marked"):Connect(function(x) print("removed", x == child) end)
+        model.Parent = nil
+        model.Parent = workspace
+    "#, 1);
+    assert_eq!(output, ["removed\ttrue", "added\ttrue"]);
+}
+
+#[test]
+fn tagged_instances_only_count_in_the_data_model() {
+    let output = run(r#"
+        local collection_service = game:GetService("CollectionService")
+        local model = Instance.new("Model")
+        collection_service:AddTag(model, "Marked")
+        print(collection_service:HasTag(model, "Marked"), #collection_service:GetTagged("Marked"), table.find(collection_service:GetAllTags(), "Marked"))
+        model.Parent = workspace
+        print(collection_service:GetTagged("Marked")[1] == model, table.find(collection_service:GetAllTags(), "Marked") ~= nil)
+        model.Parent = nil
+        print(#collection_service:GetTagged("Marked"))
+        model.Parent = workspace
+        collection_service:RemoveTag(model, "Marked")
+        print(collection_service:HasTag(model, "Marked"), #collection_service:GetTagged("Marked"), table.find(collection_service:GetAllTags(), "Marked"))
+
+        -- The signals are dropped once nothing holds or listens to them, asking for them again gives a working signal.
+        collection_service:GetInstanceAddedSignal("Marked"):Connect(function() end):Disconnect()
+        collectgarbage()
+        model.Parent = nil
+        collection_service:GetInstanceAddedSignal("Marked"):Connect(function(x) print("added", x == model) end)
+        collection_service:AddTag(model, "Marked")
+        model.Parent = workspace
+    "#, 1);
+    assert_eq!(output, ["true\t0\tnil", "true\ttrue", "0", "false\t0\tnil", "added\ttrue"]);
+}
+
+#[test]
+fn attributes_round_trip_through_the_place_file_format() {
+    use roblox_to_godot_project::userdata::{deserialize_attributes, serialize_attributes, AttributeValue};
+
+    let flag = ("Flag".to_string(), AttributeValue::Bool(true));
+    assert_eq!(serialize_attributes([(&flag.0, &flag.1)]), [1, 0, 0, 0, 4, 0, 0, 0, b'F', b'l', b'a', b'g', 0x03, 1]);
+    // An axis-aligned CFrame stored by its rotation id, 0x02 being the identity.
+    let mut data = vec![1, 0, 0, 0, 1, 0, 0, 0, b'C', 0x14];
+    data.extend([1.0f32, 2.0, 3.0].iter().flat_map(|x| x.to_le_bytes()));
+    data.push(0x02);
+    let loaded = deserialize_attributes(&data).unwrap();
+    assert!(matches!(&loaded[0].1, AttributeValue::CFrame(x) if x.pos == [1.0, 2.0, 3.0] && x.rot_matrix == [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]));
+
+    let vm = TestVM::new();
+    vm.run(r#"
+        workspace:SetAttribute("Flag", true)
+        workspace:SetAttribute("Face", Enum.NormalId.Top)
+        workspace:SetAttribute("Frame", CFrame.new(1, 2, 3))
+        workspace:SetAttribute("Label", "hello")
+        workspace:SetAttribute("Speed", 0.1)
+    "#, 1);
+    {
+        let vm = vm.vm.read().unwrap();
+        let workspace = vm.get_game_instance().find_first_child_of_class("Workspace".into()).unwrap().unwrap();
+        let data = workspace.get_attributes_serialize();
+        workspace.set_attributes_serialize(&[0, 0, 0, 0]).unwrap();
+        assert!(workspace.get_attributes().unwrap().is_empty());
+        workspace.set_attributes_serialize(&data).unwrap();
+        assert!(workspace.set_attributes_serialize(&data[..data.len() - 1]).is_err());
+    }
+    vm.push_code(r#"
+        local attributes = workspace:GetAttributes()
+        print(attributes.Flag, attributes.Face, attributes.Frame, attributes.Label, attributes.Speed)
+    "#);
+    vm.step(1);
+    assert_eq!(vm.output(), ["true\tEnum.NormalId.Top\t(1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 2, 3)\thello\t0.1"]);
+}
+
+#[test]
+fn attributes_serialize_loads_attributes_without_firing_signals() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        local source = Instance.new("Model")
+        source:SetAttribute("Label", "hello")
+        local model = Instance.new("Model")
+        model.AttributeChanged:Connect(function(name) print("changed", name) end)
+        model.AttributesSerialize = source.AttributesSerialize
+        print(model:GetAttribute("Label"), source.AttributesSerialize == model.AttributesSerialize)
+        print(pcall(function() model.AttributesSerialize = "" end))
+        print(pcall(function() model:GetAttributeChangedSignal("RBXName") end))
+    "#);
+    vm.push_code_as(r#"print(pcall(function() return workspace.AttributesSerialize end))"#, ThreadIdentityType::Script);
+    vm.step(1);
+    let output = vm.output();
+    assert_eq!(output[0], "hello\ttrue");
+    assert!(output[1].starts_with("false\t") && output[1].contains("Serialized attributes are truncated"), "{:?}", output);
+    assert!(output[2].starts_with("false\t") && output[2].contains("names starting with RBX are reserved"), "{:?}", output);
+    assert!(output[3].starts_with("false\t") && output[3].contains("lacking capability Plugin"), "{:?}", output);
+}
+
+#[test]
+fn datatype_attributes_round_trip_through_attributes_serialize() {
+    let vm = TestVM::new();
+    vm.push_code(r#"
+        local values = {
+            UDim = UDim.new(0.5, 10),
+            UDim2 = UDim2.new(0.25, -4, 1, 8),
+            BrickColor = BrickColor.new("Really red"),
+            Color3 = Color3.new(1, 0.5, 0),
+            NumberSequence = NumberSequence.new({
+                NumberSequenceKeypoint.new(0, 1, 0.5), NumberSequenceKeypoint.new(0.5, 2), NumberSequenceKeypoint.new(1, 3)
+            }),
+            ColorSequence = ColorSequence.new(Color3.new(1, 0, 0), Color3.new(0, 0, 1)),
+            NumberRange = NumberRange.new(-1, 2),
+            Rect = Rect.new(1, 2, 3, 4),
+            Font = Font.fromName("Arial", Enum.FontWeight.Bold, Enum.FontStyle.Italic)
+        }
+        local source = Instance.new("Model")
+        for name, value in values do
+            source:SetAttribute(name, value)
+        end
+        local model = Instance.new("Model")
+        model.AttributesSerialize = source.AttributesSerialize
+        for _, name in { "UDim", "UDim2", "BrickColor", "Color3", "NumberSequence", "ColorSequence", "NumberRange", "Rect", "Font" } do
+            print(name, typeof(model:GetAttribute(name)), model:GetAttribute(name) == values[name])
+        end
+
+        local function serialize(value)
+            local instance = Instance.new("Model")
+            instance:SetAttribute("A", value)
+            return instance.AttributesSerialize
+        end
+        print(serialize(UDim.new(0.5, 10)) == "\1\0\0\0\1\0\0\0A\9\0\0\0\63\10\0\0\0")
+        print(serialize(BrickColor.new(1004)) == "\1\0\0\0\1\0\0\0A\14\236\3\0\0")
+        print(serialize(NumberRange.new(1, 2)) == "\1\0\0\0\1\0\0\0A\27\0\0\128\63\0\0\0\64")
+        print(serialize(Font.new("x", Enum.FontWeight.Bold, Enum.FontStyle.Italic)) == "\1\0\0\0\1\0\0\0A\33\188\2\1\1\0\0\0x\0\0\0\0")
+        print(pcall(function() model.AttributesSerialize = "\1\0\0\0\1\0\0\0A\14\0\0\0\0" end))
+        print(pcall(NumberSequence.new, { NumberSequenceKeypoint.new(0, 1), NumberSequenceKeypoint.new(0.5, 1) }))
+    "#);
+    vm.step(1);
+    let output = vm.output();
+    assert_eq!(output[..9], [
+        "UDim\tUDim\ttrue", "UDim2\tUDim2\ttrue", "BrickColor\tBrickColor\ttrue", "Color3\tColor3\ttrue",
+        "NumberSequence\tNumberSequence\ttrue", "ColorSequence\tColorSequence\ttrue", "NumberRange\tNumberRange\ttrue",
+        "Rect\tRect\ttrue", "Font\tFont\ttrue"
+    ], "{:?}", output);
+    assert_eq!(output[9..13], ["true", "true", "true", "true"], "{:?}", output);
+    assert!(output[13].starts_with("false\t") && output[13].contains("invalid BrickColor 0"), "{:?}", output);
+    assert!(output[14].starts_with("false\t") && output[14].contains("must start at time 0 and end at time 1"), "{:?}", output);
+}